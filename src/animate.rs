@@ -0,0 +1,593 @@
+//! Animated transfer: a file shown as a looping sequence of QR codes
+//!
+//! The file is cut into equal blocks, one per frame, each behind a header
+//! naming the transfer, its length and the block's place in it. Frames past
+//! the data blocks carry fountain-coded blocks, random XOR combinations of
+//! the data blocks, so a receiver that misses frames still recovers the file
+//! from any set of frames a little larger than the block count.
+//!
+//! Frames are Base45 text after a `CQR:` prefix, which QR codes hold in
+//! alphanumeric mode at almost one byte per byte.
+
+#![allow(clippy::cast_possible_truncation)]
+#![allow(clippy::cast_precision_loss)]
+#![allow(clippy::cast_sign_loss)]
+
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write as _;
+use std::io::{Cursor, Write as _};
+use std::path::Path;
+use std::time::Duration;
+
+use image::codecs::gif::{GifDecoder, GifEncoder, Repeat};
+use image::codecs::png::PngDecoder;
+use image::imageops::overlay;
+use image::{AnimationDecoder, Delay, DynamicImage, ImageBuffer, RgbaImage};
+
+use crate::decoder;
+use crate::error::{QrError, Result};
+use crate::renderer::{create_qr_image, render_to_terminal, RenderConfig};
+use crate::terminal::TerminalGraphics;
+
+/// Text every frame starts with
+pub const PREFIX: &str = "CQR:";
+
+/// Version of the frame layout, first byte of every header
+const FORMAT_VERSION: u8 = 1;
+
+/// Header bytes: version, checksum, length, block size and frame index
+const HEADER_LEN: usize = 1 + 4 + 4 + 2 + 4;
+
+/// Most data blocks a transfer may have, which bounds the memory a frame
+/// header can make a receiver set aside
+pub const MAX_BLOCKS: usize = u16::MAX as usize;
+
+/// Base45 alphabet (RFC 9285), the characters of QR alphanumeric mode
+const BASE45: &[u8; 45] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ $%*+-./:";
+
+/// One frame of an animated transfer
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Frame {
+    /// CRC-32 of the whole file, which also tells transfers apart
+    pub checksum: u32,
+    /// Length of the file in bytes
+    pub length: u32,
+    /// Bytes per block; the last data block is padded with zeros
+    pub block_size: u16,
+    /// Data block number below the block count, fountain seed from there on
+    pub index: u32,
+    /// The data block, or the XOR of the blocks it combines
+    pub payload: Vec<u8>,
+}
+
+impl Frame {
+    /// Number of data blocks in the transfer
+    #[must_use]
+    pub fn block_count(&self) -> usize {
+        block_count(self.length as usize, self.block_size)
+    }
+
+    /// Data blocks combined into this frame's payload
+    #[must_use]
+    pub fn blocks(&self) -> Vec<usize> {
+        combination(self.checksum, self.index, self.block_count())
+    }
+
+    /// Text to encode in the frame's QR code
+    #[must_use]
+    pub fn to_text(&self) -> String {
+        let mut bytes = Vec::with_capacity(HEADER_LEN + self.payload.len());
+        bytes.push(FORMAT_VERSION);
+        bytes.extend_from_slice(&self.checksum.to_be_bytes());
+        bytes.extend_from_slice(&self.length.to_be_bytes());
+        bytes.extend_from_slice(&self.block_size.to_be_bytes());
+        bytes.extend_from_slice(&self.index.to_be_bytes());
+        bytes.extend_from_slice(&self.payload);
+        format!("{PREFIX}{}", base45_encode(&bytes))
+    }
+
+    /// Read a frame back from decoded QR text
+    pub fn parse(text: &str) -> Result<Self> {
+        let invalid =
+            |reason: &str| QrError::InvalidAnimation(format!("not a cqr frame: {reason}"));
+        let encoded = text
+            .strip_prefix(PREFIX)
+            .ok_or_else(|| invalid("missing the CQR: prefix"))?;
+        let bytes = base45_decode(encoded).ok_or_else(|| invalid("bad Base45 text"))?;
+        if bytes.len() < HEADER_LEN {
+            return Err(invalid("header cut short"));
+        }
+        if bytes[0] != FORMAT_VERSION {
+            return Err(invalid(&format!("unknown version {}", bytes[0])));
+        }
+        let u32_at = |at: usize| {
+            u32::from_be_bytes([bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]])
+        };
+        let frame = Self {
+            checksum: u32_at(1),
+            length: u32_at(5),
+            block_size: u16::from_be_bytes([bytes[9], bytes[10]]),
+            index: u32_at(11),
+            payload: bytes[HEADER_LEN..].to_vec(),
+        };
+        if frame.block_size == 0 || frame.payload.len() != usize::from(frame.block_size) {
+            return Err(invalid("payload doesn't match the block size"));
+        }
+        if frame.block_count() > MAX_BLOCKS {
+            return Err(invalid(&format!(
+                "{} blocks is more than {MAX_BLOCKS}",
+                frame.block_count()
+            )));
+        }
+        Ok(frame)
+    }
+}
+
+/// Cut `data` into frames of `block_size` bytes, followed by
+/// `redundancy` times as many fountain-coded frames (rounded up)
+pub fn frames(data: &[u8], block_size: u16, redundancy: f64) -> Result<Vec<Frame>> {
+    if block_size == 0 {
+        return Err(QrError::InvalidAnimation(
+            "chunk size must be at least 1 byte".to_string(),
+        ));
+    }
+    if !(redundancy.is_finite() && redundancy >= 0.0) {
+        return Err(QrError::InvalidAnimation(format!(
+            "redundancy must be 0 or more, not {redundancy}"
+        )));
+    }
+    let length = u32::try_from(data.len())
+        .map_err(|_| QrError::InvalidAnimation("files over 4 GiB can't be animated".to_string()))?;
+    let checksum = crc32(data);
+    let size = usize::from(block_size);
+    let count = block_count(data.len(), block_size);
+    if count > MAX_BLOCKS {
+        return Err(QrError::InvalidAnimation(format!(
+            "the file needs {count} frames, more than {MAX_BLOCKS}; use a larger chunk size"
+        )));
+    }
+    let blocks: Vec<Vec<u8>> = (0..count)
+        .map(|i| {
+            let mut block =
+                data[(i * size).min(data.len())..((i + 1) * size).min(data.len())].to_vec();
+            block.resize(size, 0);
+            block
+        })
+        .collect();
+
+    let repair = (count as f64 * redundancy).ceil() as usize;
+    Ok((0..count + repair)
+        .map(|index| {
+            let index = index as u32;
+            let mut payload = vec![0; size];
+            for block in combination(checksum, index, count) {
+                xor_into(&mut payload, &blocks[block]);
+            }
+            Frame {
+                checksum,
+                length,
+                block_size,
+                index,
+                payload,
+            }
+        })
+        .collect())
+}
+
+/// Collects frames of one transfer and solves for the file
+#[derive(Debug, Default)]
+pub struct Assembler {
+    /// Checksum, length and block size of the transfer being received
+    transfer: Option<(u32, u32, u16)>,
+    /// Data blocks in the transfer
+    blocks: usize,
+    /// Frame indexes already taken in
+    seen: HashSet<u32>,
+    /// Reduced equations by pivot block: which blocks they combine, and
+    /// their XOR
+    rows: HashMap<usize, (Vec<u64>, Vec<u8>)>,
+}
+
+impl Assembler {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Take in a frame. Returns false for one already seen or one of
+    /// another transfer, which is skipped; the first frame picks the
+    /// transfer.
+    pub fn push(&mut self, frame: &Frame) -> Result<bool> {
+        let transfer = (frame.checksum, frame.length, frame.block_size);
+        match self.transfer {
+            None => {
+                if frame.block_count() > MAX_BLOCKS {
+                    return Err(QrError::InvalidAnimation(format!(
+                        "a transfer of {} blocks is more than {MAX_BLOCKS}",
+                        frame.block_count()
+                    )));
+                }
+                self.transfer = Some(transfer);
+                self.blocks = frame.block_count();
+            }
+            Some(current) if current != transfer => return Ok(false),
+            Some(_) => {}
+        }
+        if !self.seen.insert(frame.index) {
+            return Ok(false);
+        }
+
+        let mut coefficients = vec![0u64; (self.blocks + 63) / 64];
+        for block in frame.blocks() {
+            coefficients[block / 64] |= 1 << (block % 64);
+        }
+        let mut data = frame.payload.clone();
+        while let Some(pivot) = lowest_bit(&coefficients) {
+            let Some((row, row_data)) = self.rows.get(&pivot) else {
+                self.rows.insert(pivot, (coefficients, data));
+                break;
+            };
+            for (word, other) in coefficients.iter_mut().zip(row) {
+                *word ^= other;
+            }
+            xor_into(&mut data, row_data);
+        }
+        Ok(true)
+    }
+
+    /// Frames taken in, duplicates excluded
+    #[must_use]
+    pub fn frames_seen(&self) -> usize {
+        self.seen.len()
+    }
+
+    /// Data blocks still to be solved for: at least this many more frames
+    /// are needed
+    #[must_use]
+    pub fn missing(&self) -> usize {
+        self.blocks - self.rows.len()
+    }
+
+    /// Whether every data block can be recovered
+    #[must_use]
+    pub fn is_complete(&self) -> bool {
+        self.transfer.is_some() && self.missing() == 0
+    }
+
+    /// Recover the file, checking it against the transfer's checksum
+    pub fn finish(self) -> Result<Vec<u8>> {
+        let Some((checksum, length, _)) = self.transfer else {
+            return Err(QrError::InvalidAnimation("no cqr frames found".to_string()));
+        };
+        if !self.is_complete() {
+            let missing = self.missing();
+            return Err(QrError::InvalidAnimation(format!(
+                "{missing} of {} blocks are still missing; capture at least {missing} more frame{}",
+                self.blocks,
+                if missing == 1 { "" } else { "s" }
+            )));
+        }
+
+        // Back-substitute from the last pivot, so each row is left with its
+        // own block alone
+        let mut rows: Vec<(Vec<u64>, Vec<u8>)> = (0..self.blocks)
+            .filter_map(|pivot| self.rows.get(&pivot).cloned())
+            .collect();
+        for pivot in (0..rows.len()).rev() {
+            let (done, rest) = rows.split_at_mut(pivot + 1);
+            let (coefficients, data) = &mut done[pivot];
+            for (offset, (_, solved)) in rest.iter().enumerate() {
+                let block = pivot + 1 + offset;
+                if coefficients[block / 64] & (1 << (block % 64)) != 0 {
+                    xor_into(data, solved);
+                }
+            }
+        }
+        let mut file: Vec<u8> = rows.into_iter().flat_map(|(_, data)| data).collect();
+        file.truncate(length as usize);
+        if crc32(&file) != checksum {
+            return Err(QrError::InvalidAnimation(
+                "the reassembled file fails its checksum".to_string(),
+            ));
+        }
+        Ok(file)
+    }
+}
+
+/// Render every frame as an animated GIF that loops forever
+pub fn encode_gif(texts: &[String], fps: f64, config: &RenderConfig) -> Result<Vec<u8>> {
+    let delay = frame_delay(fps)?;
+    let images = frame_images(texts, config)?;
+    let mut buffer = Vec::new();
+    {
+        // Two-color frames quantize quickly at the fastest speed
+        let mut encoder = GifEncoder::new_with_speed(&mut buffer, 30);
+        encoder.set_repeat(Repeat::Infinite)?;
+        encoder.encode_frames(images.into_iter().map(|img| {
+            image::Frame::from_parts(img, 0, 0, Delay::from_saturating_duration(delay))
+        }))?;
+    }
+    Ok(buffer)
+}
+
+/// Render every frame as an animated PNG (APNG) that loops forever
+pub fn encode_apng(texts: &[String], fps: f64, config: &RenderConfig) -> Result<Vec<u8>> {
+    let delay = frame_delay(fps)?;
+    let images = frame_images(texts, config)?;
+    let (width, height) = images.first().map_or((1, 1), ImageBuffer::dimensions);
+    let png_error = |e: png::EncodingError| QrError::ImageError(e.to_string());
+
+    let mut buffer = Vec::new();
+    let mut encoder = png::Encoder::new(&mut buffer, width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder
+        .set_animated(images.len() as u32, 0)
+        .map_err(png_error)?;
+    encoder
+        .set_frame_delay(delay.as_millis().min(u128::from(u16::MAX)) as u16, 1000)
+        .map_err(png_error)?;
+    let mut writer = encoder.write_header().map_err(png_error)?;
+    for img in &images {
+        writer.write_image_data(img.as_raw()).map_err(png_error)?;
+    }
+    writer.finish().map_err(png_error)?;
+    Ok(buffer)
+}
+
+/// Show the frames in the terminal one after another, redrawing in place.
+/// `loops` is the number of passes, 0 to play until interrupted. Frames are
+/// always Unicode blocks, whose height in lines is known.
+pub fn play_terminal(texts: &[String], fps: f64, loops: u32, config: &RenderConfig) -> Result<()> {
+    let delay = frame_delay(fps)?;
+    let config = RenderConfig {
+        terminal_graphics: TerminalGraphics::Unicode,
+        ..config.clone()
+    };
+    let rendered = texts
+        .iter()
+        .map(|text| render_to_terminal(text, &config))
+        .collect::<Result<Vec<_>>>()?;
+    let height = rendered.first().map_or(0, |frame| frame.lines().count()) + 1;
+
+    let mut stdout = std::io::stdout();
+    let mut first = true;
+    let mut pass = 0;
+    while loops == 0 || pass < loops {
+        for (i, frame) in rendered.iter().enumerate() {
+            let mut screen = String::new();
+            if !first {
+                // Back to the top of the previous frame
+                let _ = write!(screen, "\x1b[{height}A");
+            }
+            first = false;
+            let _ = writeln!(screen, "{frame}");
+            let _ = writeln!(screen, "Frame {} of {}\x1b[K", i + 1, rendered.len());
+            let _ = stdout.write_all(screen.as_bytes());
+            let _ = stdout.flush();
+            std::thread::sleep(delay);
+        }
+        pass += 1;
+    }
+    Ok(())
+}
+
+/// Decode every frame of an animated GIF or PNG and recover the file
+fn decode_animation(path: &Path) -> Result<(Vec<u8>, usize)> {
+    let bytes = std::fs::read(path).map_err(|e| QrError::FileRead {
+        path: path.to_path_buf(),
+        source: e,
+    })?;
+    let frames = if bytes.starts_with(b"GIF") {
+        GifDecoder::new(Cursor::new(bytes))?.into_frames()
+    } else {
+        PngDecoder::new(Cursor::new(bytes))?.apng().into_frames()
+    };
+    let mut transfers = Transfers::default();
+    for frame in frames {
+        let img = DynamicImage::ImageRgba8(frame?.into_buffer());
+        if let Ok(decoded) = decoder::decode_image(&img) {
+            if let Ok(frame) = Frame::parse(&decoded.content) {
+                transfers.push(&frame)?;
+            }
+        }
+        if transfers.is_complete() {
+            break;
+        }
+    }
+    transfers.finish()
+}
+
+/// Recover a file from a directory of captured frame images, or from an
+/// animated GIF or PNG.
+///
+/// Images without a cqr frame are skipped, and so are frames of other
+/// transfers. Returns the file and the number of distinct frames read.
+pub fn reassemble(input: &Path) -> Result<(Vec<u8>, usize)> {
+    if input.is_file() {
+        return decode_animation(input);
+    }
+    let mut paths: Vec<_> = std::fs::read_dir(input)
+        .map_err(|e| QrError::FileRead {
+            path: input.to_path_buf(),
+            source: e,
+        })?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.is_file())
+        .collect();
+    paths.sort();
+
+    let mut transfers = Transfers::default();
+    for path in paths {
+        let Ok(results) = decoder::decode_all(&path.to_string_lossy()) else {
+            continue;
+        };
+        for result in results {
+            if let Ok(frame) = Frame::parse(&result.content) {
+                transfers.push(&frame)?;
+            }
+        }
+    }
+    transfers.finish()
+}
+
+/// Frames sorted by the transfer they belong to, so stray captures of
+/// another transfer don't spoil the one being received
+#[derive(Default)]
+struct Transfers {
+    assemblers: HashMap<(u32, u32, u16), Assembler>,
+}
+
+impl Transfers {
+    fn push(&mut self, frame: &Frame) -> Result<bool> {
+        self.assemblers
+            .entry((frame.checksum, frame.length, frame.block_size))
+            .or_default()
+            .push(frame)
+    }
+
+    fn is_complete(&self) -> bool {
+        self.assemblers.values().any(Assembler::is_complete)
+    }
+
+    /// Recover the complete transfer, or else report on the one with the
+    /// most frames
+    fn finish(self) -> Result<(Vec<u8>, usize)> {
+        let best = self
+            .assemblers
+            .into_values()
+            .max_by_key(|assembler| (assembler.is_complete(), assembler.frames_seen()));
+        let assembler = best.unwrap_or_default();
+        let frames = assembler.frames_seen();
+        Ok((assembler.finish()?, frames))
+    }
+}
+
+/// Time each frame stays up
+fn frame_delay(fps: f64) -> Result<Duration> {
+    if fps.is_finite() && fps > 0.0 && fps <= 100.0 {
+        Ok(Duration::from_secs_f64(1.0 / fps))
+    } else {
+        Err(QrError::InvalidAnimation(format!(
+            "frame rate must be above 0 and at most 100 fps, not {fps}"
+        )))
+    }
+}
+
+/// Render each frame, centred on a canvas the size of the largest
+fn frame_images(texts: &[String], config: &RenderConfig) -> Result<Vec<RgbaImage>> {
+    let images = texts
+        .iter()
+        .map(|text| create_qr_image(text, config))
+        .collect::<Result<Vec<_>>>()?;
+    let width = images.iter().map(ImageBuffer::width).max().unwrap_or(1);
+    let height = images.iter().map(ImageBuffer::height).max().unwrap_or(1);
+    Ok(images
+        .into_iter()
+        .map(|img| {
+            if img.dimensions() == (width, height) {
+                return img;
+            }
+            let mut canvas = ImageBuffer::from_pixel(width, height, config.bg_color.to_rgba());
+            let x = (width - img.width()) / 2;
+            let y = (height - img.height()) / 2;
+            overlay(&mut canvas, &img, i64::from(x), i64::from(y));
+            canvas
+        })
+        .collect())
+}
+
+/// Data blocks in a file of `length` bytes; an empty file still has one
+fn block_count(length: usize, block_size: u16) -> usize {
+    let size = usize::from(block_size).max(1);
+    ((length + size - 1) / size).max(1)
+}
+
+/// Data blocks combined in frame `index`: the block itself below `count`,
+/// past it a random half of the blocks drawn from the checksum and index.
+/// Fountain frames take turns to always include each block, so even a short
+/// run of them covers every block.
+fn combination(checksum: u32, index: u32, count: usize) -> Vec<usize> {
+    let index = index as usize;
+    if index < count {
+        return vec![index];
+    }
+    let mut state = (u64::from(checksum) << 32) | index as u64;
+    let mut blocks = Vec::new();
+    for start in (0..count).step_by(64) {
+        let bits = splitmix64(&mut state);
+        blocks.extend(
+            (start..count.min(start + 64))
+                .filter(|&block| block == index % count || bits & (1 << (block - start)) != 0),
+        );
+    }
+    blocks
+}
+
+/// `SplitMix64` step: a small, fast generator that sender and receiver can
+/// both run from the same seed
+fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+/// Lowest set bit of a bit set
+fn lowest_bit(words: &[u64]) -> Option<usize> {
+    words
+        .iter()
+        .enumerate()
+        .find(|(_, word)| **word != 0)
+        .map(|(i, word)| i * 64 + word.trailing_zeros() as usize)
+}
+
+fn xor_into(target: &mut [u8], source: &[u8]) {
+    for (byte, other) in target.iter_mut().zip(source) {
+        *byte ^= other;
+    }
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = flate2::Crc::new();
+    crc.update(data);
+    crc.sum()
+}
+
+/// Base45 (RFC 9285): two bytes to three characters, a last odd byte to two
+fn base45_encode(bytes: &[u8]) -> String {
+    let mut text = String::with_capacity(bytes.len() / 2 * 3 + 2);
+    for pair in bytes.chunks(2) {
+        let (mut value, digits) = match *pair {
+            [a, b] => (usize::from(a) * 256 + usize::from(b), 3),
+            [a] => (usize::from(a), 2),
+            _ => unreachable!("chunks of two"),
+        };
+        for _ in 0..digits {
+            text.push(char::from(BASE45[value % 45]));
+            value /= 45;
+        }
+    }
+    text
+}
+
+fn base45_decode(text: &str) -> Option<Vec<u8>> {
+    let digits: Vec<usize> = text
+        .bytes()
+        .map(|c| BASE45.iter().position(|&d| d == c))
+        .collect::<Option<_>>()?;
+    let mut bytes = Vec::with_capacity(digits.len() / 3 * 2 + 1);
+    for group in digits.chunks(3) {
+        match *group {
+            [c, d, e] => {
+                let value = c + d * 45 + e * 45 * 45;
+                let pair = u16::try_from(value).ok()?;
+                bytes.extend_from_slice(&pair.to_be_bytes());
+            }
+            [c, d] => bytes.push(u8::try_from(c + d * 45).ok()?),
+            _ => return None,
+        }
+    }
+    Some(bytes)
+}
@@ -0,0 +1,85 @@
+//! Data Matrix, Aztec, PDF417 and linear barcodes, encoded with rxing
+
+use std::collections::HashMap;
+
+use qrcode::EcLevel;
+use rxing::{BarcodeFormat, EncodeHintType, EncodeHintValue, MultiFormatWriter, Writer};
+
+use crate::error::{QrError, Result};
+use crate::shapes::ModuleGrid;
+
+/// Bar height of EAN-13 and UPC-A at the nominal 0.33mm module: 22.85mm
+const EAN_BAR_HEIGHT: usize = 69;
+
+/// Bars of other linear barcodes are 15% of the symbol width, and never
+/// shorter than this many modules
+const MIN_BAR_HEIGHT: usize = 24;
+
+/// Encode `data` as the smallest symbol of `format` that holds it.
+///
+/// Linear barcodes come out one row tall from rxing; their bars are drawn
+/// down to the standard height here. `symbology` names the format in errors.
+pub fn encode(
+    data: &str,
+    format: BarcodeFormat,
+    symbology: &str,
+    ec_level: EcLevel,
+) -> Result<ModuleGrid> {
+    let mut hints = HashMap::new();
+    hints.insert(EncodeHintType::MARGIN, EncodeHintValue::Margin("0".into()));
+    hints.insert(
+        EncodeHintType::CHARACTER_SET,
+        EncodeHintValue::CharacterSet("UTF-8".into()),
+    );
+    if let Some(level) = error_correction(format, ec_level) {
+        hints.insert(
+            EncodeHintType::ERROR_CORRECTION,
+            EncodeHintValue::ErrorCorrection(level.to_string()),
+        );
+    }
+
+    let matrix = MultiFormatWriter
+        .encode_with_hints(data, &format, 0, 0, &hints)
+        .map_err(|e| {
+            // Drop rxing's exception name, as in "IllegalArgumentException - ..."
+            let message = e.to_string();
+            QrError::BarcodeGeneration {
+                symbology: symbology.to_string(),
+                message: message
+                    .split_once(" - ")
+                    .map_or_else(|| message.clone(), |(_, text)| text.to_string()),
+            }
+        })?;
+    let width = matrix.getWidth() as usize;
+    let rows = matrix.getHeight() as usize;
+    let dark: Vec<bool> = (0..matrix.getHeight())
+        .flat_map(|y| (0..matrix.getWidth()).map(move |x| (x, y)))
+        .map(|(x, y)| matrix.get(x, y))
+        .collect();
+
+    if rows > 1 {
+        return Ok(ModuleGrid::from_modules(width, rows, dark, &[]));
+    }
+    let height = match format {
+        BarcodeFormat::EAN_13 | BarcodeFormat::UPC_A => EAN_BAR_HEIGHT,
+        _ => (width * 3 / 20).max(MIN_BAR_HEIGHT),
+    };
+    let bars = dark.repeat(height);
+    Ok(ModuleGrid::from_modules(width, height, bars, &[]))
+}
+
+/// rxing's error correction setting for the formats that take one: the
+/// minimum share of check words for Aztec, the security level for PDF417
+const fn error_correction(format: BarcodeFormat, ec_level: EcLevel) -> Option<u32> {
+    match (format, ec_level) {
+        (BarcodeFormat::AZTEC, EcLevel::L) => Some(10),
+        (BarcodeFormat::AZTEC, EcLevel::M) => Some(23),
+        (BarcodeFormat::AZTEC, EcLevel::Q) => Some(36),
+        (BarcodeFormat::AZTEC, EcLevel::H) => Some(50),
+        (BarcodeFormat::PDF_417, EcLevel::L) => Some(1),
+        (BarcodeFormat::PDF_417, EcLevel::M) => Some(2),
+        (BarcodeFormat::PDF_417, EcLevel::Q) => Some(4),
+        (BarcodeFormat::PDF_417, EcLevel::H) => Some(6),
+        _ => None,
+    }
+}
@@ -1,8 +1,13 @@
 //! Batch QR code generation from CSV/JSON files
 
 use crate::cli::OutputFormat;
+use crate::embed::render_to_html_page;
+use crate::eps::render_to_eps_file;
 use crate::error::{QrError, Result};
-use crate::renderer::{render_to_png, render_to_svg_file, RenderConfig};
+use crate::matrix::render_matrix;
+use crate::pdf::render_to_pdf_file;
+use crate::renderer::{render_to_image, render_to_svg_file, RenderConfig};
+use crate::verify::{check_verifiable, verify_output};
 use indicatif::{ProgressBar, ProgressStyle};
 use serde::Deserialize;
 use std::fs;
@@ -15,15 +20,57 @@ pub struct BatchRecord {
     pub filename: String,
     /// Data to encode in QR code
     pub data: String,
+    /// Caption for this record, overriding `--caption` (optional column)
+    #[serde(default)]
+    pub caption: Option<String>,
 }
 
-/// Process a batch file and generate QR codes
+/// Verification outcome of one record
+#[derive(Debug)]
+pub struct RecordCheck {
+    pub filename: String,
+    /// Why the code didn't read back, or `None` if it did
+    pub error: Option<String>,
+}
+
+/// Outcome of a batch run
+#[derive(Debug, Default)]
+pub struct BatchReport {
+    /// Number of codes written
+    pub generated: usize,
+    /// One entry per record in input order; empty unless verifying
+    pub checks: Vec<RecordCheck>,
+}
+
+impl BatchReport {
+    /// Records that failed verification
+    pub fn failures(&self) -> impl Iterator<Item = &RecordCheck> {
+        self.checks.iter().filter(|check| check.error.is_some())
+    }
+}
+
+/// Process a batch file and generate QR codes.
+///
+/// With `verify`, every written code is decoded and compared with its data;
+/// failures are collected in the report instead of stopping the batch.
 pub fn process_batch(
     input_path: &PathBuf,
     output_dir: &PathBuf,
     format: &OutputFormat,
     config: &RenderConfig,
-) -> Result<usize> {
+    verify: bool,
+) -> Result<BatchReport> {
+    // Printed output can't be written per record; fall back to PNG files
+    let format = if format.is_printed() {
+        OutputFormat::Png
+    } else {
+        *format
+    };
+
+    if verify {
+        check_verifiable(format)?;
+    }
+
     // Create output directory if it doesn't exist
     fs::create_dir_all(output_dir).map_err(|e| QrError::FileWrite {
         path: output_dir.clone(),
@@ -44,28 +91,58 @@ pub fn process_batch(
     );
 
     // Generate QR codes
+    let mut report = BatchReport::default();
     for record in &records {
-        let extension = match format {
-            OutputFormat::Png | OutputFormat::Terminal | OutputFormat::Base64 => "png",
-            OutputFormat::Svg => "svg",
+        let output_path = output_dir.join(format!("{}.{}", record.filename, format.extension()));
+        let config = &RenderConfig {
+            caption: record.caption.clone().or_else(|| config.caption.clone()),
+            ..config.clone()
         };
 
-        let output_path = output_dir.join(format!("{}.{}", record.filename, extension));
-
         match format {
-            OutputFormat::Png | OutputFormat::Terminal | OutputFormat::Base64 => {
-                render_to_png(&record.data, &output_path, config)?;
-            }
             OutputFormat::Svg => {
                 render_to_svg_file(&record.data, &output_path, config)?;
             }
+            OutputFormat::Eps => {
+                render_to_eps_file(&record.data, &output_path, config)?;
+            }
+            OutputFormat::Pdf => {
+                render_to_pdf_file(&record.data, &output_path, config)?;
+            }
+            OutputFormat::HtmlPage => {
+                let page = render_to_html_page(&record.data, config)?;
+                fs::write(&output_path, page).map_err(|e| QrError::FileWrite {
+                    path: output_path.clone(),
+                    source: e,
+                })?;
+            }
+            matrix if matrix.is_matrix() => {
+                let bytes = render_matrix(&record.data, matrix, config)?;
+                fs::write(&output_path, bytes).map_err(|e| QrError::FileWrite {
+                    path: output_path.clone(),
+                    source: e,
+                })?;
+            }
+            raster => {
+                let image_format = raster.image_format().expect("raster output format");
+                render_to_image(&record.data, &output_path, image_format, config)?;
+            }
         }
 
+        if verify {
+            report.checks.push(RecordCheck {
+                filename: record.filename.clone(),
+                error: verify_output(&record.data, &output_path, format, config)
+                    .err()
+                    .map(|e| e.to_string()),
+            });
+        }
+        report.generated += 1;
         pb.inc(1);
     }
 
     pb.finish_with_message("Done!");
-    Ok(total)
+    Ok(report)
 }
 
 /// Read batch records from CSV or JSON file
@@ -1,7 +1,18 @@
 //! CLI argument parsing and command definitions
 
 use clap::{Parser, Subcommand, ValueEnum};
-use std::path::PathBuf;
+use image::ImageFormat;
+use std::path::{Path, PathBuf};
+
+use crate::encoding::EncodingMode;
+use crate::frame::{CaptionPosition, FrameStyle};
+use crate::logo::LogoKnockout;
+use crate::matrix::{BitOrder, RowPadding};
+use crate::pdf::PageSize;
+use crate::renderer::{SizeMode, SvgMode, MM_PER_INCH};
+use crate::shapes::{EyeStyle, ModuleStyle};
+use crate::symbol::SymbolType;
+use crate::terminal::{TerminalColor, TerminalGlyphs, TerminalGraphics, TerminalPolarity};
 
 #[derive(Parser)]
 #[command(name = "cqr")]
@@ -15,7 +26,9 @@ use std::path::PathBuf;
   cqr url 'https://example.com' -o link.png
   cqr text 'Hello World' --format terminal
   cqr decode image.png
+  cqr animate backup.tar.gz --redundancy 0.5 -o transfer.gif
   cqr batch --input data.csv --output-dir ./codes/")]
+#[allow(clippy::struct_excessive_bools)]
 pub struct Cli {
     #[command(subcommand)]
     pub command: Option<Commands>,
@@ -28,34 +41,205 @@ pub struct Cli {
     #[arg(short, long, default_value = "512", global = true)]
     pub size: u32,
 
-    /// Output format
-    #[arg(short = 'F', long, default_value = "png", value_enum, global = true)]
-    pub format: OutputFormat,
+    /// Pixels per module; the image size follows from the code (overrides --size)
+    #[arg(long, global = true)]
+    pub module_size: Option<u32>,
+
+    /// How raster output meets --size: fit (may be smaller), pad or scale (exact size)
+    #[arg(long, default_value = "fit", value_enum, global = true)]
+    pub size_mode: SizeMode,
+
+    /// SVG markup: legacy (one element per module) or compact (merged paths)
+    #[arg(long, default_value = "legacy", value_enum, global = true)]
+    pub svg_mode: SvgMode,
+
+    /// Draw terminal output with Unicode blocks or an inline image protocol; detected from the terminal by default
+    #[arg(long, value_enum, global = true)]
+    pub terminal_graphics: Option<TerminalGraphics>,
+
+    /// Characters of text terminal output: half-block, full-block, braille or ascii
+    #[arg(long, default_value = "half-block", value_enum, global = true)]
+    pub terminal_glyphs: TerminalGlyphs,
+
+    /// Color text terminal output with --fg-color and --bg-color (ansi256 or truecolor)
+    #[arg(long, default_value = "none", value_enum, global = true)]
+    pub terminal_color: TerminalColor,
+
+    /// Draw dark modules (normal) or light modules (inverted) as ink in text terminal output
+    #[arg(long, default_value = "auto", value_enum, global = true)]
+    pub terminal_polarity: TerminalPolarity,
+
+    /// Output format (detected from the --output extension when omitted, else png)
+    #[arg(short = 'F', long, value_enum, global = true)]
+    pub format: Option<OutputFormat>,
+
+    /// JPEG quality from 1 (smallest) to 100 (best)
+    #[arg(long, default_value = "90", value_parser = clap::value_parser!(u8).range(1..=100), global = true)]
+    pub jpeg_quality: u8,
+
+    /// Error correction level (higher = more resilient but larger); defaults to m, or h with --logo
+    #[arg(short = 'e', long, value_enum, global = true)]
+    pub error_correction: Option<ErrorCorrectionLevel>,
+
+    /// Symbol type: qr, micro (M1-M4), rmqr (rectangular), or another 2D or linear barcode
+    #[arg(
+        long,
+        visible_alias = "symbology",
+        default_value = "qr",
+        value_enum,
+        global = true
+    )]
+    pub symbol: SymbolType,
+
+    /// Smallest QR version to use (1-40); larger data still grows the code
+    #[arg(long, value_parser = clap::value_parser!(i16).range(1..=40), global = true)]
+    pub qr_version: Option<i16>,
+
+    /// Use exactly the --qr-version given, failing when the data doesn't fit
+    #[arg(long, requires = "qr_version", global = true)]
+    pub exact_version: bool,
+
+    /// Force a QR mask pattern (0-7) instead of the one with the lowest penalty
+    #[arg(long, value_parser = clap::value_parser!(u8).range(0..=7), global = true)]
+    pub mask: Option<u8>,
+
+    /// Segment mode for the data: an optimized mix, or one mode for all of it
+    #[arg(long, default_value = "optimized", value_enum, global = true)]
+    pub encoding_mode: EncodingMode,
+
+    /// Split data too long for one QR code across up to 16 linked codes (Structured Append)
+    #[arg(long, global = true)]
+    pub structured_append: bool,
 
-    /// Error correction level (higher = more resilient but larger)
-    #[arg(short = 'e', long, default_value = "m", value_enum, global = true)]
-    pub error_correction: ErrorCorrectionLevel,
+    /// Put every code of a Structured Append set on one image instead of numbered files
+    #[arg(long, requires = "structured_append", global = true)]
+    pub sheet: bool,
 
-    /// Foreground color in hex format (e.g., #000000 or #000)
+    /// Foreground color in hex format (e.g., #000000, #000 or #000000CC with alpha)
     #[arg(long, default_value = "#000000", global = true)]
     pub fg_color: String,
 
-    /// Background color in hex format (e.g., #FFFFFF or #FFF)
+    /// Background color in hex format (e.g., #FFFFFF or #FFF), or `transparent`
     #[arg(long, default_value = "#FFFFFF", global = true)]
     pub bg_color: String,
 
-    /// Quiet zone size in modules (border around QR code)
-    #[arg(long, default_value = "2", global = true)]
-    pub quiet_zone: u32,
+    /// Quiet zone size in modules (border around QR code); defaults to 2, or 11 for linear barcodes
+    #[arg(long, global = true)]
+    pub quiet_zone: Option<u32>,
 
-    /// Logo image path to overlay (PNG/JPG/etc) - forces high error correction
+    /// Keep the --quiet-zone border in matrix output (json, csv, txt, pbm, pgm)
+    #[arg(long, global = true)]
+    pub matrix_quiet_zone: bool,
+
+    /// Bit of each packed byte that holds the leftmost module, for c-header, rust and python output
+    #[arg(long, value_enum, default_value = "msb", global = true)]
+    pub bit_order: BitOrder,
+
+    /// Start each row of a packed bitmap on a new byte, or run rows on
+    #[arg(long, value_enum, default_value = "byte", global = true)]
+    pub row_padding: RowPadding,
+
+    /// Name of the bitmap in c-header, rust and python output (not a keyword of any of them);
+    /// constants use it in capitals
+    #[arg(long, value_parser = parse_identifier, default_value = "qr", global = true)]
+    pub array_name: String,
+
+    /// Logo image path to overlay (PNG/JPG/SVG/etc) - defaults to high error correction
     #[arg(long, global = true)]
     pub logo: Option<PathBuf>,
 
-    /// Gradient color (to) in hex format - creates a gradient from `fg_color`
+    /// Logo width as a fraction of the code width, quiet zone included
+    #[arg(long, default_value = "0.2", global = true)]
+    pub logo_scale: f64,
+
+    /// Background-colored area behind the logo
+    #[arg(long, default_value = "none", value_enum, global = true)]
+    pub logo_knockout: LogoKnockout,
+
+    /// Space between the logo and the edge of its knockout, in modules
+    #[arg(long, default_value = "1", global = true)]
+    pub logo_padding: f64,
+
+    /// Turn every module the logo touches light instead of covering it part-way
     #[arg(long, global = true)]
+    pub logo_clear_modules: bool,
+
+    /// Gradient color (to) in hex format - creates a gradient from `fg_color`
+    #[arg(long, global = true, conflicts_with = "gradient")]
     pub gradient_color: Option<String>,
 
+    /// Gradient spec, e.g. "linear:45:#f00@0,#00f@1" or "radial:#000,#1a237e"
+    #[arg(long, global = true)]
+    pub gradient: Option<String>,
+
+    /// Shape of the data modules (finder patterns use --eye-outer-style/--eye-inner-style)
+    #[arg(long, default_value = "square", value_enum, global = true)]
+    pub module_style: ModuleStyle,
+
+    /// Shape of the finder pattern outer rings
+    #[arg(long, default_value = "square", value_enum, global = true)]
+    pub eye_outer_style: EyeStyle,
+
+    /// Shape of the finder pattern pupils
+    #[arg(long, default_value = "square", value_enum, global = true)]
+    pub eye_inner_style: EyeStyle,
+
+    /// Finder pattern outer ring color in hex format (defaults to the module color)
+    #[arg(long, global = true)]
+    pub eye_outer_color: Option<String>,
+
+    /// Finder pattern pupil color in hex format (defaults to the module color)
+    #[arg(long, global = true)]
+    pub eye_inner_color: Option<String>,
+
+    /// Printed size of the code, quiet zone included: e.g. 25mm, 2.5cm, 1in (plain numbers are mm)
+    #[arg(long, value_parser = parse_print_size, global = true)]
+    pub print_size: Option<f64>,
+
+    /// Print resolution; with --print-size it sets the pixel size and is stored in PNG/JPEG files
+    #[arg(long, value_parser = clap::value_parser!(u16).range(1..), global = true)]
+    pub dpi: Option<u16>,
+
+    /// Page size for PDF output
+    #[arg(long, default_value = "fit", value_enum, global = true)]
+    pub page_size: PageSize,
+
+    /// Page margin in millimetres for PDF output
+    #[arg(long, default_value = "10", global = true)]
+    pub page_margin: f64,
+
+    /// Alt text of HTML and Markdown output (describes the data by default)
+    #[arg(long, global = true)]
+    pub alt: Option<String>,
+
+    /// Caption shown with the code; {data} is replaced by the encoded data
+    #[arg(long, global = true)]
+    pub caption: Option<String>,
+
+    /// Put the caption above or below the code
+    #[arg(long, default_value = "below", value_enum, global = true)]
+    pub caption_position: CaptionPosition,
+
+    /// Call-to-action banner text under the code, e.g. "SCAN ME"
+    #[arg(long, global = true)]
+    pub banner: Option<String>,
+
+    /// Border around the code and caption
+    #[arg(long, default_value = "none", value_enum, global = true)]
+    pub frame: FrameStyle,
+
+    /// Frame and banner color in hex format (defaults to the foreground color)
+    #[arg(long, global = true)]
+    pub frame_color: Option<String>,
+
+    /// Decode every generated code and fail if it doesn't read back as the input
+    #[arg(long, global = true)]
+    pub verify: bool,
+
+    /// Treat color and print warnings as errors
+    #[arg(long, global = true)]
+    pub strict: bool,
+
     /// Verbose output
     #[arg(short, long, global = true)]
     pub verbose: bool,
@@ -65,16 +249,253 @@ pub struct Cli {
     pub quiet: bool,
 }
 
-#[derive(Clone, ValueEnum, Debug, PartialEq, Eq)]
+impl Cli {
+    /// Output format: the explicit `--format`, else the `--output` extension, else PNG
+    #[must_use]
+    pub fn output_format(&self) -> OutputFormat {
+        self.format
+            .or_else(|| OutputFormat::from_extension(&self.output))
+            .unwrap_or(OutputFormat::Png)
+    }
+}
+
+/// Parse a physical length such as `25mm`, `2.5cm`, `1in` or `72pt` into millimetres
+pub fn parse_print_size(value: &str) -> Result<f64, String> {
+    let value = value.trim().to_lowercase();
+    let split = value
+        .find(|c: char| c.is_ascii_alphabetic())
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let number: f64 = number
+        .trim()
+        .parse()
+        .map_err(|_| format!("'{value}' is not a length like 25mm or 1in"))?;
+    let mm_per_unit = match unit {
+        "" | "mm" => 1.0,
+        "cm" => 10.0,
+        "in" => MM_PER_INCH,
+        "pt" => MM_PER_INCH / 72.0,
+        _ => return Err(format!("unknown unit '{unit}', use mm, cm, in or pt")),
+    };
+    if number.is_finite() && number > 0.0 {
+        Ok(number * mm_per_unit)
+    } else {
+        Err(format!("'{value}' must be a positive length"))
+    }
+}
+
+/// Reserved words of C (through C23)
+const C_KEYWORDS: &str =
+    "alignas alignof auto bool break case char const constexpr continue default do \
+     double else enum extern false float for goto if inline int long nullptr register \
+     restrict return short signed sizeof static static_assert struct switch \
+     thread_local true typedef typeof typeof_unqual union unsigned void volatile \
+     while _Alignas _Alignof _Atomic _BitInt _Bool _Complex _Decimal128 _Decimal32 \
+     _Decimal64 _Generic _Imaginary _Noreturn _Static_assert _Thread_local";
+
+/// Strict and reserved keywords of Rust
+const RUST_KEYWORDS: &str =
+    "_ abstract as async await become box break const continue crate do dyn else enum \
+     extern false final fn for gen if impl in let loop macro match mod move mut \
+     override priv pub ref return self Self static struct super trait true try type \
+     typeof unsafe unsized use virtual where while yield";
+
+/// Keywords of Python 3
+const PYTHON_KEYWORDS: &str =
+    "False None True and as assert async await break class continue def del elif else \
+     except finally for from global if import in is lambda nonlocal not or pass raise \
+     return try while with yield";
+
+/// Check that a name can be used as a C, Rust and Python identifier
+pub fn parse_identifier(value: &str) -> Result<String, String> {
+    let mut chars = value.chars();
+    let valid = chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !valid {
+        return Err(format!(
+            "'{value}' is not an identifier: use letters, digits and _, not starting with a digit"
+        ));
+    }
+    let reserved = [
+        ("C", C_KEYWORDS),
+        ("Rust", RUST_KEYWORDS),
+        ("Python", PYTHON_KEYWORDS),
+    ]
+    .into_iter()
+    .find(|(_, keywords)| keywords.split_whitespace().any(|keyword| keyword == value));
+    match reserved {
+        Some((language, _)) => Err(format!("'{value}' is a {language} keyword")),
+        None => Ok(value.to_string()),
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum, Debug, PartialEq, Eq)]
 pub enum OutputFormat {
     /// PNG image file
     Png,
+    /// JPEG image file (no transparency, see --jpeg-quality)
+    Jpeg,
+    /// Lossless WebP image file
+    Webp,
+    /// GIF image file
+    Gif,
+    /// BMP image file
+    Bmp,
+    /// TIFF image file
+    Tiff,
     /// SVG vector file
     Svg,
+    /// Encapsulated PostScript vector file
+    Eps,
+    /// PDF document with vector paths (print-ready)
+    Pdf,
     /// Print to terminal using Unicode
     Terminal,
     /// Base64 encoded PNG (for embedding)
     Base64,
+    /// HTML <img> tag with a PNG data URI, size and alt text
+    Html,
+    /// HTML fragment with the code as inline SVG
+    HtmlSvg,
+    /// Standalone HTML page file
+    HtmlPage,
+    /// Markdown image with a PNG data URI and alt text
+    Markdown,
+    /// Module matrix as JSON, with version and error correction
+    MatrixJson,
+    /// Module matrix as CSV rows of 0 and 1
+    MatrixCsv,
+    /// Module matrix as rows of 0 and 1
+    MatrixText,
+    /// Module matrix as a netpbm bitmap, one pixel per module
+    Pbm,
+    /// Module matrix as a netpbm graymap, one pixel per module
+    Pgm,
+    /// C header with the packed module bitmap
+    CHeader,
+    /// Rust constants with the packed module bitmap
+    Rust,
+    /// Python list of the packed module bitmap
+    Python,
+}
+
+impl OutputFormat {
+    /// Guess the format from a file extension, case-insensitively
+    #[must_use]
+    pub fn from_extension(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_lowercase();
+        match extension.as_str() {
+            "png" => Some(Self::Png),
+            "jpg" | "jpeg" => Some(Self::Jpeg),
+            "webp" => Some(Self::Webp),
+            "gif" => Some(Self::Gif),
+            "bmp" => Some(Self::Bmp),
+            "tif" | "tiff" => Some(Self::Tiff),
+            "svg" => Some(Self::Svg),
+            "eps" => Some(Self::Eps),
+            "pdf" => Some(Self::Pdf),
+            "html" | "htm" => Some(Self::HtmlPage),
+            "json" => Some(Self::MatrixJson),
+            "csv" => Some(Self::MatrixCsv),
+            "txt" => Some(Self::MatrixText),
+            "pbm" => Some(Self::Pbm),
+            "pgm" => Some(Self::Pgm),
+            "h" => Some(Self::CHeader),
+            "rs" => Some(Self::Rust),
+            "py" => Some(Self::Python),
+            _ => None,
+        }
+    }
+
+    /// File extension for files written in this format.
+    ///
+    /// Printed output (terminal, base64 and the snippets) is a PNG image
+    /// when written to disk.
+    #[must_use]
+    pub const fn extension(self) -> &'static str {
+        match self {
+            Self::Png
+            | Self::Terminal
+            | Self::Base64
+            | Self::Html
+            | Self::HtmlSvg
+            | Self::Markdown => "png",
+            Self::Jpeg => "jpg",
+            Self::Webp => "webp",
+            Self::Gif => "gif",
+            Self::Bmp => "bmp",
+            Self::Tiff => "tiff",
+            Self::Svg => "svg",
+            Self::Eps => "eps",
+            Self::Pdf => "pdf",
+            Self::HtmlPage => "html",
+            Self::MatrixJson => "json",
+            Self::MatrixCsv => "csv",
+            Self::MatrixText => "txt",
+            Self::Pbm => "pbm",
+            Self::Pgm => "pgm",
+            Self::CHeader => "h",
+            Self::Rust => "rs",
+            Self::Python => "py",
+        }
+    }
+
+    /// Whether the output is printed rather than written to `--output`
+    #[must_use]
+    pub const fn is_printed(self) -> bool {
+        matches!(
+            self,
+            Self::Terminal | Self::Base64 | Self::Html | Self::HtmlSvg | Self::Markdown
+        )
+    }
+
+    /// Raster encoder for image file formats, `None` for everything else
+    #[must_use]
+    pub const fn image_format(self) -> Option<ImageFormat> {
+        match self {
+            Self::Png => Some(ImageFormat::Png),
+            Self::Jpeg => Some(ImageFormat::Jpeg),
+            Self::Webp => Some(ImageFormat::WebP),
+            Self::Gif => Some(ImageFormat::Gif),
+            Self::Bmp => Some(ImageFormat::Bmp),
+            Self::Tiff => Some(ImageFormat::Tiff),
+            Self::Svg
+            | Self::Eps
+            | Self::Pdf
+            | Self::Terminal
+            | Self::Base64
+            | Self::Html
+            | Self::HtmlSvg
+            | Self::HtmlPage
+            | Self::Markdown
+            | Self::MatrixJson
+            | Self::MatrixCsv
+            | Self::MatrixText
+            | Self::Pbm
+            | Self::Pgm
+            | Self::CHeader
+            | Self::Rust
+            | Self::Python => None,
+        }
+    }
+
+    /// Whether the output is the bare module matrix
+    #[must_use]
+    pub const fn is_matrix(self) -> bool {
+        matches!(
+            self,
+            Self::MatrixJson
+                | Self::MatrixCsv
+                | Self::MatrixText
+                | Self::Pbm
+                | Self::Pgm
+                | Self::CHeader
+                | Self::Rust
+                | Self::Python
+        )
+    }
 }
 
 #[derive(Clone, ValueEnum, Debug)]
@@ -266,15 +687,53 @@ pub enum Commands {
 
     /// Decode QR code from an image file or URL
     Decode {
-        /// Path to image file or URL
-        #[arg()]
-        input: String,
+        /// Paths to image files or URLs; linked Structured Append codes are
+        /// put back together
+        #[arg(required = true)]
+        inputs: Vec<String>,
 
         /// Output decoded data as JSON
         #[arg(long)]
         json: bool,
     },
 
+    /// Show a file as a looping sequence of QR codes: an animated GIF or PNG,
+    /// or a terminal slideshow
+    Animate {
+        /// File to send, or - for standard input
+        #[arg()]
+        input: PathBuf,
+
+        /// Bytes of the file per frame
+        #[arg(long, default_value = "256", value_parser = clap::value_parser!(u16).range(1..=2800))]
+        chunk_size: u16,
+
+        /// Extra fountain-coded frames, as a share of the data frames (0.5 = half
+        /// as many again), so the receiver can miss frames
+        #[arg(long, default_value = "0")]
+        redundancy: f64,
+
+        /// Frames per second
+        #[arg(long, default_value = "4")]
+        fps: f64,
+
+        /// Passes of the terminal slideshow (0 = until interrupted)
+        #[arg(long, default_value = "0")]
+        loops: u32,
+    },
+
+    /// Rebuild a file from captured frames of `cqr animate`: a directory of
+    /// images, or the animated GIF or PNG itself
+    Reassemble {
+        /// Directory of captured frames, or an animated GIF or PNG
+        #[arg()]
+        input: PathBuf,
+
+        /// File to write (standard output when omitted)
+        #[arg(long)]
+        to: Option<PathBuf>,
+    },
+
     /// Batch generate QR codes from CSV or JSON file
     Batch {
         /// Input file (CSV or JSON)
@@ -1,19 +1,118 @@
 //! QR code decoder from image files
 
 use crate::error::{QrError, Result};
+use crate::renderer::Color;
+use crate::structured::{self, SetPosition};
+use image::{DynamicImage, ImageBuffer, RgbaImage};
+use resvg::{tiny_skia, usvg};
 use rxing::{
-    BarcodeFormat, DecodeHintType, DecodeHintValue, Luma8LuminanceSource, LuminanceSource,
+    BarcodeFormat, DecodeHintType, DecodeHintValue, DecodingHintDictionary, Luma8LuminanceSource,
+    LuminanceSource, RXingResult, RXingResultMetadataType, RXingResultMetadataValue,
 };
 use std::collections::HashMap;
 use std::path::PathBuf;
 
+/// SVG images are rendered at least this many pixels wide for decoding
+const MIN_SVG_DECODE_SIZE: f32 = 600.0;
+
+/// Formats `decode` looks for: the QR family plus every barcode cqr writes
+const POSSIBLE_FORMATS: [BarcodeFormat; 10] = [
+    BarcodeFormat::QR_CODE,
+    BarcodeFormat::MICRO_QR_CODE,
+    BarcodeFormat::RECTANGULAR_MICRO_QR_CODE,
+    BarcodeFormat::DATA_MATRIX,
+    BarcodeFormat::AZTEC,
+    BarcodeFormat::PDF_417,
+    BarcodeFormat::CODE_128,
+    BarcodeFormat::EAN_13,
+    BarcodeFormat::UPC_A,
+    BarcodeFormat::ITF,
+];
+
 /// Decoded QR code result
+#[derive(Clone, Debug)]
 pub struct DecodedQr {
     pub content: String,
+    /// Symbology that was read, such as `qrcode` or `code 128`
+    pub format: String,
+    /// Position in a set of linked QR codes, if the code is part of one
+    pub structured_append: Option<SetPosition>,
+}
+
+impl DecodedQr {
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    fn from_result(result: &RXingResult) -> Self {
+        // rxing files the symbol count under the parity key
+        let metadata = result.getRXingResultMetadata();
+        let index = metadata.get(&RXingResultMetadataType::STRUCTURED_APPEND_SEQUENCE);
+        let total = metadata.get(&RXingResultMetadataType::STRUCTURED_APPEND_PARITY);
+        let structured_append = match (index, total) {
+            (
+                Some(&RXingResultMetadataValue::StructuredAppendSequence(index)),
+                Some(&RXingResultMetadataValue::StructuredAppendParity(total)),
+            ) if index >= 0 && total > 0 => Some(SetPosition {
+                index: index as u8,
+                total: total as u8,
+            }),
+            _ => None,
+        };
+        Self {
+            content: result.getText().to_string(),
+            format: result.getBarcodeFormat().to_string(),
+            structured_append,
+        }
+    }
 }
 
-/// Decode QR code from an image file or URL
+/// Decode QR code from an image file (raster or SVG) or URL
 pub fn decode(input: &str) -> Result<DecodedQr> {
+    decode_image(&load_image(input)?)
+}
+
+/// Decode every code in an image file (raster or SVG) or URL.
+///
+/// The codes of a Structured Append sheet are read cell by cell from the
+/// grid cqr lays them out in; other images go through rxing's search for
+/// multiple codes.
+#[allow(clippy::option_if_let_else)]
+pub fn decode_all(input: &str) -> Result<Vec<DecodedQr>> {
+    let img = load_image(input)?;
+    let first = decode_image(&img)?;
+    let mut results = match first.structured_append {
+        Some(position) => {
+            let (columns, rows) = structured::sheet_grid(usize::from(position.total));
+            let (cell_width, cell_height) = (img.width() / columns, img.height() / rows);
+            (0..columns * rows)
+                .filter_map(|cell| {
+                    let (x, y) = (cell % columns * cell_width, cell / columns * cell_height);
+                    decode_image(&img.crop_imm(x, y, cell_width, cell_height)).ok()
+                })
+                .collect()
+        }
+        None => Vec::new(),
+    };
+    if first
+        .structured_append
+        .map_or(true, |position| results.len() < usize::from(position.total))
+    {
+        let (luma, width, height) = prepare(&img);
+        if let Ok(found) = rxing::helpers::detect_multiple_in_luma_with_hints(
+            luma,
+            width,
+            height,
+            &mut decode_hints(),
+        ) {
+            results.extend(found.iter().map(DecodedQr::from_result));
+        }
+    }
+    if results.is_empty() {
+        results.push(first);
+    }
+    Ok(results)
+}
+
+/// Read an image file, SVG or URL
+fn load_image(input: &str) -> Result<DynamicImage> {
     // Check if input is URL
     let img = if input.starts_with("http://") || input.starts_with("https://") {
         let resp = reqwest::blocking::get(input).map_err(|e| QrError::FileRead {
@@ -35,35 +134,101 @@ pub fn decode(input: &str) -> Result<DecodedQr> {
         image::load_from_memory(&bytes).map_err(|e| QrError::ImageError(e.to_string()))?
     } else {
         let path = PathBuf::from(input);
-        image::open(&path).map_err(|e| QrError::FileRead {
-            path: path.clone(),
-            source: std::io::Error::new(std::io::ErrorKind::Other, e.to_string()),
-        })?
+        let is_svg = path
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("svg"));
+        if is_svg {
+            let data = std::fs::read(&path).map_err(|e| QrError::FileRead {
+                path: path.clone(),
+                source: e,
+            })?;
+            rasterize_svg(&data)?
+        } else {
+            image::open(&path).map_err(|e| QrError::FileRead {
+                path: path.clone(),
+                source: std::io::Error::new(std::io::ErrorKind::Other, e.to_string()),
+            })?
+        }
+    };
+    Ok(img)
+}
+
+/// Decode QR code from an image in memory
+pub fn decode_image(img: &DynamicImage) -> Result<DecodedQr> {
+    let (luma, width, height) = prepare(img);
+    let result =
+        rxing::helpers::detect_in_luma_with_hints(luma, width, height, None, &mut decode_hints())
+            .map_err(|e| QrError::DecodeError(format!("{e:?}")))?;
+    Ok(DecodedQr::from_result(&result))
+}
+
+/// Grayscale pixels and size of an image, ready for rxing
+fn prepare(img: &DynamicImage) -> (Vec<u8>, u32, u32) {
+    // Flatten transparency onto white, so a transparent background reads as light
+    let flattened = if img.color().has_alpha() {
+        let white = Color::rgb(255, 255, 255);
+        let rgba = img.to_rgba8();
+        DynamicImage::ImageRgb8(ImageBuffer::from_fn(rgba.width(), rgba.height(), |x, y| {
+            let [r, g, b, a] = rgba.get_pixel(x, y).0;
+            Color::rgba(r, g, b, a).over(white).to_rgb()
+        }))
+    } else {
+        img.clone()
     };
 
     // Convert to grayscale
-    let gray = img.to_luma8();
+    let gray = flattened.to_luma8();
     let (width, height) = gray.dimensions();
 
     // Create luminance source for rxing
     let source = Luma8LuminanceSource::new(gray.into_raw(), width, height);
+    (source.get_matrix(), width, height)
+}
 
-    // Setup decode hints for QR codes
+/// Decode hints; trying harder puts the 2D readers first
+fn decode_hints() -> DecodingHintDictionary {
     let mut hints = HashMap::new();
     hints.insert(
         DecodeHintType::POSSIBLE_FORMATS,
-        DecodeHintValue::PossibleFormats(vec![BarcodeFormat::QR_CODE].into_iter().collect()),
+        DecodeHintValue::PossibleFormats(POSSIBLE_FORMATS.into_iter().collect()),
     );
     hints.insert(DecodeHintType::TRY_HARDER, DecodeHintValue::TryHarder(true));
+    hints
+}
 
-    // Get the matrix and decode
-    let matrix = source.get_matrix();
+/// Decode QR code from SVG markup
+pub fn decode_svg(data: &[u8]) -> Result<DecodedQr> {
+    decode_image(&rasterize_svg(data)?)
+}
 
-    // Decode
-    let result = rxing::helpers::detect_in_luma_with_hints(matrix, width, height, None, &mut hints)
-        .map_err(|e| QrError::DecodeError(format!("{e:?}")))?;
+/// Render an SVG image onto white, large enough for the modules to decode
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+fn rasterize_svg(data: &[u8]) -> Result<DynamicImage> {
+    // Crisp edges keep module seams and thin bars from blurring into gray
+    let options = usvg::Options {
+        shape_rendering: usvg::ShapeRendering::CrispEdges,
+        ..usvg::Options::default()
+    };
+    let tree = usvg::Tree::from_data(data, &options)
+        .map_err(|e| QrError::ImageError(format!("Invalid SVG: {e}")))?;
+    let size = tree.size();
+    let scale = (MIN_SVG_DECODE_SIZE / size.width().max(size.height())).max(1.0);
+    let (width, height) = (
+        (size.width() * scale).ceil() as u32,
+        (size.height() * scale).ceil() as u32,
+    );
+
+    let mut pixmap = tiny_skia::Pixmap::new(width, height)
+        .ok_or_else(|| QrError::ImageError("SVG image has no area".to_string()))?;
+    pixmap.fill(tiny_skia::Color::WHITE);
+    resvg::render(
+        &tree,
+        tiny_skia::Transform::from_scale(scale, scale),
+        &mut pixmap.as_mut(),
+    );
 
-    Ok(DecodedQr {
-        content: result.getText().to_string(),
-    })
+    // Opaque throughout, so the premultiplied pixels are plain RGBA
+    RgbaImage::from_raw(width, height, pixmap.take())
+        .map(DynamicImage::ImageRgba8)
+        .ok_or_else(|| QrError::ImageError("SVG bitmap has the wrong size".to_string()))
 }
@@ -0,0 +1,94 @@
+//! Snippets that embed a code in web pages and documents: an `<img>` tag,
+//! inline SVG, a standalone HTML page and a Markdown image
+//!
+//! Each carries alt text describing what the code holds, from
+//! `RenderConfig::alt_text` or else from the payload itself.
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use image::ImageFormat;
+
+use crate::error::Result;
+use crate::frame::xml_escape;
+use crate::generators::describe_payload;
+use crate::renderer::{create_qr_image, encode_buffer, render_to_svg, RenderConfig};
+
+/// Alt text for the code holding `data`
+#[must_use]
+pub fn alt_text(data: &str, config: &RenderConfig) -> String {
+    config
+        .alt_text
+        .clone()
+        .unwrap_or_else(|| describe_payload(data))
+}
+
+/// `<img>` tag with the code as a PNG data URI, sized in pixels
+pub fn render_to_img_tag(data: &str, config: &RenderConfig) -> Result<String> {
+    let img = create_qr_image(data, config)?;
+    let (width, height) = img.dimensions();
+    let png = encode_buffer(img, ImageFormat::Png, config)?;
+    Ok(format!(
+        r#"<img src="data:image/png;base64,{}" width="{width}" height="{height}" alt="{}">"#,
+        STANDARD.encode(png),
+        xml_escape(&alt_text(data, config))
+    ))
+}
+
+/// The SVG markup without its XML declaration, labelled for screen readers
+pub fn render_to_inline_svg(data: &str, config: &RenderConfig) -> Result<String> {
+    let svg = render_to_svg(data, config)?;
+    let svg = svg.find("<svg").map_or(svg.as_str(), |start| &svg[start..]);
+    let alt = xml_escape(&alt_text(data, config));
+    // Label the root element, and give it a title as its first child
+    let open_end = svg.find('>').unwrap_or(svg.len());
+    Ok(format!(
+        r#"<svg role="img" aria-label="{alt}"{}><title>{alt}</title>{}"#,
+        &svg[4..open_end],
+        svg.get(open_end + 1..).unwrap_or_default()
+    ))
+}
+
+/// Standalone HTML page with the code centred as inline SVG
+pub fn render_to_html_page(data: &str, config: &RenderConfig) -> Result<String> {
+    let svg = render_to_inline_svg(data, config)?;
+    Ok(format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>{}</title>
+<style>
+  body {{ margin: 0; min-height: 100vh; display: flex; align-items: center; justify-content: center; }}
+  svg {{ max-width: 100vw; height: auto; }}
+</style>
+</head>
+<body>
+{svg}
+</body>
+</html>
+"#,
+        xml_escape(&alt_text(data, config))
+    ))
+}
+
+/// Markdown image with the code as a PNG data URI
+pub fn render_to_markdown(data: &str, config: &RenderConfig) -> Result<String> {
+    let png = encode_buffer(create_qr_image(data, config)?, ImageFormat::Png, config)?;
+    Ok(format!(
+        "![{}](data:image/png;base64,{})",
+        markdown_escape(&alt_text(data, config)),
+        STANDARD.encode(png)
+    ))
+}
+
+/// Escape the characters that would end or nest a Markdown link text
+fn markdown_escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '\\' | '[' | ']' | '*' | '_' | '`') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
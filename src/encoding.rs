@@ -0,0 +1,266 @@
+//! Manual QR version, mask pattern and segment mode
+//!
+//! By default the smallest version, the mask with the lowest penalty score
+//! and an optimized mix of segments are chosen, as `qrcode` does. Labels that
+//! must keep the same layout whatever the data can pin any of them instead.
+
+use clap::ValueEnum;
+use qrcode::bits::Bits;
+use qrcode::canvas::{Canvas, MaskPattern};
+use qrcode::optimize::{Parser, Segment};
+use qrcode::types::Mode;
+use qrcode::{ec, EcLevel, Version};
+
+use crate::error::{QrError, Result};
+use crate::renderer::RenderConfig;
+use crate::shapes::{Eye, ModuleGrid};
+
+/// Mask patterns in the order of their QR pattern numbers
+const MASKS: [MaskPattern; 8] = [
+    MaskPattern::Checkerboard,
+    MaskPattern::HorizontalLines,
+    MaskPattern::VerticalLines,
+    MaskPattern::DiagonalLines,
+    MaskPattern::LargeCheckerboard,
+    MaskPattern::Fields,
+    MaskPattern::Diamonds,
+    MaskPattern::Meadow,
+];
+
+/// Characters alphanumeric mode holds besides A-Z and 0-9
+const ALPHANUMERIC_SYMBOLS: &str = " $%*+-./:";
+
+/// Segment mode for the data
+#[derive(Clone, Copy, ValueEnum, Debug, Default, PartialEq, Eq)]
+pub enum EncodingMode {
+    /// Mix of segments chosen for the smallest code
+    #[default]
+    Optimized,
+    /// Digits 0-9 only, about 3.3 bits per character
+    Numeric,
+    /// Uppercase letters, digits and " $%*+-./:", 5.5 bits per character
+    Alphanumeric,
+    /// Any text, 8 bits per UTF-8 byte
+    Byte,
+    /// Japanese text in Shift JIS double-byte characters, 13 bits each
+    Kanji,
+}
+
+impl EncodingMode {
+    /// Display name, as in verbose output
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Optimized => "optimized",
+            Self::Numeric => "numeric",
+            Self::Alphanumeric => "alphanumeric",
+            Self::Byte => "byte",
+            Self::Kanji => "Kanji",
+        }
+    }
+
+    const fn from_mode(mode: Mode) -> Self {
+        match mode {
+            Mode::Numeric => Self::Numeric,
+            Mode::Alphanumeric => Self::Alphanumeric,
+            Mode::Byte => Self::Byte,
+            Mode::Kanji => Self::Kanji,
+        }
+    }
+}
+
+/// A QR code with the version, mask and segments it was encoded with
+#[derive(Debug)]
+pub struct QrEncoding {
+    pub grid: ModuleGrid,
+    /// Version number, 1-40
+    pub version: i16,
+    /// Mask pattern number, 0-7
+    pub mask: u8,
+    /// Mode of each segment, in order
+    pub segments: Vec<EncodingMode>,
+}
+
+impl QrEncoding {
+    /// Segment modes for display, as in "numeric + byte"
+    #[must_use]
+    pub fn segment_names(&self) -> String {
+        let names: Vec<&str> = self.segments.iter().map(|mode| mode.name()).collect();
+        if names.is_empty() {
+            "none (empty data)".to_string()
+        } else {
+            names.join(" + ")
+        }
+    }
+}
+
+/// Encode `data` as a standard QR code, honoring the version, mask and mode
+/// settings of `config`.
+///
+/// The version is the smallest that holds the data, starting from
+/// `qr_version` when it is set; with `exact_version` it is `qr_version` or
+/// an error.
+pub fn encode(data: &str, config: &RenderConfig) -> Result<QrEncoding> {
+    let ec_level = config.ec_level;
+    let bytes = payload(data, config.encoding_mode)?;
+    let first = config.qr_version.unwrap_or(1);
+    let last = if config.exact_version { first } else { 40 };
+
+    let fitted = (first..=last).find_map(|number| {
+        let version = Version::Normal(number);
+        fill(&bytes, config.encoding_mode, version, ec_level)
+            .ok()
+            .map(|(bits, segments)| (number, bits, segments))
+    });
+    let Some((number, bits, segments)) = fitted else {
+        return Err(too_long(&bytes, config, first));
+    };
+
+    let version = Version::Normal(number);
+    let (data_codewords, ec_codewords) =
+        ec::construct_codewords(&bits.into_bytes(), version, ec_level)?;
+    let mut canvas = Canvas::new(version, ec_level);
+    canvas.draw_all_functional_patterns();
+    canvas.draw_data(&data_codewords, &ec_codewords);
+    let (canvas, mask) = match config.mask {
+        Some(mask) => {
+            canvas.apply_mask(MASKS[usize::from(mask)]);
+            (canvas, mask)
+        }
+        None => best_mask(&canvas),
+    };
+
+    let dark = canvas
+        .into_colors()
+        .into_iter()
+        .map(|color| color == qrcode::Color::Dark)
+        .collect();
+    let width = version.width().unsigned_abs().into();
+    Ok(QrEncoding {
+        grid: ModuleGrid::from_modules(width, width, dark, &Eye::ALL),
+        version: number,
+        mask,
+        segments: segments
+            .iter()
+            .map(|segment| EncodingMode::from_mode(segment.mode))
+            .collect(),
+    })
+}
+
+/// Bytes to encode: Shift JIS for Kanji mode, UTF-8 otherwise. Data the
+/// mode cannot hold is refused here, as `qrcode` would encode it wrongly.
+fn payload(data: &str, mode: EncodingMode) -> Result<Vec<u8>> {
+    let refused = match mode {
+        EncodingMode::Optimized | EncodingMode::Byte => None,
+        EncodingMode::Numeric => data
+            .chars()
+            .find(|c| !c.is_ascii_digit())
+            .map(|c| (c, "the digits 0-9")),
+        EncodingMode::Alphanumeric => data
+            .chars()
+            .find(|&c| {
+                !(c.is_ascii_uppercase() || c.is_ascii_digit() || ALPHANUMERIC_SYMBOLS.contains(c))
+            })
+            .map(|c| (c, "A-Z, 0-9 and \" $%*+-./:\"")),
+        EncodingMode::Kanji => {
+            let mut bytes = Vec::with_capacity(data.len());
+            for c in data.chars() {
+                let mut utf8 = [0; 4];
+                let (encoded, _, unmappable) =
+                    encoding_rs::SHIFT_JIS.encode(c.encode_utf8(&mut utf8));
+                match *encoded {
+                    [first, second] if is_kanji(first, second) && !unmappable => {
+                        bytes.extend([first, second]);
+                    }
+                    _ => return Err(refuse(mode, c, "Shift JIS double-byte characters")),
+                }
+            }
+            return Ok(bytes);
+        }
+    };
+    match refused {
+        Some((c, holds)) => Err(refuse(mode, c, holds)),
+        None => Ok(data.as_bytes().to_vec()),
+    }
+}
+
+/// Error for a character the mode cannot hold
+fn refuse(mode: EncodingMode, c: char, holds: &str) -> QrError {
+    QrError::QrGeneration(format!(
+        "{} mode only holds {holds}, not {c:?}; use --encoding-mode byte or optimized",
+        mode.name()
+    ))
+}
+
+/// Whether a Shift JIS character falls in the ranges Kanji mode encodes
+const fn is_kanji(first: u8, second: u8) -> bool {
+    let code = (first as u16) << 8 | second as u16;
+    matches!(code, 0x8140..=0x9FFC | 0xE040..=0xEBBF) && matches!(second, 0x40..=0xFC)
+}
+
+/// Segments of `bytes` and the data bits for one version, or an error when
+/// they don't fit
+fn fill(
+    bytes: &[u8],
+    mode: EncodingMode,
+    version: Version,
+    ec_level: EcLevel,
+) -> Result<(Bits, Vec<Segment>)> {
+    let whole = |mode| Segment {
+        mode,
+        begin: 0,
+        end: bytes.len(),
+    };
+    let segments = match mode {
+        EncodingMode::Optimized => Parser::new(bytes).optimize(version).collect(),
+        EncodingMode::Numeric => vec![whole(Mode::Numeric)],
+        EncodingMode::Alphanumeric => vec![whole(Mode::Alphanumeric)],
+        EncodingMode::Byte => vec![whole(Mode::Byte)],
+        EncodingMode::Kanji => vec![whole(Mode::Kanji)],
+    };
+    let mut bits = Bits::new(version);
+    bits.push_segments(bytes, segments.iter().copied())?;
+    bits.push_terminator(ec_level)?;
+    Ok((bits, segments))
+}
+
+/// Error for data that doesn't fit, naming the version it would need
+fn too_long(bytes: &[u8], config: &RenderConfig, pinned: i16) -> QrError {
+    let needed = (pinned..=40).find(|&number| {
+        fill(
+            bytes,
+            config.encoding_mode,
+            Version::Normal(number),
+            config.ec_level,
+        )
+        .is_ok()
+    });
+    if let Some(number) = needed {
+        return QrError::QrGeneration(format!(
+            "data needs version {number} at error correction level {:?}, but --exact-version \
+             fixes it at {pinned}",
+            config.ec_level
+        ));
+    }
+    QrError::QrGeneration(format!(
+        "data too long for a QR code of version {pinned} or above in {} mode at error \
+         correction level {:?}",
+        config.encoding_mode.name(),
+        config.ec_level
+    ))
+}
+
+/// Apply the mask with the lowest penalty score, as `qrcode` does, and find
+/// out which one it was
+fn best_mask(canvas: &Canvas) -> (Canvas, u8) {
+    let best = canvas.apply_best_mask();
+    let colors = best.clone().into_colors();
+    let mask = (0..8u8)
+        .find(|&mask| {
+            let mut masked = canvas.clone();
+            masked.apply_mask(MASKS[usize::from(mask)]);
+            masked.into_colors() == colors
+        })
+        .unwrap_or_default();
+    (best, mask)
+}
@@ -0,0 +1,153 @@
+//! EPS (Encapsulated PostScript) output for legacy print workflows
+
+#![allow(clippy::cast_precision_loss)]
+
+use std::fmt::Write as _;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::error::{QrError, Result};
+use crate::frame::FrameStyle;
+use crate::pdf::{rgb_components, shading_dict, DEFAULT_PRINT_SIZE_MM, PT_PER_MM};
+use crate::renderer::{vector_shapes, RenderConfig};
+use crate::shapes::{fmt_num, Path, Segment};
+
+/// Render QR code to an EPS document.
+///
+/// The bounding box is the code itself (quiet zone included): the configured
+/// print size sets its width, and the height follows the symbol's aspect
+/// ratio, as rMQR and linear barcodes are not square. PostScript has no
+/// transparency, so translucent colors are flattened onto the background.
+/// Logos, captions, banners and frames are refused rather than left out.
+pub fn render_to_eps(data: &str, config: &RenderConfig) -> Result<String> {
+    let unsupported: Vec<&str> = [
+        ("--logo", config.logo.is_some()),
+        ("--caption", config.caption.is_some()),
+        ("--banner", config.banner.is_some()),
+        ("--frame", config.frame != FrameStyle::None),
+    ]
+    .into_iter()
+    .filter_map(|(option, set)| set.then_some(option))
+    .collect();
+    if !unsupported.is_empty() {
+        return Err(QrError::InvalidLayout(format!(
+            "EPS output doesn't support {}; use PDF or SVG instead",
+            unsupported.join(", ")
+        )));
+    }
+
+    let grid = config.encode(data)?;
+
+    let size_mm = config.print_size_mm.unwrap_or(DEFAULT_PRINT_SIZE_MM);
+    if size_mm <= 0.0 {
+        return Err(QrError::InvalidLayout(
+            "print size must be positive".to_string(),
+        ));
+    }
+    let size_pt = size_mm * PT_PER_MM;
+    let total_modules = (grid.width() + config.quiet_zone as usize * 2) as f64;
+    let total_rows = (grid.height() + config.quiet_zone as usize * 2) as f64;
+    let scale = size_pt / total_modules;
+    let height_pt = total_rows * scale;
+
+    let mut eps = String::from("%!PS-Adobe-3.0 EPSF-3.0\n");
+    let _ = writeln!(
+        eps,
+        "%%BoundingBox: 0 0 {} {}",
+        size_pt.ceil(),
+        height_pt.ceil()
+    );
+    let _ = writeln!(
+        eps,
+        "%%HiResBoundingBox: 0 0 {} {}",
+        fmt_num(size_pt),
+        fmt_num(height_pt)
+    );
+    eps.push_str("%%Creator: cqr\n%%Title: QR code\n");
+    // Shadings (shfill) need LanguageLevel 3
+    let _ = writeln!(
+        eps,
+        "%%LanguageLevel: {}",
+        if config.gradient.is_some() { 3 } else { 2 }
+    );
+    eps.push_str("%%EndComments\n");
+    eps.push_str(
+        "/M {moveto} bind def /L {lineto} bind def /C {curveto} bind def /Z {closepath} bind def\n",
+    );
+    eps.push_str("gsave\n");
+    // Module units with the origin at the top-left of the quiet zone, y down
+    let _ = writeln!(
+        eps,
+        "0 {} translate {} {} scale",
+        fmt_num(height_pt),
+        fmt_num(scale),
+        fmt_num(-scale)
+    );
+
+    if config.bg_color.a > 0 {
+        let _ = writeln!(
+            eps,
+            "{} setrgbcolor 0 0 {} {} rectfill",
+            rgb_components(config.bg_color),
+            fmt_num(total_modules),
+            fmt_num(total_rows)
+        );
+    }
+
+    for shape in vector_shapes(&grid, config) {
+        if shape.path.segments.is_empty() {
+            continue;
+        }
+        match (shape.color, &config.gradient) {
+            (None, Some(gradient)) => {
+                eps.push_str("gsave newpath\n");
+                write_path(&mut eps, &shape.path);
+                let _ = writeln!(
+                    eps,
+                    "{} {} shfill grestore",
+                    if shape.even_odd { "eoclip" } else { "clip" },
+                    shading_dict(gradient, total_modules, total_rows)
+                );
+            }
+            (color, _) => {
+                let color = color.unwrap_or(config.fg_color).over(config.bg_color);
+                let _ = writeln!(eps, "{} setrgbcolor newpath", rgb_components(color));
+                write_path(&mut eps, &shape.path);
+                eps.push_str(if shape.even_odd { "eofill\n" } else { "fill\n" });
+            }
+        }
+    }
+
+    eps.push_str("grestore\n%%EOF\n");
+    Ok(eps)
+}
+
+/// Render QR code to EPS file
+pub fn render_to_eps_file(data: &str, output_path: &PathBuf, config: &RenderConfig) -> Result<()> {
+    let eps = render_to_eps(data, config)?;
+    fs::write(output_path, eps).map_err(|e| QrError::FileWrite {
+        path: output_path.clone(),
+        source: e,
+    })
+}
+
+/// Append PostScript path construction, one subpath per line
+fn write_path(eps: &mut String, path: &Path) {
+    for segment in &path.segments {
+        let _ = match *segment {
+            Segment::MoveTo(x, y) => write!(eps, "{} {} M ", fmt_num(x), fmt_num(y)),
+            Segment::LineTo(x, y) => write!(eps, "{} {} L ", fmt_num(x), fmt_num(y)),
+            Segment::CubicTo(x1, y1, x2, y2, x, y) => write!(
+                eps,
+                "{} {} {} {} {} {} C ",
+                fmt_num(x1),
+                fmt_num(y1),
+                fmt_num(x2),
+                fmt_num(y2),
+                fmt_num(x),
+                fmt_num(y)
+            ),
+            Segment::Close => writeln!(eps, "Z"),
+        };
+    }
+}
@@ -12,6 +12,9 @@ pub enum QrError {
     #[error("Failed to generate QR code: {0}")]
     QrGeneration(String),
 
+    #[error("Failed to generate {symbology} barcode: {message}")]
+    BarcodeGeneration { symbology: String, message: String },
+
     #[error("Failed to write to file '{path}': {source}")]
     FileWrite {
         path: PathBuf,
@@ -24,9 +27,37 @@ pub enum QrError {
         source: std::io::Error,
     },
 
-    #[error("Invalid color format '{0}'. Use hex format like #RRGGBB or #RGB")]
+    #[error("Invalid color format '{0}'. Use hex format like #RRGGBB, #RRGGBBAA or #RGB, or 'transparent'")]
     InvalidColor(String),
 
+    #[error("Invalid gradient {0}. Use a spec like linear:45:#f00@0,#00f@1 or radial:#fff,#000")]
+    InvalidGradient(String),
+
+    #[error("Logo hides {coverage:.1}% of the symbol, but error correction level {level} can only spare {max:.1}%; use a smaller --logo-scale or --logo-padding, or a higher --error-correction")]
+    LogoTooLarge {
+        coverage: f64,
+        max: f64,
+        level: String,
+    },
+
+    #[error("{0} (--strict treats warnings as errors)")]
+    StrictWarning(String),
+
+    #[error("Invalid size: {0}")]
+    InvalidSize(String),
+
+    #[error("Invalid print layout: {0}")]
+    InvalidLayout(String),
+
+    #[error("Verification failed: {0}; try more contrast, a smaller logo or a higher error correction level")]
+    VerificationFailed(String),
+
+    #[error("--verify can't read {0} output back; check a PNG or SVG copy instead")]
+    UnverifiableFormat(String),
+
+    #[error("Invalid animation: {0}")]
+    InvalidAnimation(String),
+
     #[error("Failed to decode QR code from image: {0}")]
     DecodeError(String),
 
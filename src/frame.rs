@@ -0,0 +1,447 @@
+//! Captions, frames and call-to-action banners around the code
+
+#![allow(clippy::cast_possible_truncation)]
+#![allow(clippy::cast_sign_loss)]
+#![allow(clippy::cast_precision_loss)]
+
+use ab_glyph::{point, Font, FontRef, Glyph, OutlineCurve, PxScale, ScaleFont};
+use clap::ValueEnum;
+use image::{ImageBuffer, Rgba};
+
+use crate::renderer::{Color, RenderConfig};
+use crate::shapes::{Path, RoundedBox, Segment};
+
+/// `DejaVu Sans Bold`, bundled so raster captions look the same everywhere
+static FONT_DATA: &[u8] = include_bytes!("../assets/fonts/DejaVuSans-Bold.ttf");
+
+/// CSS font stack for SVG captions, starting with the bundled face
+pub const SVG_FONT_FAMILY: &str = "'DejaVu Sans', Verdana, Arial, sans-serif";
+
+/// Placeholder in caption text replaced by the encoded data
+pub const DATA_PLACEHOLDER: &str = "{data}";
+
+/// Border drawn around the code (and caption)
+#[derive(Clone, Copy, ValueEnum, Debug, Default, PartialEq, Eq)]
+pub enum FrameStyle {
+    /// No border
+    #[default]
+    None,
+    /// Square corners
+    Square,
+    /// Rounded corners
+    Rounded,
+}
+
+/// Where the caption goes relative to the code
+#[derive(Clone, Copy, ValueEnum, Debug, Default, PartialEq, Eq)]
+pub enum CaptionPosition {
+    Above,
+    #[default]
+    Below,
+}
+
+/// Area filled with the frame color: a box, optionally with a window cut out
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FrameFill {
+    pub outer: RoundedBox,
+    pub hole: Option<RoundedBox>,
+}
+
+impl FrameFill {
+    #[must_use]
+    pub fn contains(&self, x: f64, y: f64) -> bool {
+        self.outer.contains(x, y) && !self.hole.is_some_and(|hole| hole.contains(x, y))
+    }
+
+    /// Outline to fill with the even-odd rule
+    #[must_use]
+    pub fn outline(&self) -> Path {
+        let mut path = self.outer.outline();
+        if let Some(hole) = self.hole {
+            path.extend(&hole.outline());
+        }
+        path
+    }
+}
+
+/// One line of horizontally centered text
+#[derive(Clone, Debug, PartialEq)]
+pub struct TextLine {
+    pub text: String,
+    pub center_x: f64,
+    pub baseline: f64,
+    /// Em size, as in CSS `font-size`
+    pub font_size: f64,
+    pub color: Color,
+}
+
+/// Placement of the code and its decorations on the canvas.
+///
+/// All lengths are in the units of the code size the layout was made for
+/// (pixels for raster output, user units for SVG, modules for PDF).
+#[derive(Clone, Debug, PartialEq)]
+pub struct FrameLayout {
+    pub width: f64,
+    pub height: f64,
+    /// Top-left corner of the code
+    pub code_x: f64,
+    pub code_y: f64,
+    /// Width of the code
+    pub code_size: f64,
+    pub code_height: f64,
+    pub fills: Vec<FrameFill>,
+    pub frame_color: Color,
+    pub texts: Vec<TextLine>,
+}
+
+impl FrameLayout {
+    /// Lay out the decorations around a square code `code_size` wide, or
+    /// `None` when the configuration asks for none
+    #[must_use]
+    pub fn new(config: &RenderConfig, data: &str, code_size: f64) -> Option<Self> {
+        Self::for_code(config, data, code_size, code_size)
+    }
+
+    /// Lay out the decorations around a code `code_size` wide and
+    /// `code_height` tall; text is sized by the width
+    #[allow(clippy::too_many_lines)]
+    #[must_use]
+    pub fn for_code(
+        config: &RenderConfig,
+        data: &str,
+        code_size: f64,
+        code_height: f64,
+    ) -> Option<Self> {
+        let caption = config
+            .caption
+            .as_deref()
+            .map(|text| expand_caption(text, data))
+            .filter(|text| !text.trim().is_empty());
+        let banner = config
+            .banner
+            .as_deref()
+            .filter(|text| !text.trim().is_empty());
+        if caption.is_none() && banner.is_none() && config.frame == FrameStyle::None {
+            return None;
+        }
+
+        let size = code_size;
+        // Decorations scale with the shorter side, so wide codes don't get huge text
+        let unit = code_size.min(code_height);
+        let framed = config.frame != FrameStyle::None;
+        let stroke = if framed { unit * 0.03 } else { 0.0 };
+        let radius = if config.frame == FrameStyle::Rounded {
+            unit * 0.08
+        } else {
+            0.0
+        };
+        let width = 2.0f64.mul_add(stroke, size);
+        let center_x = width / 2.0;
+        let frame_color = config.frame_color.unwrap_or(config.fg_color);
+
+        let mut texts = Vec::new();
+        let mut caption_line = |top: f64| {
+            caption.as_ref().map_or(0.0, |text| {
+                let font_size = fit_font_size(text, unit * 0.07, size * 0.94);
+                texts.push(TextLine {
+                    text: text.clone(),
+                    center_x,
+                    baseline: font_size.mul_add(1.15, top),
+                    font_size,
+                    color: config.fg_color,
+                });
+                font_size * 1.6
+            })
+        };
+
+        let mut y = stroke;
+        if config.caption_position == CaptionPosition::Above {
+            y += caption_line(y);
+        }
+        let code_y = y;
+        y += code_height;
+        if config.caption_position == CaptionPosition::Below {
+            y += caption_line(y);
+        }
+        let window_bottom = y;
+
+        let mut fills = Vec::new();
+        if let Some(text) = banner {
+            let font_size = fit_font_size(text, unit * 0.08, size * 0.9);
+            let band = font_size * 2.0;
+            // The banner sits in the frame band; without a frame it is a bar of its own
+            let top = if framed { y } else { size.mul_add(0.02, y) };
+            if !framed {
+                fills.push(FrameFill {
+                    outer: RoundedBox {
+                        x: 0.0,
+                        y: top,
+                        width,
+                        height: band,
+                        radius: band * 0.25,
+                    },
+                    hole: None,
+                });
+            }
+            texts.push(TextLine {
+                text: text.to_string(),
+                center_x,
+                baseline: font_size.mul_add(1.35, top),
+                font_size,
+                // Knock the text out of the banner
+                color: if config.bg_color.is_opaque() {
+                    config.bg_color
+                } else {
+                    Color::rgb(255, 255, 255)
+                },
+            });
+            y = top + band;
+        } else {
+            y += stroke;
+        }
+
+        if framed {
+            fills.insert(
+                0,
+                FrameFill {
+                    outer: RoundedBox {
+                        x: 0.0,
+                        y: 0.0,
+                        width,
+                        height: y,
+                        radius,
+                    },
+                    hole: Some(RoundedBox {
+                        x: stroke,
+                        y: stroke,
+                        width: size,
+                        height: window_bottom - stroke,
+                        radius: (radius - stroke).max(0.0),
+                    }),
+                },
+            );
+        }
+
+        Some(Self {
+            width,
+            height: y,
+            code_x: stroke,
+            code_y,
+            code_size: size,
+            code_height,
+            fills,
+            frame_color,
+            texts,
+        })
+    }
+
+    /// Paint the decorations and place the rendered code on a new canvas
+    #[must_use]
+    pub fn render_image(
+        &self,
+        code: &ImageBuffer<Rgba<u8>, Vec<u8>>,
+        background: Color,
+    ) -> ImageBuffer<Rgba<u8>, Vec<u8>> {
+        let width = self.width.round() as u32;
+        let height = self.height.round() as u32;
+        let mut img = ImageBuffer::from_fn(width, height, |x, y| {
+            let (u, v) = (f64::from(x) + 0.5, f64::from(y) + 0.5);
+            if self.fills.iter().any(|fill| fill.contains(u, v)) {
+                self.frame_color.over(background).to_rgba()
+            } else {
+                background.to_rgba()
+            }
+        });
+
+        image::imageops::replace(
+            &mut img,
+            code,
+            self.code_x.round() as i64,
+            self.code_y.round() as i64,
+        );
+
+        let font = font();
+        for line in &self.texts {
+            for glyph in layout_glyphs(&font, line) {
+                let Some(outlined) = font.outline_glyph(glyph) else {
+                    continue;
+                };
+                let bounds = outlined.px_bounds();
+                outlined.draw(|gx, gy, coverage| {
+                    let x = bounds.min.x as i64 + i64::from(gx);
+                    let y = bounds.min.y as i64 + i64::from(gy);
+                    if x < 0 || y < 0 || x >= i64::from(width) || y >= i64::from(height) {
+                        return;
+                    }
+                    let (x, y) = (x as u32, y as u32);
+                    let alpha = f64::from(line.color.a) * f64::from(coverage.clamp(0.0, 1.0));
+                    let ink = Color {
+                        a: alpha.round() as u8,
+                        ..line.color
+                    };
+                    let below = img.get_pixel(x, y).0;
+                    let below = Color::rgba(below[0], below[1], below[2], below[3]);
+                    img.put_pixel(x, y, ink.over(below).to_rgba());
+                });
+            }
+        }
+        img
+    }
+}
+
+impl TextLine {
+    /// Glyph outlines of the line, for vector output without font embedding
+    #[must_use]
+    pub fn outline(&self) -> Path {
+        let font = font();
+        let units = font_units_scale(&font, self.font_size);
+        let mut path = Path::default();
+        for glyph in layout_glyphs(&font, self) {
+            let Some(outline) = font.outline(glyph.id) else {
+                continue;
+            };
+            let origin = glyph.position;
+            let map = |p: ab_glyph::Point| {
+                (
+                    f64::from(p.x).mul_add(units, f64::from(origin.x)),
+                    f64::from(-p.y).mul_add(units, f64::from(origin.y)),
+                )
+            };
+
+            let mut current = None;
+            for curve in &outline.curves {
+                let (start, end) = match *curve {
+                    OutlineCurve::Line(p0, p1)
+                    | OutlineCurve::Quad(p0, _, p1)
+                    | OutlineCurve::Cubic(p0, _, _, p1) => (p0, p1),
+                };
+                if current != Some(start) {
+                    if current.is_some() {
+                        path.segments.push(Segment::Close);
+                    }
+                    let (x, y) = map(start);
+                    path.segments.push(Segment::MoveTo(x, y));
+                }
+                match *curve {
+                    OutlineCurve::Line(_, p1) => {
+                        let (x, y) = map(p1);
+                        path.segments.push(Segment::LineTo(x, y));
+                    }
+                    OutlineCurve::Quad(p0, p1, p2) => {
+                        // Degree elevation: control points 2/3 of the way to the quad control
+                        let toward = |from: ab_glyph::Point| {
+                            point(
+                                (p1.x - from.x).mul_add(2.0 / 3.0, from.x),
+                                (p1.y - from.y).mul_add(2.0 / 3.0, from.y),
+                            )
+                        };
+                        let (c1, c2) = (toward(p0), toward(p2));
+                        let ((x1, y1), (x2, y2), (x, y)) = (map(c1), map(c2), map(p2));
+                        path.segments.push(Segment::CubicTo(x1, y1, x2, y2, x, y));
+                    }
+                    OutlineCurve::Cubic(_, c1, c2, p3) => {
+                        let ((x1, y1), (x2, y2), (x, y)) = (map(c1), map(c2), map(p3));
+                        path.segments.push(Segment::CubicTo(x1, y1, x2, y2, x, y));
+                    }
+                }
+                current = Some(end);
+            }
+            if current.is_some() {
+                path.segments.push(Segment::Close);
+            }
+        }
+        path
+    }
+
+    /// SVG `<text>` element
+    #[must_use]
+    pub fn to_svg(&self) -> String {
+        use crate::shapes::fmt_num;
+
+        format!(
+            r#"<text x="{}" y="{}" font-family="{}" font-size="{}" font-weight="bold" text-anchor="middle" {}>{}</text>"#,
+            fmt_num(self.center_x),
+            fmt_num(self.baseline),
+            SVG_FONT_FAMILY,
+            fmt_num(self.font_size),
+            self.color.to_svg_fill(),
+            xml_escape(&self.text)
+        )
+    }
+}
+
+/// Replace the `{data}` placeholder with the encoded data
+#[must_use]
+pub fn expand_caption(template: &str, data: &str) -> String {
+    template.replace(DATA_PLACEHOLDER, data)
+}
+
+/// Width of `text` set in the bundled font at `font_size`
+#[must_use]
+pub fn text_width(text: &str, font_size: f64) -> f64 {
+    let font = font();
+    let scaled = font.as_scaled(px_scale(&font, font_size));
+    let mut width = 0.0;
+    let mut previous = None;
+    for c in text.chars() {
+        let id = scaled.glyph_id(c);
+        if let Some(previous) = previous {
+            width += scaled.kern(previous, id);
+        }
+        width += scaled.h_advance(id);
+        previous = Some(id);
+    }
+    f64::from(width)
+}
+
+/// Escape text for use in XML content and attributes
+#[must_use]
+pub fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn font() -> FontRef<'static> {
+    FontRef::try_from_slice(FONT_DATA).expect("bundled font is valid")
+}
+
+/// Scale factor from font units to output units at an em size
+fn font_units_scale(font: &FontRef<'_>, font_size: f64) -> f64 {
+    font_size / f64::from(font.units_per_em().unwrap_or(2048.0))
+}
+
+/// `ab_glyph` scales by line height rather than em size
+fn px_scale(font: &FontRef<'_>, font_size: f64) -> PxScale {
+    PxScale::from((font_units_scale(font, font_size) * f64::from(font.height_unscaled())) as f32)
+}
+
+/// Largest size up to `preferred` at which `text` fits in `max_width`
+fn fit_font_size(text: &str, preferred: f64, max_width: f64) -> f64 {
+    let width = text_width(text, preferred);
+    if width > max_width {
+        preferred * max_width / width
+    } else {
+        preferred
+    }
+}
+
+/// Position the glyphs of a centered line, with kerning
+fn layout_glyphs(font: &FontRef<'_>, line: &TextLine) -> Vec<Glyph> {
+    let scale = px_scale(font, line.font_size);
+    let scaled = font.as_scaled(scale);
+    let mut x = (line.center_x - text_width(&line.text, line.font_size) / 2.0) as f32;
+    let mut glyphs = Vec::new();
+    let mut previous = None;
+    for c in line.text.chars() {
+        let id = scaled.glyph_id(c);
+        if let Some(previous) = previous {
+            x += scaled.kern(previous, id);
+        }
+        glyphs.push(id.with_scale_and_position(scale, point(x, line.baseline as f32)));
+        x += scaled.h_advance(id);
+        previous = Some(id);
+    }
+    glyphs
+}
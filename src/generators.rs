@@ -164,6 +164,111 @@ pub fn generate_sepa_string(
     sepa
 }
 
+/// Longest value quoted in a payload description, in characters
+const DESCRIPTION_LENGTH: usize = 80;
+
+/// Describe what a payload means to someone scanning it, as in "`WiFi`
+/// network `MyNetwork`" or "Link to example.com"; the inverse of the
+/// generators above, for alt text. Plain text is quoted as is.
+#[must_use]
+#[allow(clippy::option_if_let_else)]
+pub fn describe_payload(data: &str) -> String {
+    let field = |prefix: &str| {
+        data.lines()
+            .find_map(|line| line.strip_prefix(prefix))
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+    };
+
+    let scheme = |prefix: &str| {
+        data.get(..prefix.len())
+            .filter(|head| head.eq_ignore_ascii_case(prefix))
+            .map(|_| &data[prefix.len()..])
+    };
+
+    let description = if let Some(fields) = scheme("WIFI:") {
+        wifi_ssid(fields).map_or_else(
+            || "WiFi network".to_string(),
+            |ssid| format!("WiFi network {ssid}"),
+        )
+    } else if let Some(rest) = scheme("mailto:") {
+        let address = rest.split('?').next().unwrap_or_default();
+        format!("Email to {address}")
+    } else if let Some(number) = scheme("tel:") {
+        format!("Phone number {number}")
+    } else if let Some(rest) = scheme("SMSTO:") {
+        let number = rest.split(':').next().unwrap_or_default();
+        format!("Text message to {number}")
+    } else if let Some(rest) = scheme("geo:") {
+        let coordinates = rest.split(['?', ';']).next().unwrap_or_default();
+        format!("Location {}", coordinates.replace(',', ", "))
+    } else if let Some(rest) = scheme("bitcoin:") {
+        let (address, query) = rest.split_once('?').unwrap_or((rest, ""));
+        let amount = query
+            .split('&')
+            .find_map(|param| param.strip_prefix("amount="));
+        amount.map_or_else(
+            || format!("Bitcoin address {address}"),
+            |amount| format!("Bitcoin payment of {amount} BTC to {address}"),
+        )
+    } else if data.starts_with("BEGIN:VCARD") {
+        field("FN:").map_or_else(
+            || "Contact card".to_string(),
+            |name| format!("Contact card for {name}"),
+        )
+    } else if data.starts_with("BEGIN:VEVENT") || data.starts_with("BEGIN:VCALENDAR") {
+        field("SUMMARY:").map_or_else(
+            || "Calendar event".to_string(),
+            |summary| format!("Calendar event {summary}"),
+        )
+    } else if data.starts_with("BCD\n") {
+        // EPC lines: service tag, version, charset, SCT, BIC, name, IBAN, amount
+        let lines: Vec<&str> = data.lines().collect();
+        let name = lines.get(5).copied().unwrap_or_default();
+        match lines.get(7).and_then(|amount| amount.strip_prefix("EUR")) {
+            Some(amount) if !amount.is_empty() => {
+                format!("SEPA payment of EUR {amount} to {name}")
+            }
+            _ => format!("SEPA payment to {name}"),
+        }
+    } else if let Some(link) = scheme("https://").or_else(|| scheme("http://")) {
+        format!("Link to {}", link.trim_end_matches('/'))
+    } else {
+        format!(
+            "Text: {}",
+            data.split_whitespace().collect::<Vec<_>>().join(" ")
+        )
+    };
+
+    if description.chars().count() > DESCRIPTION_LENGTH {
+        let cut: String = description.chars().take(DESCRIPTION_LENGTH - 1).collect();
+        format!("{}\u{2026}", cut.trim_end())
+    } else {
+        description
+    }
+}
+
+/// Network name of a `WIFI:` payload (without the prefix), unescaping `\;`,
+/// `\:`, `\,` and `\\`
+fn wifi_ssid(fields: &str) -> Option<String> {
+    let mut ssid = None;
+    let mut current = String::new();
+    let mut chars = fields.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => current.extend(chars.next()),
+            ';' => {
+                if let Some(value) = current.strip_prefix("S:") {
+                    ssid = Some(value.to_string());
+                }
+                current.clear();
+            }
+            _ => current.push(c),
+        }
+    }
+    ssid.filter(|ssid| !ssid.is_empty())
+}
+
 /// Simple URL encoding helper
 fn url_encode(input: &str) -> String {
     url::form_urlencoded::byte_serialize(input.as_bytes()).collect()
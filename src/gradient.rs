@@ -0,0 +1,240 @@
+//! Gradient fills shared by the raster and vector renderers
+//!
+//! Gradients are laid out in image space (the full canvas including the quiet
+//! zone), so the PNG math and the SVG `userSpaceOnUse` gradient agree exactly.
+
+#![allow(clippy::cast_possible_truncation)]
+#![allow(clippy::cast_precision_loss)]
+#![allow(clippy::cast_sign_loss)]
+
+use std::fmt::Write;
+
+use crate::error::{QrError, Result};
+use crate::renderer::Color;
+use crate::shapes::fmt_num;
+
+/// Geometry of a gradient
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GradientKind {
+    /// Linear gradient; 0° runs left to right, 90° top to bottom
+    Linear { angle: f64 },
+    /// Radial gradient from the center out to the corners
+    Radial,
+}
+
+/// A color at a position (0.0 to 1.0) along the gradient
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ColorStop {
+    pub offset: f64,
+    pub color: Color,
+}
+
+/// Multi-stop gradient fill for the dark modules
+#[derive(Clone, Debug, PartialEq)]
+pub struct Gradient {
+    pub kind: GradientKind,
+    pub stops: Vec<ColorStop>,
+}
+
+impl Gradient {
+    /// Two-stop diagonal gradient, as produced by `--gradient-color`
+    #[must_use]
+    pub fn diagonal(from: Color, to: Color) -> Self {
+        Self {
+            kind: GradientKind::Linear { angle: 45.0 },
+            stops: vec![
+                ColorStop {
+                    offset: 0.0,
+                    color: from,
+                },
+                ColorStop {
+                    offset: 1.0,
+                    color: to,
+                },
+            ],
+        }
+    }
+
+    /// Parse a gradient spec such as `linear:45:#f00@0,#00f@1` or
+    /// `radial:#fff,#000`.
+    ///
+    /// The angle defaults to 45° and stops without an `@offset` are spread
+    /// evenly. Offsets are fractions or percentages (`@50%`).
+    pub fn parse(spec: &str) -> Result<Self> {
+        let invalid = |reason: &str| QrError::InvalidGradient(format!("'{spec}': {reason}"));
+
+        let (kind, rest) = spec
+            .split_once(':')
+            .ok_or_else(|| invalid("expected 'linear:' or 'radial:' prefix"))?;
+
+        let (kind, stops) = match kind.trim().to_lowercase().as_str() {
+            "linear" => match rest.split_once(':') {
+                Some((angle, stops)) => {
+                    let angle = angle
+                        .trim()
+                        .trim_end_matches("deg")
+                        .parse::<f64>()
+                        .map_err(|_| invalid("angle must be a number of degrees"))?;
+                    (GradientKind::Linear { angle }, stops)
+                }
+                None => (GradientKind::Linear { angle: 45.0 }, rest),
+            },
+            "radial" => (GradientKind::Radial, rest),
+            _ => return Err(invalid("expected 'linear:' or 'radial:' prefix")),
+        };
+
+        let parts: Vec<&str> = stops.split(',').map(str::trim).collect();
+        if parts.len() < 2 {
+            return Err(invalid("at least two color stops are needed"));
+        }
+
+        let last = (parts.len() - 1) as f64;
+        let mut parsed = Vec::with_capacity(parts.len());
+        for (i, part) in parts.iter().enumerate() {
+            let (color, offset) = match part.split_once('@') {
+                Some((color, offset)) => (
+                    color,
+                    parse_offset(offset).ok_or_else(|| {
+                        invalid("offsets must be between 0 and 1 (or 0% and 100%)")
+                    })?,
+                ),
+                None => (*part, i as f64 / last),
+            };
+            parsed.push(ColorStop {
+                offset,
+                color: Color::from_hex(color.trim())?,
+            });
+        }
+
+        if parsed.windows(2).any(|w| w[1].offset < w[0].offset) {
+            return Err(invalid("stop offsets must be in increasing order"));
+        }
+
+        Ok(Self {
+            kind,
+            stops: parsed,
+        })
+    }
+
+    /// Position along the gradient (0.0 to 1.0) of point `(x, y)` on a
+    /// canvas `width` by `height` units
+    #[must_use]
+    pub fn progress_at(&self, x: f64, y: f64, width: f64, height: f64) -> f64 {
+        let t = match self.kind {
+            GradientKind::Linear { .. } => {
+                let ((x1, y1), (x2, y2)) = self.linear_endpoints(width, height);
+                let (dx, dy) = (x2 - x1, y2 - y1);
+                (x - x1).mul_add(dx, (y - y1) * dy) / dx.mul_add(dx, dy * dy)
+            }
+            GradientKind::Radial => {
+                (x - width / 2.0).hypot(y - height / 2.0) / Self::radial_radius(width, height)
+            }
+        };
+        t.clamp(0.0, 1.0)
+    }
+
+    /// Color at position `t` (0.0 to 1.0), interpolating between stops
+    #[must_use]
+    pub fn color_at(&self, t: f64) -> Color {
+        let first = self.stops[0];
+        let last = self.stops[self.stops.len() - 1];
+        if t <= first.offset {
+            return first.color;
+        }
+        if t >= last.offset {
+            return last.color;
+        }
+
+        let (from, to) = self
+            .stops
+            .windows(2)
+            .map(|w| (w[0], w[1]))
+            .find(|(_, to)| t <= to.offset)
+            .unwrap_or((last, last));
+        let span = to.offset - from.offset;
+        let local = if span > 0.0 {
+            (t - from.offset) / span
+        } else {
+            1.0
+        };
+        let lerp = |a: u8, b: u8| {
+            (f64::from(b) - f64::from(a))
+                .mul_add(local, f64::from(a))
+                .round() as u8
+        };
+        Color::rgba(
+            lerp(from.color.r, to.color.r),
+            lerp(from.color.g, to.color.g),
+            lerp(from.color.b, to.color.b),
+            lerp(from.color.a, to.color.a),
+        )
+    }
+
+    /// Start and end of a linear gradient line chosen so that the two
+    /// corners farthest along the angle land exactly on offsets 0 and 1
+    pub(crate) fn linear_endpoints(&self, width: f64, height: f64) -> ((f64, f64), (f64, f64)) {
+        let angle = match self.kind {
+            GradientKind::Linear { angle } => angle.to_radians(),
+            GradientKind::Radial => 0.0,
+        };
+        let (sin, cos) = angle.sin_cos();
+        let half = cos.abs().mul_add(width, sin.abs() * height) / 2.0;
+        let (center_x, center_y) = (width / 2.0, height / 2.0);
+        (
+            (cos.mul_add(-half, center_x), sin.mul_add(-half, center_y)),
+            (cos.mul_add(half, center_x), sin.mul_add(half, center_y)),
+        )
+    }
+
+    /// Radius of a radial gradient: from the center to the corners
+    pub(crate) fn radial_radius(width: f64, height: f64) -> f64 {
+        width.hypot(height) / 2.0
+    }
+
+    /// SVG `<linearGradient>`/`<radialGradient>` element with the given id
+    #[must_use]
+    pub fn to_svg_def(&self, id: &str, width: f64, height: f64) -> String {
+        let mut def = match self.kind {
+            GradientKind::Linear { .. } => {
+                let ((x1, y1), (x2, y2)) = self.linear_endpoints(width, height);
+                format!(
+                    r#"<linearGradient id="{id}" gradientUnits="userSpaceOnUse" x1="{}" y1="{}" x2="{}" y2="{}">"#,
+                    fmt_num(x1),
+                    fmt_num(y1),
+                    fmt_num(x2),
+                    fmt_num(y2)
+                )
+            }
+            GradientKind::Radial => format!(
+                r#"<radialGradient id="{id}" gradientUnits="userSpaceOnUse" cx="{}" cy="{}" r="{}">"#,
+                fmt_num(width / 2.0),
+                fmt_num(height / 2.0),
+                fmt_num(Self::radial_radius(width, height))
+            ),
+        };
+        for stop in &self.stops {
+            let _ = write!(
+                def,
+                r#"<stop offset="{}%" style="stop-color:{};stop-opacity:{}" />"#,
+                fmt_num(stop.offset * 100.0),
+                stop.color.to_hex(),
+                fmt_num(stop.color.opacity())
+            );
+        }
+        def.push_str(match self.kind {
+            GradientKind::Linear { .. } => "</linearGradient>",
+            GradientKind::Radial => "</radialGradient>",
+        });
+        def
+    }
+}
+
+/// Parse a stop offset given as a fraction (`0.5`) or percentage (`50%`)
+fn parse_offset(offset: &str) -> Option<f64> {
+    let offset = offset.trim();
+    let value = match offset.strip_suffix('%') {
+        Some(percent) => percent.trim().parse::<f64>().ok()? / 100.0,
+        None => offset.parse::<f64>().ok()?,
+    };
+    (0.0..=1.0).contains(&value).then_some(value)
+}
@@ -1,17 +1,34 @@
 //! Captain QR - A professional CLI tool to generate and decode QR codes
 //!
 //! Generate QR codes for `WiFi`, URLs, contacts, payments, and more.
-//! Supports PNG, SVG, terminal, and base64 output formats.
+//! Supports PNG, JPEG, WebP, GIF, BMP, TIFF, SVG, EPS, PDF, terminal, and base64 output.
 
 #![allow(clippy::missing_errors_doc)]
 #![allow(clippy::missing_panics_doc)]
 
+pub mod animate;
+pub mod barcode;
 pub mod batch;
 pub mod cli;
 pub mod decoder;
+pub mod embed;
+pub mod encoding;
+pub mod eps;
 pub mod error;
+pub mod frame;
 pub mod generators;
+pub mod gradient;
+pub mod lint;
+pub mod logo;
+pub mod matrix;
+pub mod pdf;
 pub mod renderer;
+pub mod rmqr;
+pub mod shapes;
+pub mod structured;
+pub mod symbol;
+pub mod terminal;
+pub mod verify;
 
 pub mod wizard;
 
@@ -25,9 +42,12 @@ use generators::{
     generate_phone_string, generate_sepa_string, generate_sms_string, generate_vcard_string,
     generate_wifi_string,
 };
+use gradient::Gradient;
 use renderer::{
-    render_to_base64, render_to_png, render_to_svg_file, render_to_terminal, Color, RenderConfig,
+    render_to_base64, render_to_image, render_to_svg_file, render_to_terminal, Color, RenderConfig,
 };
+use symbol::SymbolType;
+use terminal::{TerminalColor, TerminalGlyphs, TerminalGraphics, TerminalPolarity};
 
 #[allow(clippy::too_many_lines)]
 pub fn run() -> Result<()> {
@@ -36,61 +56,147 @@ pub fn run() -> Result<()> {
     // Parse colors
     let fg_color = Color::from_hex(&cli.fg_color)?;
     let bg_color = Color::from_hex(&cli.bg_color)?;
-    let gradient_color = if let Some(hex) = &cli.gradient_color {
-        Some(Color::from_hex(hex)?)
+    let gradient = if let Some(spec) = &cli.gradient {
+        Some(Gradient::parse(spec)?)
+    } else if let Some(hex) = &cli.gradient_color {
+        Some(Gradient::diagonal(fg_color, Color::from_hex(hex)?))
     } else {
         None
     };
 
+    let eye_outer_color = cli
+        .eye_outer_color
+        .as_deref()
+        .map(Color::from_hex)
+        .transpose()?;
+    let eye_inner_color = cli
+        .eye_inner_color
+        .as_deref()
+        .map(Color::from_hex)
+        .transpose()?;
+
     // Build render config
     let config = RenderConfig {
         size: cli.size,
-        quiet_zone: cli.quiet_zone,
+        module_size: cli.module_size,
+        size_mode: cli.size_mode,
+        svg_mode: cli.svg_mode,
+        // Asking for text glyphs, colors or polarity means text output
+        terminal_graphics: cli.terminal_graphics.unwrap_or_else(|| {
+            if cli.terminal_glyphs == TerminalGlyphs::HalfBlock
+                && cli.terminal_color == TerminalColor::None
+                && cli.terminal_polarity == TerminalPolarity::Auto
+            {
+                TerminalGraphics::detect()
+            } else {
+                TerminalGraphics::Unicode
+            }
+        }),
+        terminal_glyphs: cli.terminal_glyphs,
+        terminal_color: cli.terminal_color,
+        terminal_polarity: cli.terminal_polarity,
+        quiet_zone: cli
+            .quiet_zone
+            .unwrap_or_else(|| cli.symbol.default_quiet_zone()),
+        matrix_quiet_zone: cli.matrix_quiet_zone,
+        bit_order: cli.bit_order,
+        row_padding: cli.row_padding,
+        array_name: cli.array_name.clone(),
         fg_color,
         bg_color,
-        gradient_color,
+        gradient,
         logo: cli.logo.clone(),
-        ec_level: if cli.logo.is_some() {
-            // Force high error correction if logo is present
-            qrcode::EcLevel::H
-        } else {
-            cli.error_correction.to_qrcode_ecl()
+        logo_scale: cli.logo_scale,
+        logo_padding: cli.logo_padding,
+        logo_knockout: cli.logo_knockout,
+        logo_clear_modules: cli.logo_clear_modules,
+        ec_level: match (&cli.error_correction, &cli.logo) {
+            (Some(level), _) => level.to_qrcode_ecl(),
+            // Default to high error correction if logo is present
+            (None, Some(_)) => qrcode::EcLevel::H,
+            (None, None) => qrcode::EcLevel::M,
         },
+        symbol: cli.symbol,
+        qr_version: cli.qr_version,
+        exact_version: cli.exact_version,
+        mask: cli.mask,
+        encoding_mode: cli.encoding_mode,
+        module_style: cli.module_style,
+        eye_outer_style: cli.eye_outer_style,
+        eye_inner_style: cli.eye_inner_style,
+        eye_outer_color,
+        eye_inner_color,
+        print_size_mm: cli.print_size,
+        page_size: cli.page_size,
+        page_margin_mm: cli.page_margin,
+        jpeg_quality: cli.jpeg_quality,
+        dpi: cli.dpi,
+        caption: cli.caption.clone(),
+        alt_text: cli.alt.clone(),
+        caption_position: cli.caption_position,
+        banner: cli.banner.clone(),
+        frame: cli.frame,
+        frame_color: cli
+            .frame_color
+            .as_deref()
+            .map(Color::from_hex)
+            .transpose()?,
+        structured_append: None,
+        svg_id_prefix: String::new(),
     };
+    let format = cli.output_format();
+    config.validate()?;
 
     // Handle special commands that don't generate QR codes
     if let Some(command) = &cli.command {
         match command {
-            Commands::Decode { input, json } => {
-                let result = decoder::decode(input)?;
-                if *json {
-                    println!(
-                        "{}",
-                        serde_json::json!({
-                            "content": result.content,
-                            "source": input
-                        })
-                    );
-                } else if cli.quiet {
-                    println!("{}", result.content);
-                } else {
-                    println!("{} {}", "📖 Decoded:".green().bold(), result.content);
-                }
-                return Ok(());
+            Commands::Decode { inputs, json } => {
+                return print_decoded(inputs, *json, cli.quiet, cli.verbose);
             }
             Commands::Batch { input, output_dir } => {
+                report_warnings(&color_warnings(&config), cli.strict, cli.quiet)?;
                 if !cli.quiet {
                     println!("{}", "📦 Starting batch generation...".cyan().bold());
                 }
-                let count = batch::process_batch(input, output_dir, &cli.format, &config)?;
+                let report = batch::process_batch(input, output_dir, &format, &config, cli.verify)?;
                 if !cli.quiet {
                     println!(
                         "{} Generated {} QR codes in {}",
                         "✅".green(),
-                        count.to_string().yellow().bold(),
+                        report.generated.to_string().yellow().bold(),
                         output_dir.display().to_string().blue()
                     );
                 }
+                return print_batch_checks(&report, cli.quiet);
+            }
+            Commands::Animate { .. } => {
+                report_warnings(&color_warnings(&config), cli.strict, cli.quiet)?;
+                return run_animate(&cli, &config, format);
+            }
+            Commands::Reassemble { input, to } => {
+                let (file, frames) = animate::reassemble(input)?;
+                if let Some(path) = to {
+                    write_file(path, &file)?;
+                    if !cli.quiet {
+                        println!(
+                            "{} Rebuilt {} bytes from {} frames into {}",
+                            "✅".green(),
+                            file.len(),
+                            frames,
+                            path.display().to_string().blue().bold()
+                        );
+                    }
+                } else {
+                    use std::io::Write;
+                    let mut stdout = std::io::stdout();
+                    stdout
+                        .write_all(&file)
+                        .and_then(|()| stdout.flush())
+                        .map_err(|e| error::QrError::FileWrite {
+                            path: "<stdout>".into(),
+                            source: e,
+                        })?;
+                }
                 return Ok(());
             }
             Commands::Completions { shell } => {
@@ -155,7 +261,11 @@ pub fn run() -> Result<()> {
                 reference,
             } => generate_sepa_string(name, iban, None, *amount, reference.clone(), None),
             // These are handled above
-            Commands::Decode { .. } | Commands::Batch { .. } | Commands::Completions { .. } => {
+            Commands::Decode { .. }
+            | Commands::Animate { .. }
+            | Commands::Reassemble { .. }
+            | Commands::Batch { .. }
+            | Commands::Completions { .. } => {
                 unreachable!()
             }
         }
@@ -163,49 +273,494 @@ pub fn run() -> Result<()> {
         wizard::interactive_mode()?
     };
 
+    let parts = if cli.structured_append {
+        if config.symbol != SymbolType::Qr {
+            return Err(error::QrError::QrGeneration(
+                "Structured Append links standard QR codes; use --symbol qr".to_string(),
+            ));
+        }
+        if config.has_manual_encoding() {
+            return Err(error::QrError::QrGeneration(
+                "Structured Append picks the version, mask and mode of each part itself"
+                    .to_string(),
+            ));
+        }
+        structured::split(&data, config.ec_level)?
+    } else {
+        Vec::new()
+    };
+
     if !cli.quiet {
         println!("{}", "🏴‍☠️ Captain QR at your service!".cyan().bold());
         if cli.verbose {
             println!("{} {}", "📄 Data:".dimmed(), data.dimmed());
+            println!("{} {:?}", "🛡️  Error correction:".dimmed(), config.ec_level);
+            if parts.is_empty() && config.symbol == SymbolType::Qr {
+                let encoded = encoding::encode(&data, &config)?;
+                println!("{} {}", "🔲 Version:".dimmed(), encoded.version);
+                println!("{} {}", "🎭 Mask pattern:".dimmed(), encoded.mask);
+                println!("{} {}", "🔤 Segments:".dimmed(), encoded.segment_names());
+            } else if config.symbol != SymbolType::Qr {
+                let grid = config.encode(&data)?;
+                println!(
+                    "{} {}",
+                    "🔲 Symbol:".dimmed(),
+                    config.symbol.version_name(&grid)
+                );
+            }
+            if parts.len() > 1 {
+                println!(
+                    "{} {} linked codes",
+                    "🧩 Structured Append:".dimmed(),
+                    parts.len()
+                );
+            }
+        }
+    }
+    let mut warnings = color_warnings(&config);
+    if parts.len() > 1 {
+        for &(header, part) in &parts {
+            warnings.extend(structured::part_config(&config, header).print_warning(part)?);
+        }
+        warnings.dedup();
+    } else {
+        warnings.extend(config.print_warning(&data)?);
+    }
+    report_warnings(&warnings, cli.strict, cli.quiet)?;
+    if cli.verify {
+        verify::check_verifiable(format)?;
+    }
+
+    if parts.len() > 1 {
+        return write_set(&parts, &data, &cli, format, &config);
+    }
+    let printed = write_output(&data, &cli.output, format, &config, cli.quiet)?;
+    if cli.verify {
+        verify_written(&data, &cli.output, printed.as_deref(), format, &config)?;
+        if !cli.quiet {
             println!(
-                "{} {:?}",
-                "🛡️  Error correction:".dimmed(),
-                cli.error_correction
+                "{} Verified: the code reads back as the input",
+                "🔍".green()
             );
         }
     }
 
-    // Render based on format
-    match cli.format {
+    Ok(())
+}
+
+/// Show a file as a looping sequence of QR codes
+fn run_animate(cli: &Cli, config: &RenderConfig, format: OutputFormat) -> Result<()> {
+    let Some(Commands::Animate {
+        input,
+        chunk_size,
+        redundancy,
+        fps,
+        loops,
+    }) = &cli.command
+    else {
+        unreachable!("called for the animate command")
+    };
+    let data = if input.as_os_str() == "-" {
+        use std::io::Read;
+        let mut data = Vec::new();
+        std::io::stdin()
+            .read_to_end(&mut data)
+            .map_err(|e| error::QrError::FileRead {
+                path: input.clone(),
+                source: e,
+            })?;
+        data
+    } else {
+        std::fs::read(input).map_err(|e| error::QrError::FileRead {
+            path: input.clone(),
+            source: e,
+        })?
+    };
+    let frames = animate::frames(&data, *chunk_size, *redundancy)?;
+    let texts: Vec<String> = frames.iter().map(animate::Frame::to_text).collect();
+
+    if !cli.quiet {
+        println!("{}", "🏴‍☠️ Captain QR at your service!".cyan().bold());
+        if cli.verbose {
+            let blocks = frames.first().map_or(0, animate::Frame::block_count);
+            println!(
+                "{} {} bytes in {} frames ({} data, {} fountain-coded)",
+                "🎞️  Animation:".dimmed(),
+                data.len(),
+                frames.len(),
+                blocks,
+                frames.len() - blocks
+            );
+        }
+    }
+
+    let bytes = match format {
         OutputFormat::Terminal => {
-            let qr = render_to_terminal(&data, &config)?;
-            println!("{qr}");
+            return animate::play_terminal(&texts, *fps, *loops, config);
+        }
+        OutputFormat::Gif => animate::encode_gif(&texts, *fps, config)?,
+        OutputFormat::Png => animate::encode_apng(&texts, *fps, config)?,
+        _ => {
+            return Err(error::QrError::InvalidAnimation(
+                "animations are written as GIF, PNG (APNG) or a terminal slideshow".to_string(),
+            ))
+        }
+    };
+    write_file(&cli.output, &bytes)?;
+    if !cli.quiet {
+        println!(
+            "{} Saved {} frames to {}",
+            "✅".green(),
+            frames.len(),
+            cli.output.display().to_string().blue().bold()
+        );
+    }
+    if cli.verify {
+        match animate::reassemble(&cli.output) {
+            Ok((file, _)) if file == data => {}
+            Ok(_) => {
+                return Err(error::QrError::VerificationFailed(
+                    "the frames rebuild a different file".to_string(),
+                ))
+            }
+            Err(e) => {
+                return Err(error::QrError::VerificationFailed(format!(
+                    "the frames could not be read back ({e})"
+                )))
+            }
+        }
+        if !cli.quiet {
+            println!("{} Verified: the frames rebuild the file", "🔍".green());
+        }
+    }
+    Ok(())
+}
+
+/// Decode each input and print what it holds. The codes of a Structured
+/// Append set, on one image or several, print as the payload they make up.
+fn print_decoded(inputs: &[String], json: bool, quiet: bool, verbose: bool) -> Result<()> {
+    let mut results = Vec::new();
+    for input in inputs {
+        let result = decoder::decode(input)?;
+        if result.structured_append.is_some() {
+            // The image may be a sheet holding the rest of the set
+            let rest = decoder::decode_all(input).unwrap_or_default();
+            results.extend(rest.into_iter().map(|other| (input, other)));
+        }
+        results.push((input, result));
+    }
+
+    let Some(header) = results
+        .iter()
+        .find_map(|(_, result)| result.structured_append)
+    else {
+        for (input, result) in results {
+            if json {
+                println!(
+                    "{}",
+                    serde_json::json!({
+                        "content": result.content,
+                        "format": result.format,
+                        "source": input
+                    })
+                );
+            } else if quiet {
+                println!("{}", result.content);
+            } else {
+                println!("{} {}", "📖 Decoded:".green().bold(), result.content);
+                if verbose {
+                    println!("{} {}", "🔲 Format:".dimmed(), result.format);
+                }
+            }
+        }
+        return Ok(());
+    };
+
+    let parts: Vec<_> = results.into_iter().map(|(_, result)| result).collect();
+    let content = structured::reassemble(&parts)?;
+    if json {
+        println!(
+            "{}",
+            serde_json::json!({
+                "content": content,
+                "format": "qrcode",
+                "parts": header.total,
+                "sources": inputs
+            })
+        );
+    } else if quiet {
+        println!("{content}");
+    } else {
+        println!("{} {}", "📖 Decoded:".green().bold(), content);
+        if verbose {
+            println!(
+                "{} {} linked codes",
+                "🧩 Structured Append:".dimmed(),
+                header.total
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Write the codes of a Structured Append set: numbered files, one sheet,
+/// or one after another on the terminal
+fn write_set(
+    parts: &[(structured::StructuredAppend, &str)],
+    data: &str,
+    cli: &Cli,
+    format: OutputFormat,
+    config: &RenderConfig,
+) -> Result<()> {
+    if cli.sheet {
+        match (format, format.image_format()) {
+            (_, Some(image_format)) => {
+                let bytes = structured::render_sheet_image(parts, image_format, config)?;
+                write_file(&cli.output, bytes.as_slice())?;
+            }
+            (OutputFormat::Svg, None) => {
+                let svg = structured::render_sheet_svg(parts, config)?;
+                write_file(&cli.output, svg.as_bytes())?;
+            }
+            _ => {
+                return Err(error::QrError::InvalidLayout(
+                    "--sheet needs a raster or SVG output format".to_string(),
+                ))
+            }
         }
-        OutputFormat::Png => {
-            render_to_png(&data, &cli.output, &config)?;
+        if !cli.quiet {
+            println!(
+                "{} Saved {} linked codes to {}",
+                "✅".green(),
+                parts.len(),
+                cli.output.display().to_string().blue().bold()
+            );
+        }
+        if cli.verify {
+            verify::verify_sheet(data, &cli.output)?;
             if !cli.quiet {
+                println!(
+                    "{} Verified: the codes read back as the input",
+                    "🔍".green()
+                );
+            }
+        }
+        return Ok(());
+    }
+
+    for &(header, part) in parts {
+        let config = structured::part_config(config, header);
+        let path = structured::part_path(&cli.output, header);
+        if !cli.quiet && format.is_printed() {
+            println!(
+                "{}",
+                format!("🧩 Part {} of {}", header.index + 1, header.total)
+                    .cyan()
+                    .bold()
+            );
+        }
+        let printed = write_output(part, &path, format, &config, cli.quiet)?;
+        if cli.verify {
+            verify_written(part, &path, printed.as_deref(), format, &config)?;
+        }
+    }
+    if cli.verify && !cli.quiet {
+        println!(
+            "{} Verified: every code reads back as its part of the input",
+            "🔍".green()
+        );
+    }
+    Ok(())
+}
+
+/// Check the code as printed, or else as saved to `path`
+fn verify_written(
+    data: &str,
+    path: &std::path::Path,
+    printed: Option<&str>,
+    format: OutputFormat,
+    config: &RenderConfig,
+) -> Result<()> {
+    printed.map_or_else(
+        || verify::verify_output(data, path, format, config),
+        |text| verify::verify_printed(data, text, format, config),
+    )
+}
+
+/// Write a file, reporting the path on failure
+fn write_file(path: &std::path::Path, bytes: &[u8]) -> Result<()> {
+    std::fs::write(path, bytes).map_err(|e| error::QrError::FileWrite {
+        path: path.to_path_buf(),
+        source: e,
+    })
+}
+
+/// Render one code in `format`: print it for terminal, base64, HTML snippet
+/// and Markdown output, else save it to `output`. Returns the printed text.
+fn write_output(
+    data: &str,
+    output: &std::path::PathBuf,
+    format: OutputFormat,
+    config: &RenderConfig,
+    quiet: bool,
+) -> Result<Option<String>> {
+    let printed = match format {
+        OutputFormat::Terminal => render_to_terminal(data, config)?,
+        OutputFormat::Base64 => render_to_base64(data, config)?,
+        OutputFormat::Html => embed::render_to_img_tag(data, config)?,
+        OutputFormat::HtmlSvg => embed::render_to_inline_svg(data, config)?,
+        OutputFormat::Markdown => embed::render_to_markdown(data, config)?,
+        _ => {
+            save_output(data, output, format, config, quiet)?;
+            return Ok(None);
+        }
+    };
+    println!("{printed}");
+    Ok(Some(printed))
+}
+
+/// Save one code in a file format to `output`
+fn save_output(
+    data: &str,
+    output: &std::path::PathBuf,
+    format: OutputFormat,
+    config: &RenderConfig,
+    quiet: bool,
+) -> Result<()> {
+    match format {
+        OutputFormat::Png
+        | OutputFormat::Jpeg
+        | OutputFormat::Webp
+        | OutputFormat::Gif
+        | OutputFormat::Bmp
+        | OutputFormat::Tiff => {
+            let image_format = format.image_format().expect("raster output format");
+            render_to_image(data, output, image_format, config)?;
+            if !quiet {
                 println!(
                     "{} Saved to {}",
                     "✅".green(),
-                    cli.output.display().to_string().blue().bold()
+                    output.display().to_string().blue().bold()
                 );
             }
         }
         OutputFormat::Svg => {
-            render_to_svg_file(&data, &cli.output, &config)?;
-            if !cli.quiet {
+            render_to_svg_file(data, output, config)?;
+            if !quiet {
                 println!(
                     "{} Saved to {}",
                     "✅".green(),
-                    cli.output.display().to_string().blue().bold()
+                    output.display().to_string().blue().bold()
                 );
             }
         }
-        OutputFormat::Base64 => {
-            let b64 = render_to_base64(&data, &config)?;
-            println!("{b64}");
+        OutputFormat::Eps => {
+            eps::render_to_eps_file(data, output, config)?;
+            if !quiet {
+                println!(
+                    "{} Saved to {}",
+                    "✅".green(),
+                    output.display().to_string().blue().bold()
+                );
+            }
+        }
+        OutputFormat::Pdf => {
+            pdf::render_to_pdf_file(data, output, config)?;
+            if !quiet {
+                println!(
+                    "{} Saved to {}",
+                    "✅".green(),
+                    output.display().to_string().blue().bold()
+                );
+            }
+        }
+        OutputFormat::Terminal
+        | OutputFormat::Base64
+        | OutputFormat::Html
+        | OutputFormat::HtmlSvg
+        | OutputFormat::Markdown => unreachable!("printed by write_output"),
+        OutputFormat::MatrixJson
+        | OutputFormat::MatrixCsv
+        | OutputFormat::MatrixText
+        | OutputFormat::Pbm
+        | OutputFormat::Pgm
+        | OutputFormat::CHeader
+        | OutputFormat::Rust
+        | OutputFormat::Python => {
+            write_file(output, &matrix::render_matrix(data, format, config)?)?;
+            if !quiet {
+                println!(
+                    "{} Saved to {}",
+                    "✅".green(),
+                    output.display().to_string().blue().bold()
+                );
+            }
+        }
+        OutputFormat::HtmlPage => {
+            write_file(output, embed::render_to_html_page(data, config)?.as_bytes())?;
+            if !quiet {
+                println!(
+                    "{} Saved to {}",
+                    "✅".green(),
+                    output.display().to_string().blue().bold()
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Color lint warnings as printable messages
+fn color_warnings(config: &RenderConfig) -> Vec<String> {
+    config
+        .lint_colors()
+        .iter()
+        .map(ToString::to_string)
+        .collect()
+}
+
+/// Print warnings, or fail on the first one in strict mode
+fn report_warnings(warnings: &[String], strict: bool, quiet: bool) -> Result<()> {
+    if let (Some(warning), true) = (warnings.first(), strict) {
+        return Err(error::QrError::StrictWarning(warning.clone()));
+    }
+    if !quiet {
+        for warning in warnings {
+            eprintln!("{} {}", "⚠️  Warning:".yellow().bold(), warning);
         }
     }
+    Ok(())
+}
 
+/// Print the per-record verification results of a batch run, failing if any
+/// code didn't read back
+fn print_batch_checks(report: &batch::BatchReport, quiet: bool) -> Result<()> {
+    if report.checks.is_empty() {
+        return Ok(());
+    }
+    for check in &report.checks {
+        match &check.error {
+            None if !quiet => println!("  {} {}", "✔".green(), check.filename),
+            None => {}
+            Some(error) => eprintln!("  {} {}: {}", "✘".red(), check.filename, error),
+        }
+    }
+
+    let failed = report.failures().count();
+    if !quiet {
+        println!(
+            "{} {} passed, {} failed",
+            "🔍 Verification:".cyan().bold(),
+            (report.checks.len() - failed).to_string().green(),
+            failed.to_string().red()
+        );
+    }
+    if failed > 0 {
+        return Err(error::QrError::VerificationFailed(format!(
+            "{failed} of {} codes didn't read back",
+            report.checks.len()
+        )));
+    }
     Ok(())
 }
@@ -0,0 +1,199 @@
+//! Color accessibility lint: contrast, polarity and color-vision deficiencies
+
+use std::fmt;
+
+use crate::renderer::{Color, RenderConfig};
+
+/// Minimum contrast ratio every foreground color needs against the background.
+///
+/// This is the WCAG 2.1 threshold for non-text graphics; below it many phone
+/// cameras stop telling dark from light modules.
+pub const MIN_CONTRAST_RATIO: f64 = 3.0;
+
+/// Common color-vision deficiencies, simulated at full severity
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Deficiency {
+    /// No red cones
+    Protanopia,
+    /// No green cones
+    Deuteranopia,
+    /// No blue cones
+    Tritanopia,
+}
+
+impl Deficiency {
+    pub const ALL: [Self; 3] = [Self::Protanopia, Self::Deuteranopia, Self::Tritanopia];
+
+    /// Simulation matrix in linear RGB (Machado, Oliveira & Fernandes 2009)
+    const fn matrix(self) -> [[f64; 3]; 3] {
+        match self {
+            Self::Protanopia => [
+                [0.152_286, 1.052_583, -0.204_868],
+                [0.114_503, 0.786_281, 0.099_216],
+                [-0.003_882, -0.048_116, 1.051_998],
+            ],
+            Self::Deuteranopia => [
+                [0.367_322, 0.860_646, -0.227_968],
+                [0.280_085, 0.672_501, 0.047_413],
+                [-0.011_820, 0.042_940, 0.968_881],
+            ],
+            Self::Tritanopia => [
+                [1.255_528, -0.076_749, -0.178_779],
+                [-0.078_411, 0.930_809, 0.147_602],
+                [0.004_733, 0.691_367, 0.303_900],
+            ],
+        }
+    }
+
+    /// How an opaque color appears with this deficiency
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    #[must_use]
+    pub fn simulate(self, color: Color) -> Color {
+        let linear = [color.r, color.g, color.b].map(|c| {
+            let c = f64::from(c) / 255.0;
+            if c <= 0.040_45 {
+                c / 12.92
+            } else {
+                ((c + 0.055) / 1.055).powf(2.4)
+            }
+        });
+        let [r, g, b] = self.matrix().map(|row| {
+            let c = row[2]
+                .mul_add(linear[2], row[0].mul_add(linear[0], row[1] * linear[1]))
+                .clamp(0.0, 1.0);
+            let encoded = if c <= 0.003_130_8 {
+                c * 12.92
+            } else {
+                1.055f64.mul_add(c.powf(1.0 / 2.4), -0.055)
+            };
+            (encoded * 255.0).round() as u8
+        });
+        Color::rgba(r, g, b, color.a)
+    }
+}
+
+impl fmt::Display for Deficiency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Protanopia => "protanopia (red-blind)",
+            Self::Deuteranopia => "deuteranopia (green-blind)",
+            Self::Tritanopia => "tritanopia (blue-blind)",
+        })
+    }
+}
+
+/// A color choice that may stop the code from scanning or being noticed
+#[derive(Clone, Debug, PartialEq)]
+pub enum ColorWarning {
+    /// A foreground color too close to the background in luminance
+    LowContrast {
+        role: String,
+        color: String,
+        ratio: f64,
+    },
+    /// Light modules on a dark background
+    Inverted,
+    /// Enough contrast for typical vision, but not with a color-vision deficiency
+    ColorBlind {
+        role: String,
+        color: String,
+        deficiency: Deficiency,
+        ratio: f64,
+    },
+}
+
+impl fmt::Display for ColorWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::LowContrast { role, color, ratio } => write!(
+                f,
+                "{role} {color} has a contrast ratio of {ratio:.2}:1 against the background; \
+                 at least {MIN_CONTRAST_RATIO}:1 is needed to scan reliably"
+            ),
+            Self::Inverted => f.write_str(
+                "the modules are lighter than the background; many scanners only read \
+                 dark-on-light codes",
+            ),
+            Self::ColorBlind {
+                role,
+                color,
+                deficiency,
+                ratio,
+            } => write!(
+                f,
+                "{role} {color} drops to {ratio:.2}:1 against the background with {deficiency}"
+            ),
+        }
+    }
+}
+
+impl RenderConfig {
+    /// Check the foreground colors against the background.
+    ///
+    /// Covers the module fill (every gradient stop) and the finder colors,
+    /// with translucent colors compared as they appear on a white page.
+    #[must_use]
+    pub fn lint_colors(&self) -> Vec<ColorWarning> {
+        let background = self.bg_color.over(Color::rgb(255, 255, 255));
+        let fills: Vec<(String, Color)> = self.gradient.as_ref().map_or_else(
+            || vec![("foreground".to_string(), self.fg_color)],
+            |gradient| {
+                gradient
+                    .stops
+                    .iter()
+                    .enumerate()
+                    .map(|(i, stop)| (format!("gradient stop {}", i + 1), stop.color))
+                    .collect()
+            },
+        );
+
+        let mut warnings = Vec::new();
+        let inverted = fills.iter().all(|(_, color)| {
+            color.over(background).relative_luminance() > background.relative_luminance()
+        });
+        if inverted {
+            warnings.push(ColorWarning::Inverted);
+        }
+
+        let eyes = [
+            ("eye outer color", self.eye_outer_color),
+            ("eye inner color", self.eye_inner_color),
+        ];
+        let colors = fills.into_iter().chain(
+            eyes.into_iter()
+                .filter_map(|(role, color)| Some((role.to_string(), color?))),
+        );
+        for (role, color) in colors {
+            let ratio = color.contrast_ratio(background);
+            if ratio < MIN_CONTRAST_RATIO {
+                warnings.push(ColorWarning::LowContrast {
+                    role,
+                    color: color.to_hex(),
+                    ratio,
+                });
+                continue;
+            }
+
+            // Report the deficiency that loses the most contrast
+            let foreground = color.over(background);
+            let worst = Deficiency::ALL
+                .into_iter()
+                .map(|deficiency| {
+                    let ratio = deficiency
+                        .simulate(foreground)
+                        .contrast_ratio(deficiency.simulate(background));
+                    (deficiency, ratio)
+                })
+                .min_by(|a, b| a.1.total_cmp(&b.1));
+            if let Some((deficiency, ratio)) = worst.filter(|(_, r)| *r < MIN_CONTRAST_RATIO) {
+                warnings.push(ColorWarning::ColorBlind {
+                    role,
+                    color: color.to_hex(),
+                    deficiency,
+                    ratio,
+                });
+            }
+        }
+        warnings
+    }
+}
@@ -0,0 +1,313 @@
+//! Logo placement: size, background knockout and error correction budget
+
+#![allow(clippy::cast_precision_loss)]
+
+use std::path::PathBuf;
+
+use clap::ValueEnum;
+use image::imageops::{resize, FilterType};
+use image::{DynamicImage, ImageBuffer, RgbaImage};
+use qrcode::EcLevel;
+use resvg::{tiny_skia, usvg};
+
+use crate::error::{QrError, Result};
+use crate::renderer::RenderConfig;
+use crate::shapes::{fmt_num, ModuleGrid, RoundedBox};
+
+/// Share of the error correction capacity a logo may use up; the rest is
+/// left for print defects, glare and wear
+const EC_BUDGET_SHARE: f64 = 0.75;
+
+/// Corner radius of a rounded knockout, relative to its side
+const ROUNDED_KNOCKOUT_RADIUS: f64 = 0.2;
+
+/// Shape of the background-colored area behind the logo
+#[derive(Clone, Copy, ValueEnum, Debug, Default, PartialEq, Eq)]
+pub enum LogoKnockout {
+    /// Draw the logo straight over the modules
+    #[default]
+    None,
+    Square,
+    Rounded,
+    Circle,
+}
+
+/// Fraction of damaged codewords each error correction level can restore
+#[must_use]
+pub const fn recoverable_fraction(level: EcLevel) -> f64 {
+    match level {
+        EcLevel::L => 0.07,
+        EcLevel::M => 0.15,
+        EcLevel::Q => 0.25,
+        EcLevel::H => 0.30,
+    }
+}
+
+/// Logo geometry in module units, with the origin at the top-left corner of
+/// the quiet zone
+#[derive(Clone, Debug, PartialEq)]
+pub struct LogoLayout {
+    /// Left edge of the (square) logo
+    pub x: f64,
+    /// Top edge of the logo
+    pub y: f64,
+    /// Side of the logo
+    pub size: f64,
+    /// Area filled with the background color behind the logo
+    pub knockout: Option<RoundedBox>,
+    quiet_zone: f64,
+}
+
+impl LogoLayout {
+    /// Center the logo on the symbol, or `None` without a logo.
+    ///
+    /// Fails when the logo hides more modules than the error correction
+    /// level can safely make up for.
+    pub fn new(config: &RenderConfig, grid: &ModuleGrid) -> Result<Option<Self>> {
+        if config.logo.is_none() {
+            return Ok(None);
+        }
+        if !(config.logo_scale > 0.0 && config.logo_scale < 1.0) {
+            return Err(QrError::InvalidSize(format!(
+                "logo scale must be between 0 and 1, got {}",
+                config.logo_scale
+            )));
+        }
+        if !(config.logo_padding >= 0.0 && config.logo_padding.is_finite()) {
+            return Err(QrError::InvalidSize(format!(
+                "logo padding must be zero or more modules, got {}",
+                config.logo_padding
+            )));
+        }
+
+        let quiet_zone = f64::from(config.quiet_zone);
+        let total_width = 2.0f64.mul_add(quiet_zone, grid.width() as f64);
+        let total_height = 2.0f64.mul_add(quiet_zone, grid.height() as f64);
+        // Rectangular symbols size the logo by their shorter side
+        let size = total_width.min(total_height) * config.logo_scale;
+
+        let knockout_side = 2.0f64.mul_add(config.logo_padding, size);
+        let radius = match config.logo_knockout {
+            LogoKnockout::None => None,
+            LogoKnockout::Square => Some(0.0),
+            LogoKnockout::Rounded => Some(knockout_side * ROUNDED_KNOCKOUT_RADIUS),
+            LogoKnockout::Circle => Some(knockout_side / 2.0),
+        };
+        let knockout = radius.map(|radius| RoundedBox {
+            x: (total_width - knockout_side) / 2.0,
+            y: (total_height - knockout_side) / 2.0,
+            width: knockout_side,
+            height: knockout_side,
+            radius,
+        });
+
+        let layout = Self {
+            x: (total_width - size) / 2.0,
+            y: (total_height - size) / 2.0,
+            size,
+            knockout,
+            quiet_zone,
+        };
+        layout.check_coverage(grid, config.ec_level)?;
+        Ok(Some(layout))
+    }
+
+    /// Area hidden by the logo: the knockout, or else the logo itself
+    fn hidden_area(&self) -> RoundedBox {
+        self.knockout.unwrap_or(RoundedBox {
+            x: self.x,
+            y: self.y,
+            width: self.size,
+            height: self.size,
+            radius: 0.0,
+        })
+    }
+
+    /// Whether the logo hides any part of symbol module `(x, y)`
+    #[must_use]
+    pub fn covers(&self, x: usize, y: usize) -> bool {
+        const EPSILON: f64 = 1e-9;
+
+        let area = self.hidden_area();
+        let radius = area.radius.min(area.width / 2.0).min(area.height / 2.0);
+        let left = x as f64 + self.quiet_zone;
+        let top = y as f64 + self.quiet_zone;
+
+        // A rounded box is its inner rectangle grown by the radius, so the
+        // module overlaps it when the gap to that rectangle is below the radius
+        let gap_x = (area.x + radius - (left + 1.0)).max(left - (area.x + area.width - radius));
+        let gap_y = (area.y + radius - (top + 1.0)).max(top - (area.y + area.height - radius));
+        if gap_x < -EPSILON && gap_y < -EPSILON {
+            return true;
+        }
+        let (gap_x, gap_y) = (gap_x.max(0.0), gap_y.max(0.0));
+        gap_x.mul_add(gap_x, gap_y * gap_y) < radius * radius - EPSILON
+    }
+
+    /// Number of symbol modules the logo hides, wholly or in part
+    #[must_use]
+    pub fn covered_modules(&self, grid: &ModuleGrid) -> usize {
+        let width = grid.width();
+        (0..width * grid.height())
+            .filter(|&i| self.covers(i % width, i / width))
+            .count()
+    }
+
+    /// Turn every module the logo touches light, so none is cut part-way.
+    ///
+    /// Finder patterns are left alone.
+    pub fn clear_modules(&self, grid: &mut ModuleGrid) {
+        for y in 0..grid.height() {
+            for x in 0..grid.width() {
+                if self.covers(x, y) && !grid.is_finder(x, y) {
+                    grid.set_light(x, y);
+                }
+            }
+        }
+    }
+
+    /// Turn light every module whose center falls inside the knockout.
+    ///
+    /// A transparent background can't paint over the modules, so they are
+    /// left out instead. Finder patterns are left alone.
+    pub fn clear_knockout(&self, grid: &mut ModuleGrid) {
+        let Some(knockout) = &self.knockout else {
+            return;
+        };
+        for y in 0..grid.height() {
+            for x in 0..grid.width() {
+                let center_x = x as f64 + self.quiet_zone + 0.5;
+                let center_y = y as f64 + self.quiet_zone + 0.5;
+                if knockout.contains(center_x, center_y) && !grid.is_finder(x, y) {
+                    grid.set_light(x, y);
+                }
+            }
+        }
+    }
+
+    fn check_coverage(&self, grid: &ModuleGrid, level: EcLevel) -> Result<()> {
+        let total = grid.width() * grid.height();
+        let coverage = self.covered_modules(grid) as f64 / total as f64;
+        let max = recoverable_fraction(level) * EC_BUDGET_SHARE;
+        if coverage > max {
+            return Err(QrError::LogoTooLarge {
+                coverage: coverage * 100.0,
+                max: max * 100.0,
+                level: format!("{level:?}"),
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Logo artwork: a bitmap, or SVG kept as vectors wherever the output allows
+pub enum LogoImage {
+    Raster(DynamicImage),
+    Vector(Box<usvg::Tree>),
+}
+
+impl LogoImage {
+    /// Load a logo; `.svg` and `.svgz` files are read as vector artwork
+    pub fn load(path: &PathBuf) -> Result<Self> {
+        let is_svg = path.extension().is_some_and(|extension| {
+            extension.eq_ignore_ascii_case("svg") || extension.eq_ignore_ascii_case("svgz")
+        });
+        if !is_svg {
+            return image::open(path)
+                .map(Self::Raster)
+                .map_err(|e| QrError::FileWrite {
+                    path: path.clone(),
+                    source: std::io::Error::new(
+                        std::io::ErrorKind::Other,
+                        format!("Failed to load logo: {e}"),
+                    ),
+                });
+        }
+
+        let data = std::fs::read(path).map_err(|e| QrError::FileRead {
+            path: path.clone(),
+            source: e,
+        })?;
+        let tree = usvg::Tree::from_data(&data, &usvg::Options::default()).map_err(|e| {
+            QrError::ImageError(format!("Invalid SVG logo '{}': {e}", path.display()))
+        })?;
+        Ok(Self::Vector(Box::new(tree)))
+    }
+
+    /// Square bitmap `size` pixels wide. Raster logos are stretched to fill
+    /// it; vector logos keep their aspect ratio and are centered.
+    pub fn to_rgba(&self, size: u32) -> Result<RgbaImage> {
+        match self {
+            Self::Raster(image) => Ok(resize(&image.to_rgba8(), size, size, FilterType::Lanczos3)),
+            Self::Vector(tree) => rasterize(tree, size),
+        }
+    }
+
+    /// Bitmap for embedding at print size: raster logos at their own
+    /// resolution, vector logos rendered `vector_size` pixels wide
+    pub fn to_print_rgba(&self, vector_size: u32) -> Result<RgbaImage> {
+        match self {
+            Self::Raster(image) => Ok(image.to_rgba8()),
+            Self::Vector(tree) => rasterize(tree, vector_size),
+        }
+    }
+
+    /// Nested `<svg>` element showing a vector logo in the square at
+    /// `(x, y)`, or `None` for raster logos. Its ids start with `id_prefix`.
+    #[must_use]
+    pub fn to_svg(&self, x: f64, y: f64, size: f64, id_prefix: &str) -> Option<String> {
+        let Self::Vector(tree) = self else {
+            return None;
+        };
+        let markup = tree.to_string(&usvg::WriteOptions {
+            // Keep the logo's ids apart from the code's own (`grad`)
+            id_prefix: Some(format!("{id_prefix}logo-")),
+            indent: usvg::Indent::None,
+            ..usvg::WriteOptions::default()
+        });
+        let markup = markup
+            .find("<svg")
+            .map_or(markup.as_str(), |start| &markup[start..]);
+        let tree_size = tree.size();
+        Some(format!(
+            r#"<svg x="{}" y="{}" width="{}" height="{}" viewBox="0 0 {} {}">{markup}</svg>"#,
+            fmt_num(x),
+            fmt_num(y),
+            fmt_num(size),
+            fmt_num(size),
+            fmt_num(f64::from(tree_size.width())),
+            fmt_num(f64::from(tree_size.height()))
+        ))
+    }
+}
+
+/// Render SVG artwork into a transparent square `size` pixels wide
+fn rasterize(tree: &usvg::Tree, size: u32) -> Result<RgbaImage> {
+    let mut pixmap = tiny_skia::Pixmap::new(size, size)
+        .ok_or_else(|| QrError::ImageError(format!("Can't render a {size}px logo")))?;
+
+    let tree_size = tree.size();
+    let pixels = size as f32;
+    let scale = (pixels / tree_size.width()).min(pixels / tree_size.height());
+    let transform = tiny_skia::Transform::from_row(
+        scale,
+        0.0,
+        0.0,
+        scale,
+        tree_size.width().mul_add(-scale, pixels) / 2.0,
+        tree_size.height().mul_add(-scale, pixels) / 2.0,
+    );
+    resvg::render(tree, transform, &mut pixmap.as_mut());
+
+    // tiny-skia stores premultiplied alpha
+    let data = pixmap
+        .pixels()
+        .iter()
+        .flat_map(|pixel| {
+            let color = pixel.demultiply();
+            [color.red(), color.green(), color.blue(), color.alpha()]
+        })
+        .collect();
+    ImageBuffer::from_raw(size, size, data)
+        .ok_or_else(|| QrError::ImageError("Logo bitmap has the wrong size".to_string()))
+}
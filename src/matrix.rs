@@ -0,0 +1,418 @@
+//! Raw module matrix output for devices that draw the code themselves
+//!
+//! JSON with symbol metadata, CSV, netpbm PBM and PGM, a plain 0/1 grid, and
+//! packed bitmaps as C, Rust or Python source to compile into firmware.
+//!
+//! Rows run top to bottom and columns left to right, with 1 (or black) for a
+//! dark module. The quiet zone is left out unless `matrix_quiet_zone` is set.
+
+use std::fmt::Write as _;
+use std::path::Path;
+
+use clap::ValueEnum;
+use image::{GrayImage, Luma};
+
+use crate::cli::OutputFormat;
+use crate::error::{QrError, Result};
+use crate::renderer::RenderConfig;
+
+/// Pixels per module when a matrix is drawn back for decoding
+const PREVIEW_MODULE_SIZE: u32 = 8;
+
+/// Modules of light margin around a matrix drawn back for decoding
+const PREVIEW_QUIET_ZONE: u32 = 4;
+
+/// Bytes per line of packed source output when rows run on
+const BYTES_PER_LINE: usize = 12;
+
+/// Which bit of a packed byte holds the leftmost module
+#[derive(Clone, Copy, ValueEnum, Debug, Default, PartialEq, Eq)]
+pub enum BitOrder {
+    /// Most significant bit first, as in PBM and most display drivers
+    #[default]
+    Msb,
+    /// Least significant bit first
+    Lsb,
+}
+
+/// How rows of a packed bitmap fill their bytes
+#[derive(Clone, Copy, ValueEnum, Debug, Default, PartialEq, Eq)]
+pub enum RowPadding {
+    /// Each row starts on a new byte, with unused bits cleared
+    #[default]
+    Byte,
+    /// Rows follow on bit by bit; only the last byte is padded
+    None,
+}
+
+/// The encoded modules, with the quiet zone when configured
+pub struct Matrix {
+    /// Rows of modules, `true` for dark
+    pub rows: Vec<Vec<bool>>,
+    /// Version name, as in verbose output: `7`, `M2`, `R7x43` or a size
+    pub version: String,
+    /// Light modules added on each side
+    pub quiet_zone: u32,
+}
+
+impl Matrix {
+    /// Encode `data` and lay out its modules
+    pub fn encode(data: &str, config: &RenderConfig) -> Result<Self> {
+        let grid = config.encode(data)?;
+        let quiet_zone = if config.matrix_quiet_zone {
+            config.quiet_zone
+        } else {
+            0
+        };
+        let quiet = quiet_zone as usize;
+        let rows = (0..grid.height() + quiet * 2)
+            .map(|y| {
+                (0..grid.width() + quiet * 2)
+                    .map(|x| {
+                        x.checked_sub(quiet)
+                            .zip(y.checked_sub(quiet))
+                            .is_some_and(|(x, y)| grid.is_dark(x, y))
+                    })
+                    .collect()
+            })
+            .collect();
+        Ok(Self {
+            rows,
+            version: config.symbol.version_name(&grid),
+            quiet_zone,
+        })
+    }
+
+    /// Number of columns
+    #[must_use]
+    pub fn width(&self) -> usize {
+        self.rows.first().map_or(0, Vec::len)
+    }
+
+    /// Number of rows
+    #[must_use]
+    pub fn height(&self) -> usize {
+        self.rows.len()
+    }
+
+    /// Rows of `1` and `0` joined by `separator`, one per line
+    fn digits(&self, separator: &str) -> String {
+        self.rows
+            .iter()
+            .map(|row| {
+                let digits: Vec<&str> = row
+                    .iter()
+                    .map(|&dark| if dark { "1" } else { "0" })
+                    .collect();
+                digits.join(separator) + "\n"
+            })
+            .collect()
+    }
+}
+
+/// Render the module matrix of `data` in one of the matrix formats
+pub fn render_matrix(data: &str, format: OutputFormat, config: &RenderConfig) -> Result<Vec<u8>> {
+    let matrix = Matrix::encode(data, config)?;
+    Ok(match format {
+        OutputFormat::MatrixJson => to_json(&matrix, config).into_bytes(),
+        OutputFormat::MatrixCsv => matrix.digits(",").into_bytes(),
+        OutputFormat::MatrixText => matrix.digits("").into_bytes(),
+        OutputFormat::Pbm => to_pbm(&matrix),
+        OutputFormat::Pgm => to_pgm(&matrix),
+        OutputFormat::CHeader | OutputFormat::Rust | OutputFormat::Python => {
+            to_source(&matrix, format, config).into_bytes()
+        }
+        _ => unreachable!("not a matrix format"),
+    })
+}
+
+/// Pack modules into bytes, dark modules as set bits
+#[must_use]
+pub fn pack(rows: &[Vec<bool>], order: BitOrder, padding: RowPadding) -> Vec<u8> {
+    let byte = |bits: &[bool]| {
+        bits.iter()
+            .enumerate()
+            .filter(|(_, &dark)| dark)
+            .fold(0u8, |byte, (i, _)| match order {
+                BitOrder::Msb => byte | 0x80 >> i,
+                BitOrder::Lsb => byte | 1 << i,
+            })
+    };
+    match padding {
+        RowPadding::Byte => rows
+            .iter()
+            .flat_map(|row| row.chunks(8).map(byte))
+            .collect(),
+        RowPadding::None => rows.concat().chunks(8).map(byte).collect(),
+    }
+}
+
+/// Unpack bytes written by [`pack`] into `height` rows of `width` modules;
+/// `None` when there are too few bytes
+#[must_use]
+pub fn unpack(
+    bytes: &[u8],
+    width: usize,
+    height: usize,
+    order: BitOrder,
+    padding: RowPadding,
+) -> Option<Vec<Vec<bool>>> {
+    let stride = match padding {
+        RowPadding::Byte => (width + 7) / 8 * 8,
+        RowPadding::None => width,
+    };
+    let bit = |index: usize| {
+        let byte = bytes.get(index / 8)?;
+        let shift = match order {
+            BitOrder::Msb => 7 - index % 8,
+            BitOrder::Lsb => index % 8,
+        };
+        Some(byte >> shift & 1 == 1)
+    };
+    (0..height)
+        .map(|y| (0..width).map(|x| bit(y * stride + x)).collect())
+        .collect()
+}
+
+/// Packed bitmap as a C header, Rust constants or a Python list, with the
+/// size and packing in constants and a leading comment
+fn to_source(matrix: &Matrix, format: OutputFormat, config: &RenderConfig) -> String {
+    let name = &config.array_name;
+    let upper = name.to_uppercase();
+    let (width, height) = (matrix.width(), matrix.height());
+    let bytes = pack(&matrix.rows, config.bit_order, config.row_padding);
+    let stride = (width + 7) / 8;
+
+    let level = if config.symbol.is_qr_family() {
+        format!(", error correction {:?}", config.ec_level)
+    } else {
+        String::new()
+    };
+    let packing = match (config.bit_order, config.row_padding) {
+        (BitOrder::Msb, RowPadding::Byte) => "MSB first, rows padded to whole bytes",
+        (BitOrder::Lsb, RowPadding::Byte) => "LSB first, rows padded to whole bytes",
+        (BitOrder::Msb, RowPadding::None) => "MSB first, rows unpadded",
+        (BitOrder::Lsb, RowPadding::None) => "LSB first, rows unpadded",
+    };
+    let summary = format!(
+        "{} version {}{level}, {width}x{height} modules, {packing}; set bits are dark",
+        config.symbol.name(),
+        matrix.version
+    );
+
+    // A padded row per line; otherwise lines of equal length
+    let per_line = match config.row_padding {
+        RowPadding::Byte => stride.max(1),
+        RowPadding::None => BYTES_PER_LINE,
+    };
+    let lines: Vec<String> = bytes
+        .chunks(per_line)
+        .map(|line| {
+            let hex: Vec<String> = line.iter().map(|byte| format!("0x{byte:02x}")).collect();
+            format!("    {},", hex.join(", "))
+        })
+        .collect();
+    let body = lines.join("\n");
+
+    let mut source = String::new();
+    match format {
+        OutputFormat::CHeader => {
+            let guard = format!("{upper}_H");
+            let _ = writeln!(source, "/* {summary} */");
+            let _ = writeln!(source, "#ifndef {guard}\n#define {guard}\n");
+            let _ = writeln!(source, "#include <stdint.h>\n");
+            let _ = writeln!(source, "#define {upper}_WIDTH {width}");
+            let _ = writeln!(source, "#define {upper}_HEIGHT {height}");
+            if config.row_padding == RowPadding::Byte {
+                let _ = writeln!(source, "#define {upper}_BYTES_PER_ROW {stride}");
+            }
+            let _ = writeln!(
+                source,
+                "\nstatic const uint8_t {name}[{}] = {{\n{body}\n}};\n\n#endif /* {guard} */",
+                bytes.len()
+            );
+        }
+        OutputFormat::Rust => {
+            let _ = writeln!(source, "// {summary}");
+            let _ = writeln!(source, "pub const {upper}_WIDTH: usize = {width};");
+            let _ = writeln!(source, "pub const {upper}_HEIGHT: usize = {height};");
+            if config.row_padding == RowPadding::Byte {
+                let _ = writeln!(source, "pub const {upper}_BYTES_PER_ROW: usize = {stride};");
+            }
+            let _ = writeln!(
+                source,
+                "\n#[rustfmt::skip]\npub const {upper}: [u8; {}] = [\n{body}\n];",
+                bytes.len()
+            );
+        }
+        _ => {
+            let _ = writeln!(source, "# {summary}");
+            let _ = writeln!(source, "{upper}_WIDTH = {width}");
+            let _ = writeln!(source, "{upper}_HEIGHT = {height}");
+            if config.row_padding == RowPadding::Byte {
+                let _ = writeln!(source, "{upper}_BYTES_PER_ROW = {stride}");
+            }
+            let _ = writeln!(source, "\n{upper} = [\n{body}\n]");
+        }
+    }
+    source
+}
+
+/// JSON object with the symbol, version, error correction level, size and
+/// quiet zone, and the modules as an array of rows of 0 and 1
+fn to_json(matrix: &Matrix, config: &RenderConfig) -> String {
+    // Only the QR family has a selectable error correction level
+    let error_correction = config
+        .symbol
+        .is_qr_family()
+        .then(|| format!("{:?}", config.ec_level));
+    let fields = [
+        ("symbol", serde_json::json!(config.symbol.name())),
+        ("version", serde_json::json!(matrix.version)),
+        ("error_correction", serde_json::json!(error_correction)),
+        ("width", serde_json::json!(matrix.width())),
+        ("height", serde_json::json!(matrix.height())),
+        ("quiet_zone", serde_json::json!(matrix.quiet_zone)),
+    ];
+    let mut json = String::from("{\n");
+    for (key, value) in fields {
+        let _ = writeln!(json, "  \"{key}\": {value},");
+    }
+    // One row per line keeps the grid readable
+    let rows: Vec<String> = matrix
+        .rows
+        .iter()
+        .map(|row| {
+            let modules: Vec<&str> = row
+                .iter()
+                .map(|&dark| if dark { "1" } else { "0" })
+                .collect();
+            format!("[{}]", modules.join(","))
+        })
+        .collect();
+    let _ = writeln!(
+        json,
+        "  \"modules\": [\n    {}\n  ]\n}}",
+        rows.join(",\n    ")
+    );
+    json
+}
+
+/// Binary PBM (P4): one bit per module, rows padded to whole bytes
+fn to_pbm(matrix: &Matrix) -> Vec<u8> {
+    let mut pbm = format!("P4\n{} {}\n", matrix.width(), matrix.height()).into_bytes();
+    pbm.extend(pack(&matrix.rows, BitOrder::Msb, RowPadding::Byte));
+    pbm
+}
+
+/// Binary PGM (P5): one byte per module, 0 for dark and 255 for light
+fn to_pgm(matrix: &Matrix) -> Vec<u8> {
+    let mut pgm = format!("P5\n{} {}\n255\n", matrix.width(), matrix.height()).into_bytes();
+    pgm.extend(
+        matrix
+            .rows
+            .iter()
+            .flatten()
+            .map(|&dark| if dark { 0 } else { 255 }),
+    );
+    pgm
+}
+
+/// Read a matrix file written in `format` back into rows of modules; source
+/// files are unpacked with the bit order and row padding of `config`
+pub fn read_matrix(
+    path: &Path,
+    format: OutputFormat,
+    config: &RenderConfig,
+) -> Result<Vec<Vec<bool>>> {
+    let unreadable = |reason: String| {
+        QrError::VerificationFailed(format!("the matrix in '{}' {reason}", path.display()))
+    };
+    if matches!(format, OutputFormat::Pbm | OutputFormat::Pgm) {
+        let img = image::open(path)?.to_luma8();
+        return Ok(img
+            .rows()
+            .map(|row| row.map(|pixel| pixel.0[0] < 128).collect())
+            .collect());
+    }
+
+    let text = std::fs::read_to_string(path).map_err(|e| QrError::FileRead {
+        path: path.to_path_buf(),
+        source: e,
+    })?;
+    let rows: Vec<Vec<bool>> = if matches!(
+        format,
+        OutputFormat::CHeader | OutputFormat::Rust | OutputFormat::Python
+    ) {
+        // The size follows its constant's name; the bytes are the hex literals
+        let size = |suffix: &str| {
+            let after = &text[text.find(suffix)? + suffix.len()..];
+            let digits: String = after
+                .chars()
+                .skip_while(|c| !c.is_ascii_digit())
+                .take_while(char::is_ascii_digit)
+                .collect();
+            digits.parse().ok()
+        };
+        let bytes: Vec<u8> = text
+            .split(|c: char| !c.is_ascii_alphanumeric())
+            .filter_map(|token| token.strip_prefix("0x"))
+            .map(|hex| u8::from_str_radix(hex, 16))
+            .collect::<std::result::Result<_, _>>()
+            .map_err(|e| unreadable(format!("has a bad byte ({e})")))?;
+        size("_WIDTH")
+            .zip(size("_HEIGHT"))
+            .and_then(|(width, height)| {
+                unpack(&bytes, width, height, config.bit_order, config.row_padding)
+            })
+            .ok_or_else(|| unreadable("has no size or too few bytes".to_string()))?
+    } else if format == OutputFormat::MatrixJson {
+        let json: serde_json::Value = serde_json::from_str(&text)?;
+        serde_json::from_value::<Vec<Vec<u8>>>(json["modules"].clone())?
+            .into_iter()
+            .map(|row| row.into_iter().map(|module| module == 1).collect())
+            .collect()
+    } else {
+        text.lines()
+            .map(|line| {
+                line.chars()
+                    .filter(|&c| c != ',')
+                    .map(|c| match c {
+                        '0' => Ok(false),
+                        '1' => Ok(true),
+                        _ => Err(unreadable(format!("has an unexpected {c:?}"))),
+                    })
+                    .collect()
+            })
+            .collect::<Result<_>>()?
+    };
+    if rows.iter().any(|row| row.len() != rows[0].len()) {
+        return Err(unreadable("has rows of different lengths".to_string()));
+    }
+    Ok(rows)
+}
+
+/// Draw matrix rows as a black-on-white image with a margin, for decoding
+#[allow(clippy::cast_possible_truncation)]
+#[must_use]
+pub fn preview(rows: &[Vec<bool>]) -> GrayImage {
+    let width = rows.first().map_or(0, Vec::len) as u32;
+    let height = rows.len() as u32;
+    let margin = PREVIEW_QUIET_ZONE * PREVIEW_MODULE_SIZE;
+    GrayImage::from_fn(
+        width * PREVIEW_MODULE_SIZE + margin * 2,
+        height * PREVIEW_MODULE_SIZE + margin * 2,
+        |x, y| {
+            let module = |pixel: u32| {
+                pixel
+                    .checked_sub(margin)
+                    .map(|p| (p / PREVIEW_MODULE_SIZE) as usize)
+            };
+            let dark = module(x)
+                .zip(module(y))
+                .and_then(|(x, y)| rows.get(y)?.get(x).copied())
+                .unwrap_or(false);
+            Luma([if dark { 0 } else { 255 }])
+        },
+    )
+}
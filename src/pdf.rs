@@ -0,0 +1,521 @@
+//! PDF output with true vector paths for print workflows
+
+#![allow(clippy::cast_precision_loss)]
+
+use std::collections::BTreeSet;
+use std::fmt::Write as _;
+use std::fs;
+use std::io::Write as _;
+use std::path::PathBuf;
+
+use clap::ValueEnum;
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+
+use crate::error::{QrError, Result};
+use crate::frame::FrameLayout;
+use crate::gradient::{ColorStop, Gradient, GradientKind};
+use crate::logo::LogoImage;
+use crate::renderer::{logo_grid, vector_shapes, Color, RenderConfig, MM_PER_INCH};
+use crate::shapes::{fmt_num, Path, Segment};
+
+/// PDF points per millimetre (PDF user space is 1/72 inch)
+pub const PT_PER_MM: f64 = 72.0 / 25.4;
+
+/// Printed size of the code when none is given
+pub const DEFAULT_PRINT_SIZE_MM: f64 = 50.0;
+
+/// Resolution vector logos are rendered at when no `--dpi` is given
+const DEFAULT_LOGO_DPI: f64 = 300.0;
+
+/// Page size of the generated document
+#[derive(Clone, Copy, ValueEnum, Debug, Default, PartialEq, Eq)]
+pub enum PageSize {
+    /// Page fits the code plus margins
+    #[default]
+    Fit,
+    /// ISO A3 (297 x 420 mm)
+    A3,
+    /// ISO A4 (210 x 297 mm)
+    A4,
+    /// ISO A5 (148 x 210 mm)
+    A5,
+    /// US Letter (8.5 x 11 in)
+    Letter,
+    /// US Legal (8.5 x 14 in)
+    Legal,
+}
+
+impl PageSize {
+    /// Width and height in millimetres, or `None` when the page fits the code
+    #[must_use]
+    pub const fn dimensions_mm(self) -> Option<(f64, f64)> {
+        match self {
+            Self::Fit => None,
+            Self::A3 => Some((297.0, 420.0)),
+            Self::A4 => Some((210.0, 297.0)),
+            Self::A5 => Some((148.0, 210.0)),
+            Self::Letter => Some((215.9, 279.4)),
+            Self::Legal => Some((215.9, 355.6)),
+        }
+    }
+}
+
+/// Placement of the code on the page, in millimetres
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PrintLayout {
+    pub page_width: f64,
+    pub page_height: f64,
+    /// Left edge of the code (including quiet zone)
+    pub x: f64,
+    /// Top edge of the code (including quiet zone)
+    pub y: f64,
+    /// Width of the code (including quiet zone)
+    pub size: f64,
+}
+
+impl PrintLayout {
+    /// Center the code (with its frame, if any) on the configured page,
+    /// checking that it fits. `aspect` is the code's height over its width.
+    pub fn new(config: &RenderConfig, frame: Option<&FrameLayout>, aspect: f64) -> Result<Self> {
+        let size = config.print_size_mm.unwrap_or(DEFAULT_PRINT_SIZE_MM);
+        let margin = config.page_margin_mm;
+        if size <= 0.0 || margin < 0.0 {
+            return Err(QrError::InvalidLayout(
+                "print size must be positive and margins can't be negative".to_string(),
+            ));
+        }
+
+        // Frame dimensions scale with the code
+        let (canvas_width, canvas_height, code_x, code_y) =
+            frame.map_or((size, size * aspect, 0.0, 0.0), |frame| {
+                let mm = size / frame.code_size;
+                (
+                    frame.width * mm,
+                    frame.height * mm,
+                    frame.code_x * mm,
+                    frame.code_y * mm,
+                )
+            });
+        let needed_width = 2.0f64.mul_add(margin, canvas_width);
+        let needed_height = 2.0f64.mul_add(margin, canvas_height);
+        let (page_width, page_height) = config
+            .page_size
+            .dimensions_mm()
+            .unwrap_or((needed_width, needed_height));
+        if needed_width > page_width + 1e-9 || needed_height > page_height + 1e-9 {
+            return Err(QrError::InvalidLayout(format!(
+                "a {}mm code with {}mm margins needs {}x{}mm but the {:?} page is only {}x{}mm",
+                fmt_num(size),
+                fmt_num(margin),
+                fmt_num(needed_width),
+                fmt_num(needed_height),
+                config.page_size,
+                fmt_num(page_width),
+                fmt_num(page_height)
+            )));
+        }
+
+        Ok(Self {
+            page_width,
+            page_height,
+            x: (page_width - canvas_width) / 2.0 + code_x,
+            y: (page_height - canvas_height) / 2.0 + code_y,
+            size,
+        })
+    }
+}
+
+/// Render QR code to a PDF document
+#[allow(clippy::too_many_lines)]
+pub fn render_to_pdf(data: &str, config: &RenderConfig) -> Result<Vec<u8>> {
+    let (grid, logo) = logo_grid(data, config)?;
+    let total_modules = (grid.width() + config.quiet_zone as usize * 2) as f64;
+    let total_rows = (grid.height() + config.quiet_zone as usize * 2) as f64;
+    let frame = FrameLayout::for_code(config, data, total_modules, total_rows);
+    let layout = PrintLayout::new(config, frame.as_ref(), total_rows / total_modules)?;
+
+    let scale = layout.size * PT_PER_MM / total_modules;
+    // Module units (y down, origin at the top-left of the quiet zone) to PDF
+    // user space (y up, origin at the bottom-left of the page)
+    let matrix = format!(
+        "{} 0 0 {} {} {}",
+        fmt_num(scale),
+        fmt_num(-scale),
+        fmt_num(layout.x * PT_PER_MM),
+        fmt_num((layout.page_height - layout.y) * PT_PER_MM)
+    );
+
+    let mut doc = PdfDocument::default();
+    let catalog = doc.reserve();
+    let pages = doc.reserve();
+    let page = doc.reserve();
+
+    let mut resources = String::new();
+    let mut content = String::new();
+    let mut opacities = BTreeSet::new();
+    let _ = writeln!(content, "q\n{matrix} cm");
+
+    if let Some(frame) = &frame {
+        write_frame(&mut content, &mut opacities, frame, config.bg_color);
+    } else if config.bg_color.a > 0 {
+        fill_solid(&mut content, &mut opacities, config.bg_color);
+        let _ = writeln!(
+            content,
+            "0 0 {} {} re f\nQ",
+            fmt_num(total_modules),
+            fmt_num(total_rows)
+        );
+    }
+
+    // Module fill: gradient pattern or plain foreground color
+    let pattern = config.gradient.as_ref().map(|gradient| {
+        let shading = doc.add(shading_dict(gradient, total_modules, total_rows).into_bytes());
+        doc.add(
+            format!("<< /PatternType 2 /Shading {shading} 0 R /Matrix [{matrix}] >>").into_bytes(),
+        )
+    });
+    if let Some(pattern) = pattern {
+        let _ = write!(resources, "/Pattern << /P0 {pattern} 0 R >> ");
+    }
+
+    for shape in vector_shapes(&grid, config) {
+        if shape.path.segments.is_empty() {
+            continue;
+        }
+        if shape.color.is_none() && pattern.is_some() {
+            content.push_str("q\n/Pattern cs /P0 scn\n");
+        } else {
+            let color = shape.color.unwrap_or(config.fg_color);
+            fill_solid(&mut content, &mut opacities, color);
+        }
+        write_path(&mut content, &shape.path);
+        content.push_str(if shape.even_odd { "f*\nQ\n" } else { "f\nQ\n" });
+    }
+
+    if let (Some(logo_path), Some(logo)) = (&config.logo, &logo) {
+        if let (Some(knockout), true) = (&logo.knockout, config.bg_color.a > 0) {
+            fill_solid(&mut content, &mut opacities, config.bg_color);
+            write_path(&mut content, &knockout.outline());
+            content.push_str("f\nQ\n");
+        }
+
+        let logo_mm = layout.size * logo.size / total_modules;
+        let image = embed_logo(&mut doc, logo_path, logo_mm, config.dpi)?;
+        let _ = write!(resources, "/XObject << /Im0 {image} 0 R >> ");
+        let _ = writeln!(
+            content,
+            "q\n{} 0 0 {} {} {} cm\n/Im0 Do\nQ",
+            fmt_num(logo.size),
+            fmt_num(-logo.size),
+            fmt_num(logo.x),
+            fmt_num(logo.y + logo.size)
+        );
+    }
+    content.push_str("Q\n");
+
+    if !opacities.is_empty() {
+        resources.push_str("/ExtGState << ");
+        for alpha in &opacities {
+            let _ = write!(
+                resources,
+                "/GS{alpha} << /ca {} >> ",
+                fmt_num(f64::from(*alpha) / 255.0)
+            );
+        }
+        resources.push_str(">> ");
+    }
+
+    let contents = doc.add_stream_raw("", content.as_bytes());
+    doc.set(
+        page,
+        format!(
+            "<< /Type /Page /Parent {pages} 0 R /MediaBox [0 0 {} {}] /Resources << {resources}>> /Contents {contents} 0 R >>",
+            fmt_num(layout.page_width * PT_PER_MM),
+            fmt_num(layout.page_height * PT_PER_MM)
+        )
+        .into_bytes(),
+    );
+    doc.set(
+        pages,
+        format!("<< /Type /Pages /Kids [{page} 0 R] /Count 1 >>").into_bytes(),
+    );
+    doc.set(
+        catalog,
+        format!("<< /Type /Catalog /Pages {pages} 0 R >>").into_bytes(),
+    );
+    let info = doc.add(b"<< /Producer (cqr) >>".to_vec());
+
+    Ok(doc.finish(catalog, info))
+}
+
+/// Render QR code to PDF file
+pub fn render_to_pdf_file(data: &str, output_path: &PathBuf, config: &RenderConfig) -> Result<()> {
+    let pdf = render_to_pdf(data, config)?;
+    fs::write(output_path, pdf).map_err(|e| QrError::FileWrite {
+        path: output_path.clone(),
+        source: e,
+    })
+}
+
+/// Embed the logo as an image `XObject` (with a soft mask for transparency).
+///
+/// Vector logos are rendered to fill `size_mm` at the print resolution.
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+fn embed_logo(
+    doc: &mut PdfDocument,
+    logo_path: &PathBuf,
+    size_mm: f64,
+    dpi: Option<u16>,
+) -> Result<usize> {
+    let dpi = dpi.map_or(DEFAULT_LOGO_DPI, f64::from);
+    let vector_size = (size_mm / MM_PER_INCH * dpi).ceil() as u32;
+    let logo = LogoImage::load(logo_path)?.to_print_rgba(vector_size.max(1))?;
+    let (width, height) = logo.dimensions();
+    let mut rgb = Vec::with_capacity((width * height * 3) as usize);
+    let mut alpha = Vec::with_capacity((width * height) as usize);
+    for pixel in logo.pixels() {
+        rgb.extend_from_slice(&pixel.0[..3]);
+        alpha.push(pixel.0[3]);
+    }
+
+    let image_dict = format!(
+        "/Type /XObject /Subtype /Image /Width {width} /Height {height} /BitsPerComponent 8"
+    );
+    let smask = if alpha.iter().any(|&a| a < 255) {
+        let id = doc.add_stream(&format!("{image_dict} /ColorSpace /DeviceGray"), &alpha)?;
+        format!(" /SMask {id} 0 R")
+    } else {
+        String::new()
+    };
+    doc.add_stream(&format!("{image_dict} /ColorSpace /DeviceRGB{smask}"), &rgb)
+}
+
+/// Draw the canvas background, frame and caption (coordinates start at the
+/// code corner, so the frame is shifted back to the canvas corner)
+fn write_frame(
+    content: &mut String,
+    opacities: &mut BTreeSet<u8>,
+    frame: &FrameLayout,
+    background: Color,
+) {
+    let _ = writeln!(
+        content,
+        "q\n1 0 0 1 {} {} cm",
+        fmt_num(-frame.code_x),
+        fmt_num(-frame.code_y)
+    );
+    if background.a > 0 {
+        fill_solid(content, opacities, background);
+        let _ = writeln!(
+            content,
+            "0 0 {} {} re f\nQ",
+            fmt_num(frame.width),
+            fmt_num(frame.height)
+        );
+    }
+    for fill in &frame.fills {
+        fill_solid(content, opacities, frame.frame_color);
+        write_path(content, &fill.outline());
+        content.push_str("f*\nQ\n");
+    }
+    // Text as glyph outlines, so no font has to be embedded
+    for line in &frame.texts {
+        fill_solid(content, opacities, line.color);
+        write_path(content, &line.outline());
+        content.push_str("f\nQ\n");
+    }
+    content.push_str("Q\n");
+}
+
+/// Start a `q` block filling with a solid color (the caller closes it)
+fn fill_solid(content: &mut String, opacities: &mut BTreeSet<u8>, color: Color) {
+    content.push_str("q\n");
+    if !color.is_opaque() {
+        opacities.insert(color.a);
+        let _ = writeln!(content, "/GS{} gs", color.a);
+    }
+    let _ = writeln!(content, "{} rg", rgb_components(color));
+}
+
+/// Color as three PDF components from 0 to 1
+pub(crate) fn rgb_components(color: Color) -> String {
+    format!(
+        "{} {} {}",
+        fmt_num(f64::from(color.r) / 255.0),
+        fmt_num(f64::from(color.g) / 255.0),
+        fmt_num(f64::from(color.b) / 255.0)
+    )
+}
+
+/// Append path construction operators
+fn write_path(content: &mut String, path: &Path) {
+    for segment in &path.segments {
+        let _ = match *segment {
+            Segment::MoveTo(x, y) => writeln!(content, "{} {} m", fmt_num(x), fmt_num(y)),
+            Segment::LineTo(x, y) => writeln!(content, "{} {} l", fmt_num(x), fmt_num(y)),
+            Segment::CubicTo(x1, y1, x2, y2, x, y) => writeln!(
+                content,
+                "{} {} {} {} {} {} c",
+                fmt_num(x1),
+                fmt_num(y1),
+                fmt_num(x2),
+                fmt_num(y2),
+                fmt_num(x),
+                fmt_num(y)
+            ),
+            Segment::Close => writeln!(content, "h"),
+        };
+    }
+}
+
+/// Axial or radial shading dictionary matching the raster gradient geometry.
+///
+/// Stop opacity is not supported by PDF shadings and is ignored.
+pub(crate) fn shading_dict(gradient: &Gradient, width: f64, height: f64) -> String {
+    let (shading_type, coords) = match gradient.kind {
+        GradientKind::Linear { .. } => {
+            let ((x1, y1), (x2, y2)) = gradient.linear_endpoints(width, height);
+            (
+                2,
+                format!(
+                    "{} {} {} {}",
+                    fmt_num(x1),
+                    fmt_num(y1),
+                    fmt_num(x2),
+                    fmt_num(y2)
+                ),
+            )
+        }
+        GradientKind::Radial => {
+            let center = format!("{} {}", fmt_num(width / 2.0), fmt_num(height / 2.0));
+            (
+                3,
+                format!(
+                    "{center} 0 {center} {}",
+                    fmt_num(Gradient::radial_radius(width, height))
+                ),
+            )
+        }
+    };
+
+    // The stitching function needs stops at both ends of the [0, 1] domain
+    let mut stops = gradient.stops.clone();
+    if stops[0].offset > 0.0 {
+        stops.insert(
+            0,
+            ColorStop {
+                offset: 0.0,
+                color: stops[0].color,
+            },
+        );
+    }
+    if stops[stops.len() - 1].offset < 1.0 {
+        stops.push(ColorStop {
+            offset: 1.0,
+            color: stops[stops.len() - 1].color,
+        });
+    }
+
+    let segments: Vec<String> = stops
+        .windows(2)
+        .map(|pair| {
+            format!(
+                "<< /FunctionType 2 /Domain [0 1] /C0 [{}] /C1 [{}] /N 1 >>",
+                rgb_components(pair[0].color),
+                rgb_components(pair[1].color)
+            )
+        })
+        .collect();
+    let function = if segments.len() == 1 {
+        segments[0].clone()
+    } else {
+        let bounds: Vec<String> = stops[1..stops.len() - 1]
+            .iter()
+            .map(|stop| fmt_num(stop.offset))
+            .collect();
+        format!(
+            "<< /FunctionType 3 /Domain [0 1] /Functions [{}] /Bounds [{}] /Encode [{}] >>",
+            segments.join(" "),
+            bounds.join(" "),
+            vec!["0 1"; segments.len()].join(" ")
+        )
+    };
+
+    format!(
+        "<< /ShadingType {shading_type} /ColorSpace /DeviceRGB /Coords [{coords}] /Function {function} /Extend [true true] >>"
+    )
+}
+
+/// Minimal PDF object writer
+#[derive(Default)]
+struct PdfDocument {
+    objects: Vec<Vec<u8>>,
+}
+
+impl PdfDocument {
+    /// Reserve an object number to fill in later
+    fn reserve(&mut self) -> usize {
+        self.objects.push(Vec::new());
+        self.objects.len()
+    }
+
+    /// Set the body of a reserved object
+    fn set(&mut self, id: usize, body: Vec<u8>) {
+        self.objects[id - 1] = body;
+    }
+
+    /// Add an object and return its number
+    fn add(&mut self, body: Vec<u8>) -> usize {
+        self.objects.push(body);
+        self.objects.len()
+    }
+
+    /// Add an uncompressed stream object
+    fn add_stream_raw(&mut self, dict: &str, data: &[u8]) -> usize {
+        let separator = if dict.is_empty() { "" } else { " " };
+        let mut body =
+            format!("<< {dict}{separator}/Length {} >>\nstream\n", data.len()).into_bytes();
+        body.extend_from_slice(data);
+        body.extend_from_slice(b"\nendstream");
+        self.add(body)
+    }
+
+    /// Add a Flate-compressed stream object
+    fn add_stream(&mut self, dict: &str, data: &[u8]) -> Result<usize> {
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder
+            .write_all(data)
+            .and_then(|()| encoder.flush())
+            .map_err(|e| QrError::ImageError(e.to_string()))?;
+        let compressed = encoder
+            .finish()
+            .map_err(|e| QrError::ImageError(e.to_string()))?;
+        Ok(self.add_stream_raw(&format!("{dict} /Filter /FlateDecode"), &compressed))
+    }
+
+    /// Serialize the document with its cross-reference table
+    fn finish(self, root: usize, info: usize) -> Vec<u8> {
+        let mut out = b"%PDF-1.4\n%\xe2\xe3\xcf\xd3\n".to_vec();
+        let mut offsets = Vec::with_capacity(self.objects.len());
+        for (i, body) in self.objects.iter().enumerate() {
+            offsets.push(out.len());
+            out.extend_from_slice(format!("{} 0 obj\n", i + 1).as_bytes());
+            out.extend_from_slice(body);
+            out.extend_from_slice(b"\nendobj\n");
+        }
+
+        let xref = out.len();
+        let mut table = format!("xref\n0 {}\n0000000000 65535 f \n", self.objects.len() + 1);
+        for offset in offsets {
+            let _ = writeln!(table, "{offset:010} 00000 n ");
+        }
+        let _ = write!(
+            table,
+            "trailer\n<< /Size {} /Root {root} 0 R /Info {info} 0 R >>\nstartxref\n{xref}\n%%EOF\n",
+            self.objects.len() + 1
+        );
+        out.extend_from_slice(table.as_bytes());
+        out
+    }
+}
@@ -1,58 +1,181 @@
-//! QR code rendering to various formats: PNG, SVG, terminal, base64
+//! QR code rendering to various formats: raster images, SVG, terminal, base64
 
 #![allow(clippy::cast_possible_truncation)]
 #![allow(clippy::cast_sign_loss)]
 #![allow(clippy::cast_precision_loss)]
 #![allow(clippy::uninlined_format_args)]
 
+use clap::ValueEnum;
+
+use crate::encoding::{self, EncodingMode};
 use crate::error::{QrError, Result};
+use crate::frame::{CaptionPosition, FrameLayout, FrameStyle};
+use crate::gradient::Gradient;
+use crate::logo::{LogoImage, LogoKnockout, LogoLayout};
+use crate::matrix::{BitOrder, RowPadding};
+use crate::pdf::{PageSize, DEFAULT_PRINT_SIZE_MM};
+use crate::shapes::{fmt_num, EyeStyle, ModuleGrid, ModuleStyle, Path, RoundedBox, FINDER_SIZE};
+use crate::structured::{self, StructuredAppend};
+use crate::symbol::{self, SymbolType};
+use crate::terminal::{self, TerminalColor, TerminalGlyphs, TerminalGraphics, TerminalPolarity};
 use image::imageops::{overlay, resize, FilterType};
-use image::{ImageBuffer, Rgb, Rgba};
-use qrcode::render::unicode;
-use qrcode::{EcLevel, QrCode};
+use image::{DynamicImage, ImageBuffer, ImageFormat, ImageOutputFormat, Rgb, Rgba};
+use qrcode::EcLevel;
 use std::fs::File;
-use std::io::Write;
+use std::io::{Cursor, Write};
 use std::path::PathBuf;
 
-/// RGB color parsed from hex string
-#[derive(Clone, Copy)]
+/// Largest raster image side in pixels, about 1 GB of RGBA at most
+pub const MAX_IMAGE_SIZE: u32 = 16_384;
+
+/// RGBA color parsed from hex string
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct Color {
     pub r: u8,
     pub g: u8,
     pub b: u8,
+    /// Opacity, from 0 (fully transparent) to 255 (opaque)
+    pub a: u8,
 }
 
 impl Color {
-    /// Parse color from hex string like #RRGGBB or #RGB
+    /// Fully transparent (white) color, as parsed from `transparent`
+    pub const TRANSPARENT: Self = Self::rgba(255, 255, 255, 0);
+
+    /// Opaque color from red, green and blue components
+    #[must_use]
+    pub const fn rgb(r: u8, g: u8, b: u8) -> Self {
+        Self { r, g, b, a: 255 }
+    }
+
+    /// Color from red, green, blue and alpha components
+    #[must_use]
+    pub const fn rgba(r: u8, g: u8, b: u8, a: u8) -> Self {
+        Self { r, g, b, a }
+    }
+
+    /// Parse color from hex string like #RRGGBB, #RRGGBBAA, #RGB or #RGBA,
+    /// or the keyword `transparent`
     pub fn from_hex(hex: &str) -> Result<Self> {
+        if hex.trim().eq_ignore_ascii_case("transparent") {
+            return Ok(Self::TRANSPARENT);
+        }
+
         let hex = hex.trim_start_matches('#');
+        if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(QrError::InvalidColor(hex.to_string()));
+        }
 
-        let (r, g, b) = match hex.len() {
-            3 => {
-                let r = u8::from_str_radix(&hex[0..1], 16)
-                    .map_err(|_| QrError::InvalidColor(hex.to_string()))?
-                    * 17;
-                let g = u8::from_str_radix(&hex[1..2], 16)
-                    .map_err(|_| QrError::InvalidColor(hex.to_string()))?
-                    * 17;
-                let b = u8::from_str_radix(&hex[2..3], 16)
-                    .map_err(|_| QrError::InvalidColor(hex.to_string()))?
-                    * 17;
-                (r, g, b)
-            }
-            6 => {
-                let r = u8::from_str_radix(&hex[0..2], 16)
-                    .map_err(|_| QrError::InvalidColor(hex.to_string()))?;
-                let g = u8::from_str_radix(&hex[2..4], 16)
-                    .map_err(|_| QrError::InvalidColor(hex.to_string()))?;
-                let b = u8::from_str_radix(&hex[4..6], 16)
-                    .map_err(|_| QrError::InvalidColor(hex.to_string()))?;
-                (r, g, b)
-            }
+        // Short forms repeat each digit (#f80 == #ff8800)
+        let digits = match hex.len() {
+            3 | 4 => 1,
+            6 | 8 => 2,
             _ => return Err(QrError::InvalidColor(hex.to_string())),
         };
+        let channel = |i: usize| {
+            let value = u8::from_str_radix(&hex[i * digits..(i + 1) * digits], 16)
+                .map_err(|_| QrError::InvalidColor(hex.to_string()))?;
+            Ok::<u8, QrError>(if digits == 1 { value * 17 } else { value })
+        };
+
+        let a = if hex.len() == 4 || hex.len() == 8 {
+            channel(3)?
+        } else {
+            255
+        };
+        Ok(Self::rgba(channel(0)?, channel(1)?, channel(2)?, a))
+    }
+
+    /// Whether the color is fully opaque
+    #[must_use]
+    pub const fn is_opaque(self) -> bool {
+        self.a == 255
+    }
+
+    /// Opacity as a fraction from 0.0 to 1.0
+    #[must_use]
+    pub fn opacity(self) -> f64 {
+        f64::from(self.a) / 255.0
+    }
+
+    /// Composite this color over `below` (Porter-Duff "source over")
+    #[must_use]
+    pub fn over(self, below: Self) -> Self {
+        let (top_a, below_a) = (self.opacity(), below.opacity());
+        let out_a = below_a.mul_add(1.0 - top_a, top_a);
+        if out_a <= 0.0 {
+            return Self::TRANSPARENT;
+        }
+        let channel = |top: u8, bottom: u8| {
+            let mixed =
+                (f64::from(bottom) * below_a).mul_add(1.0 - top_a, f64::from(top) * top_a) / out_a;
+            mixed.round().clamp(0.0, 255.0) as u8
+        };
+        Self::rgba(
+            channel(self.r, below.r),
+            channel(self.g, below.g),
+            channel(self.b, below.b),
+            (out_a * 255.0).round() as u8,
+        )
+    }
+
+    /// Format as a lowercase `#rrggbb` hex string (alpha is dropped)
+    #[must_use]
+    pub fn to_hex(self) -> String {
+        format!("#{:02x}{:02x}{:02x}", self.r, self.g, self.b)
+    }
+
+    /// SVG `fill` attribute, with `fill-opacity` for translucent colors
+    #[must_use]
+    pub fn to_svg_fill(self) -> String {
+        if self.is_opaque() {
+            format!(r#"fill="{}""#, self.to_hex())
+        } else {
+            format!(
+                r#"fill="{}" fill-opacity="{}""#,
+                self.to_hex(),
+                fmt_num(self.opacity())
+            )
+        }
+    }
+
+    /// WCAG relative luminance (0.0 for black to 1.0 for white)
+    #[must_use]
+    pub fn relative_luminance(self) -> f64 {
+        let channel = |c: u8| {
+            let c = f64::from(c) / 255.0;
+            if c <= 0.039_28 {
+                c / 12.92
+            } else {
+                ((c + 0.055) / 1.055).powf(2.4)
+            }
+        };
+        0.0722f64.mul_add(
+            channel(self.b),
+            0.2126f64.mul_add(channel(self.r), 0.7152 * channel(self.g)),
+        )
+    }
 
-        Ok(Self { r, g, b })
+    /// WCAG contrast ratio of this foreground color against a background
+    /// (1.0 to 21.0).
+    ///
+    /// Translucent colors are compared as they appear: the background over a
+    /// white page, and the foreground over that.
+    #[must_use]
+    pub fn contrast_ratio(self, background: Self) -> f64 {
+        let background = background.over(Self::rgb(255, 255, 255));
+        let foreground = self.over(background);
+        let (fg, bg) = (
+            foreground.relative_luminance(),
+            background.relative_luminance(),
+        );
+        (fg.max(bg) + 0.05) / (fg.min(bg) + 0.05)
+    }
+
+    /// Convert to image crate Rgba type
+    #[must_use]
+    pub const fn to_rgba(self) -> Rgba<u8> {
+        Rgba([self.r, self.g, self.b, self.a])
     }
 
     /// Convert to image crate Rgb type (unused now but kept for API completeness, optional)
@@ -63,257 +186,980 @@ impl Color {
     }
 }
 
+/// Millimetres per inch
+pub const MM_PER_INCH: f64 = 25.4;
+
+/// Smallest module that still prints and scans reliably
+pub const MIN_PRINT_MODULE_MM: f64 = 0.25;
+
+/// Fewest printer dots a module should span, so dot gain can't swallow it
+pub const MIN_PRINT_MODULE_DOTS: f64 = 2.0;
+
+/// How raster output meets the requested `--size`
+#[derive(Clone, Copy, ValueEnum, Debug, Default, PartialEq, Eq)]
+pub enum SizeMode {
+    /// Largest whole-pixel module size that fits; the image may be smaller
+    #[default]
+    Fit,
+    /// Whole-pixel modules centred on a canvas of exactly the requested size
+    Pad,
+    /// Resample to exactly the requested size (modules differ by up to a pixel)
+    Scale,
+}
+
+/// Markup style of SVG output
+#[derive(Clone, Copy, ValueEnum, Debug, Default, PartialEq, Eq)]
+pub enum SvgMode {
+    /// One element per module, easy to post-process
+    #[default]
+    Legacy,
+    /// Modules merged into a single path per fill, much smaller files
+    Compact,
+}
+
+/// Pixel geometry of a raster render
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PixelLayout {
+    /// Pixels per module
+    pub module_size: u32,
+    /// Width of the code itself, quiet zone included
+    pub code_size: u32,
+    /// Width of the final image
+    pub image_size: u32,
+}
+
 /// Configuration for QR code rendering
+#[derive(Clone, Debug)]
 pub struct RenderConfig {
     pub size: u32,
+    /// Fixed pixels per module; overrides `size` for raster output
+    pub module_size: Option<u32>,
+    pub size_mode: SizeMode,
+    pub svg_mode: SvgMode,
+    /// Unicode blocks, or an inline image protocol for terminal output
+    pub terminal_graphics: TerminalGraphics,
+    pub terminal_glyphs: TerminalGlyphs,
+    pub terminal_color: TerminalColor,
+    pub terminal_polarity: TerminalPolarity,
     pub quiet_zone: u32,
+    /// Add the quiet zone around matrix output, which is the bare symbol otherwise
+    pub matrix_quiet_zone: bool,
+    /// Bit order and row padding of packed bitmaps in source output
+    pub bit_order: BitOrder,
+    pub row_padding: RowPadding,
+    /// Identifier of the bitmap in source output
+    pub array_name: String,
     pub fg_color: Color,
     pub bg_color: Color,
-    pub gradient_color: Option<Color>,
+    pub gradient: Option<Gradient>,
     pub logo: Option<PathBuf>,
+    /// Logo width as a fraction of the code width, quiet zone included
+    pub logo_scale: f64,
+    /// Margin between the logo and the edge of its knockout, in modules
+    pub logo_padding: f64,
+    pub logo_knockout: LogoKnockout,
+    /// Turn every module the logo touches light instead of covering it part-way
+    pub logo_clear_modules: bool,
     pub ec_level: EcLevel,
+    /// QR, Micro QR or rectangular Micro QR
+    pub symbol: SymbolType,
+    /// Smallest QR version to use (1-40); the exact one with `exact_version`
+    pub qr_version: Option<i16>,
+    pub exact_version: bool,
+    /// QR mask pattern (0-7); the one with the lowest penalty when unset
+    pub mask: Option<u8>,
+    pub encoding_mode: EncodingMode,
+    pub module_style: ModuleStyle,
+    pub eye_outer_style: EyeStyle,
+    pub eye_inner_style: EyeStyle,
+    /// Outer ring color; defaults to the module fill
+    pub eye_outer_color: Option<Color>,
+    /// Pupil color; defaults to the module fill
+    pub eye_inner_color: Option<Color>,
+    /// Physical size of the code (including quiet zone) for print output
+    pub print_size_mm: Option<f64>,
+    pub page_size: PageSize,
+    pub page_margin_mm: f64,
+    /// JPEG encoder quality, 1-100
+    pub jpeg_quality: u8,
+    /// Target print resolution; with `print_size_mm` it sets the pixel size
+    pub dpi: Option<u16>,
+    /// Text next to the code; `{data}` is replaced by the encoded data
+    pub caption: Option<String>,
+    /// Alt text of HTML and Markdown output; described from the data if unset
+    pub alt_text: Option<String>,
+    pub caption_position: CaptionPosition,
+    /// Call-to-action text in a bar under the code, e.g. "SCAN ME"
+    pub banner: Option<String>,
+    pub frame: FrameStyle,
+    /// Frame and banner color; defaults to the foreground color
+    pub frame_color: Option<Color>,
+    /// Link the code into a set of QR codes holding one payload
+    pub structured_append: Option<StructuredAppend>,
+    /// Prepended to every id in SVG output, so codes sharing a document
+    /// don't clash
+    pub svg_id_prefix: String,
 }
 
 impl Default for RenderConfig {
     fn default() -> Self {
         Self {
             size: 512,
+            module_size: None,
+            size_mode: SizeMode::Fit,
+            svg_mode: SvgMode::Legacy,
+            terminal_graphics: TerminalGraphics::Unicode,
+            terminal_glyphs: TerminalGlyphs::HalfBlock,
+            terminal_color: TerminalColor::None,
+            terminal_polarity: TerminalPolarity::Auto,
             quiet_zone: 2,
-            fg_color: Color { r: 0, g: 0, b: 0 },
-            bg_color: Color {
-                r: 255,
-                g: 255,
-                b: 255,
-            },
-            gradient_color: None,
+            matrix_quiet_zone: false,
+            bit_order: BitOrder::Msb,
+            row_padding: RowPadding::Byte,
+            array_name: "qr".to_string(),
+            fg_color: Color::rgb(0, 0, 0),
+            bg_color: Color::rgb(255, 255, 255),
+            gradient: None,
             logo: None,
+            logo_scale: 0.2,
+            logo_padding: 1.0,
+            logo_knockout: LogoKnockout::None,
+            logo_clear_modules: false,
             ec_level: EcLevel::M,
+            symbol: SymbolType::Qr,
+            qr_version: None,
+            exact_version: false,
+            mask: None,
+            encoding_mode: EncodingMode::Optimized,
+            module_style: ModuleStyle::Square,
+            eye_outer_style: EyeStyle::Square,
+            eye_inner_style: EyeStyle::Square,
+            eye_outer_color: None,
+            eye_inner_color: None,
+            print_size_mm: None,
+            page_size: PageSize::Fit,
+            page_margin_mm: 10.0,
+            jpeg_quality: 90,
+            dpi: None,
+            caption: None,
+            alt_text: None,
+            caption_position: CaptionPosition::Below,
+            banner: None,
+            frame: FrameStyle::None,
+            frame_color: None,
+            structured_append: None,
+            svg_id_prefix: String::new(),
         }
     }
 }
 
-/// Create QR code with specified error correction level
-fn create_qr_code(data: &str, ec_level: EcLevel) -> Result<QrCode> {
-    QrCode::with_error_correction_level(data.as_bytes(), ec_level)
-        .map_err(|e| QrError::QrGeneration(e.to_string()))
+impl RenderConfig {
+    /// Check the configuration for problems that would stop the code from scanning
+    pub fn validate(&self) -> Result<()> {
+        // Logo coverage is budgeted against QR error correction
+        if self.logo.is_some() && !self.symbol.is_qr_family() {
+            return Err(QrError::BarcodeGeneration {
+                symbology: self.symbol.name().to_string(),
+                message: "logos need QR error correction to cover for them".to_string(),
+            });
+        }
+        if self.symbol.is_linear() && self.module_style != ModuleStyle::Square {
+            return Err(QrError::BarcodeGeneration {
+                symbology: self.symbol.name().to_string(),
+                message: "bars must be drawn solid; use --module-style square".to_string(),
+            });
+        }
+        if self.has_manual_encoding() && self.symbol != SymbolType::Qr {
+            return Err(QrError::QrGeneration(
+                "--qr-version, --mask and --encoding-mode apply to standard QR codes; use --symbol qr"
+                    .to_string(),
+            ));
+        }
+        if self
+            .qr_version
+            .is_some_and(|version| !(1..=40).contains(&version))
+        {
+            return Err(QrError::QrGeneration(
+                "QR versions run from 1 to 40".to_string(),
+            ));
+        }
+        if self.mask.is_some_and(|mask| mask > 7) {
+            return Err(QrError::QrGeneration(
+                "QR mask patterns run from 0 to 7".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Encode `data` as the configured symbol, with the Structured Append
+    /// header when the code is part of a set
+    #[allow(clippy::option_if_let_else)]
+    pub fn encode(&self, data: &str) -> Result<ModuleGrid> {
+        match self.structured_append {
+            Some(header) => structured::encode(data, header, self.ec_level),
+            None if self.symbol == SymbolType::Qr => Ok(encoding::encode(data, self)?.grid),
+            None => symbol::encode(data, self.symbol, self.ec_level),
+        }
+    }
+
+    /// Whether the QR version, mask or segment mode is set by hand
+    #[must_use]
+    pub fn has_manual_encoding(&self) -> bool {
+        self.qr_version.is_some()
+            || self.exact_version
+            || self.mask.is_some()
+            || self.encoding_mode != EncodingMode::Optimized
+    }
+
+    /// Requested image width in pixels: the print size at `dpi` when both are set
+    #[must_use]
+    pub fn target_size(&self) -> u32 {
+        match (self.print_size_mm, self.dpi) {
+            (Some(size_mm), Some(dpi)) => (size_mm / MM_PER_INCH * f64::from(dpi)).round() as u32,
+            _ => self.size,
+        }
+    }
+
+    /// Warn when the modules come out too small to print reliably.
+    ///
+    /// Only applies when a physical size is known (`print_size_mm` or `dpi`).
+    pub fn print_warning(&self, data: &str) -> Result<Option<String>> {
+        if self.print_size_mm.is_none() && self.dpi.is_none() {
+            return Ok(None);
+        }
+        let grid = self.encode(data)?;
+        let total_modules = (grid.width() + self.quiet_zone as usize * 2) as u32;
+
+        let (module_mm, min_mm) = if let Some(dpi) = self.dpi {
+            let dpi = f64::from(dpi);
+            let module_px = self.pixel_layout(total_modules)?.module_size;
+            (
+                f64::from(module_px) / dpi * MM_PER_INCH,
+                MIN_PRINT_MODULE_MM.max(MIN_PRINT_MODULE_DOTS / dpi * MM_PER_INCH),
+            )
+        } else {
+            let size_mm = self.print_size_mm.unwrap_or(DEFAULT_PRINT_SIZE_MM);
+            (size_mm / f64::from(total_modules), MIN_PRINT_MODULE_MM)
+        };
+
+        Ok((module_mm < min_mm).then(|| {
+            format!(
+                "modules are {module_mm:.3}mm wide, below the {min_mm:.3}mm minimum for reliable printing; \
+                 increase --print-size or --dpi, or lower the error correction"
+            )
+        }))
+    }
+
+    /// Raster geometry for a symbol `total_modules` wide, quiet zone included
+    pub fn pixel_layout(&self, total_modules: u32) -> Result<PixelLayout> {
+        if let Some(module_size) = self.module_size {
+            if module_size == 0 {
+                return Err(QrError::InvalidSize(
+                    "module size must be at least 1 pixel".to_string(),
+                ));
+            }
+            let code_size = module_size
+                .checked_mul(total_modules)
+                .filter(|&code_size| code_size <= MAX_IMAGE_SIZE)
+                .ok_or_else(|| {
+                    QrError::InvalidSize(format!(
+                        "{module_size}px modules make a code over {MAX_IMAGE_SIZE}px wide; \
+                         use --module-size {} or less",
+                        MAX_IMAGE_SIZE / total_modules
+                    ))
+                })?;
+            return Ok(PixelLayout {
+                module_size,
+                code_size,
+                image_size: code_size,
+            });
+        }
+
+        let size = self.target_size();
+        if size > MAX_IMAGE_SIZE {
+            return Err(QrError::InvalidSize(format!(
+                "{size}px is larger than the {MAX_IMAGE_SIZE}px limit"
+            )));
+        }
+        if size < total_modules {
+            return Err(QrError::InvalidSize(format!(
+                "{size}px can't hold a code {total_modules} modules wide (quiet zone included); \
+                 use --size {total_modules} or more"
+            )));
+        }
+        let module_size = match self.size_mode {
+            SizeMode::Fit | SizeMode::Pad => size / total_modules,
+            // Render slightly larger and sample down, so no module is lost
+            SizeMode::Scale => (size + total_modules - 1) / total_modules,
+        };
+        let code_size = module_size * total_modules;
+        Ok(PixelLayout {
+            module_size,
+            code_size,
+            image_size: if self.size_mode == SizeMode::Fit {
+                code_size
+            } else {
+                size
+            },
+        })
+    }
+}
+
+/// Module fill color at pixel `(x, y)` of an image `width` by `height` pixels
+fn fill_color_at(config: &RenderConfig, px_x: u32, px_y: u32, width: u32, height: u32) -> Color {
+    config.gradient.as_ref().map_or_else(
+        || config.fg_color,
+        |gradient| {
+            // Sample at the pixel center, like an SVG renderer would
+            let t = gradient.progress_at(
+                f64::from(px_x) + 0.5,
+                f64::from(px_y) + 0.5,
+                f64::from(width),
+                f64::from(height),
+            );
+            gradient.color_at(t)
+        },
+    )
 }
 
-/// Render QR code to terminal using Unicode block characters
+/// Encode `data` as the configured symbol with the logo placed on it; the
+/// modules under the logo are cleared when `logo_clear_modules` is set, and
+/// those inside the knockout when the background is transparent
+pub(crate) fn logo_grid(
+    data: &str,
+    config: &RenderConfig,
+) -> Result<(ModuleGrid, Option<LogoLayout>)> {
+    let mut grid = config.encode(data)?;
+    let logo = LogoLayout::new(config, &grid)?;
+    if let Some(logo) = &logo {
+        if config.logo_clear_modules {
+            logo.clear_modules(&mut grid);
+        } else if config.bg_color.a == 0 {
+            logo.clear_knockout(&mut grid);
+        }
+    }
+    Ok((grid, logo))
+}
+
+/// A filled outline of the vector renderers (PDF, EPS)
+pub(crate) struct VectorShape {
+    pub path: Path,
+    /// Fill with the even-odd rule (finder rings) instead of non-zero
+    pub even_odd: bool,
+    /// Solid color, or `None` for the module fill (foreground or gradient)
+    pub color: Option<Color>,
+}
+
+/// Outlines of all dark modules in module units, with the origin at the
+/// top-left corner of the quiet zone
+pub(crate) fn vector_shapes(grid: &ModuleGrid, config: &RenderConfig) -> Vec<VectorShape> {
+    let quiet = f64::from(config.quiet_zone);
+
+    let mut modules = Path::default();
+    for (qr_x, qr_y) in grid.dark_modules() {
+        if !grid.is_finder(qr_x, qr_y) {
+            modules.extend(&config.module_style.outline(
+                qr_x as f64 + quiet,
+                qr_y as f64 + quiet,
+                1.0,
+                grid.neighbors(qr_x, qr_y),
+            ));
+        }
+    }
+
+    let mut shapes = vec![VectorShape {
+        path: modules,
+        even_odd: false,
+        color: None,
+    }];
+    for (eye, eye_x, eye_y) in grid.eyes() {
+        let (x, y) = (eye_x as f64 + quiet, eye_y as f64 + quiet);
+        shapes.push(VectorShape {
+            path: config.eye_outer_style.ring_outline(eye, x, y, 1.0),
+            even_odd: true,
+            color: config.eye_outer_color,
+        });
+        shapes.push(VectorShape {
+            path: config.eye_inner_style.pupil_outline(eye, x, y, 1.0),
+            even_odd: false,
+            color: config.eye_inner_color,
+        });
+    }
+    shapes
+}
+
+/// Render QR code to terminal, as text or an inline image in the protocol
+/// `config.terminal_graphics` names.
+///
+/// Images are the full raster, as written to PNG. Text takes the glyphs,
+/// colors and polarity of `config`; by default two module rows share a line,
+/// and light modules are drawn as blocks so the code reads on dark terminal
+/// backgrounds. Any quiet zone is widened to the one the symbology calls for.
 pub fn render_to_terminal(data: &str, config: &RenderConfig) -> Result<String> {
-    let code = create_qr_code(data, config.ec_level)?;
-    let image = code
-        .render::<unicode::Dense1x2>()
-        .dark_color(unicode::Dense1x2::Light)
-        .light_color(unicode::Dense1x2::Dark)
-        .quiet_zone(config.quiet_zone > 0)
-        .build();
-    Ok(image)
+    let image = |data| create_qr_image(data, config);
+    match config.terminal_graphics {
+        TerminalGraphics::Unicode => render_blocks(data, config),
+        TerminalGraphics::Sixel => Ok(terminal::sixel(&image(data)?)),
+        TerminalGraphics::Kitty => {
+            let png = encode_buffer(image(data)?, ImageFormat::Png, config)?;
+            Ok(terminal::kitty(&png))
+        }
+        TerminalGraphics::Iterm2 => {
+            let img = image(data)?;
+            let (width, height) = img.dimensions();
+            let png = encode_buffer(img, ImageFormat::Png, config)?;
+            Ok(terminal::iterm2(&png, width, height))
+        }
+    }
+}
+
+/// Text rendering for terminals without image support
+fn render_blocks(data: &str, config: &RenderConfig) -> Result<String> {
+    let grid = config.encode(data)?;
+    let quiet = if config.quiet_zone > 0 {
+        config.symbol.standard_quiet_zone() as usize
+    } else {
+        0
+    };
+    Ok(terminal::render_text(&grid, quiet, config))
+}
+
+/// Create QR code image buffer, with caption and frame when configured
+pub(crate) fn create_qr_image(
+    data: &str,
+    config: &RenderConfig,
+) -> Result<ImageBuffer<Rgba<u8>, Vec<u8>>> {
+    let img = create_code_image(data, config)?;
+    Ok(
+        match FrameLayout::for_code(
+            config,
+            data,
+            f64::from(img.width()),
+            f64::from(img.height()),
+        ) {
+            Some(layout) => layout.render_image(&img, config.bg_color),
+            None => img,
+        },
+    )
 }
 
 /// Create QR code image buffer with support for gradient and logo
-#[allow(clippy::option_if_let_else)]
-fn create_qr_image(data: &str, config: &RenderConfig) -> Result<ImageBuffer<Rgba<u8>, Vec<u8>>> {
-    let code = create_qr_code(data, config.ec_level)?;
-    let module_count = code.width();
-
-    // Calculate module size to fit desired image size
-    let total_modules = module_count + (config.quiet_zone as usize * 2);
-    let module_size = config.size / total_modules as u32;
-    let actual_size = module_size * total_modules as u32;
-
-    let mut img: ImageBuffer<Rgba<u8>, Vec<u8>> = ImageBuffer::from_pixel(
-        actual_size,
-        actual_size,
-        Rgba([config.bg_color.r, config.bg_color.g, config.bg_color.b, 255]),
-    );
+#[allow(clippy::option_if_let_else, clippy::too_many_lines)]
+fn create_code_image(data: &str, config: &RenderConfig) -> Result<ImageBuffer<Rgba<u8>, Vec<u8>>> {
+    let (grid, logo) = logo_grid(data, config)?;
+    let quiet_modules = config.quiet_zone as usize * 2;
 
-    let colors = code.to_colors();
+    // The size applies to the width; rectangular symbols are less tall
+    let layout = config.pixel_layout((grid.width() + quiet_modules) as u32)?;
+    let module_size = layout.module_size;
+    let actual_width = layout.code_size;
+    let actual_height = (grid.height() + quiet_modules) as u32 * module_size;
+
+    let mut img: ImageBuffer<Rgba<u8>, Vec<u8>> =
+        ImageBuffer::from_pixel(actual_width, actual_height, config.bg_color.to_rgba());
+    let fill_at = |x, y| fill_color_at(config, x, y, actual_width, actual_height);
     let quiet_offset = config.quiet_zone * module_size;
 
-    for (i, color) in colors.iter().enumerate() {
-        if *color == qrcode::Color::Dark {
-            let qr_x = (i % module_count) as u32;
-            let qr_y = (i / module_count) as u32;
-            let px_x = qr_x * module_size + quiet_offset;
-            let px_y = qr_y * module_size + quiet_offset;
-
-            // Calculate gradient color if enabled
-            let draw_color = if let Some(grad_end) = config.gradient_color {
-                let progress = (px_x as f32 + px_y as f32) / (actual_size as f32 * 2.0);
-                let r = (f32::from(grad_end.r) - f32::from(config.fg_color.r))
-                    .mul_add(progress, f32::from(config.fg_color.r)) as u8;
-                let g = (f32::from(grad_end.g) - f32::from(config.fg_color.g))
-                    .mul_add(progress, f32::from(config.fg_color.g)) as u8;
-                let b = (f32::from(grad_end.b) - f32::from(config.fg_color.b))
-                    .mul_add(progress, f32::from(config.fg_color.b)) as u8;
-                Rgba([r, g, b, 255])
-            } else {
-                Rgba([config.fg_color.r, config.fg_color.g, config.fg_color.b, 255])
-            };
+    // Finder patterns are painted as whole eyes below
+    for (qr_x, qr_y) in grid.dark_modules() {
+        if grid.is_finder(qr_x, qr_y) {
+            continue;
+        }
+        let px_x = qr_x as u32 * module_size + quiet_offset;
+        let px_y = qr_y as u32 * module_size + quiet_offset;
+        let neighbors = grid.neighbors(qr_x, qr_y);
 
-            for dy in 0..module_size {
-                for dx in 0..module_size {
-                    img.put_pixel(px_x + dx, px_y + dy, draw_color);
+        // Sample the module shape at each pixel center
+        for dy in 0..module_size {
+            let v = (f64::from(dy) + 0.5) / f64::from(module_size);
+            for dx in 0..module_size {
+                let u = (f64::from(dx) + 0.5) / f64::from(module_size);
+                if config.module_style.contains(u, v, neighbors) {
+                    let (x, y) = (px_x + dx, px_y + dy);
+                    img.put_pixel(x, y, fill_at(x, y).over(config.bg_color).to_rgba());
                 }
             }
         }
     }
 
+    // Draw the finder patterns with their own shapes and colors
+    let eye_box = FINDER_SIZE as u32 * module_size;
+    for (eye, eye_x, eye_y) in grid.eyes() {
+        let origin_x = eye_x as u32 * module_size + quiet_offset;
+        let origin_y = eye_y as u32 * module_size + quiet_offset;
+
+        for dy in 0..eye_box {
+            let v = (f64::from(dy) + 0.5) / f64::from(eye_box);
+            for dx in 0..eye_box {
+                let u = (f64::from(dx) + 0.5) / f64::from(eye_box);
+                let eye_color = if config.eye_inner_style.pupil_contains(eye, u, v) {
+                    config.eye_inner_color
+                } else if config.eye_outer_style.ring_contains(eye, u, v) {
+                    config.eye_outer_color
+                } else {
+                    continue;
+                };
+                let (px_x, px_y) = (origin_x + dx, origin_y + dy);
+                let draw_color = eye_color.unwrap_or_else(|| fill_at(px_x, px_y));
+                img.put_pixel(px_x, px_y, draw_color.over(config.bg_color).to_rgba());
+            }
+        }
+    }
+
     // Overlay logo if present
-    if let Some(logo_path) = &config.logo {
-        let logo_img = image::open(logo_path).map_err(|e| QrError::FileWrite {
-            path: logo_path.clone(),
-            source: std::io::Error::new(
-                std::io::ErrorKind::Other,
-                format!("Failed to load logo: {e}"),
-            ),
-        })?;
+    if let (Some(logo_path), Some(logo)) = (&config.logo, &logo) {
+        let scale = f64::from(module_size);
+        if let Some(knockout) = &logo.knockout {
+            let pixels = |start: f64, length: f64, limit: u32| {
+                ((start * scale).floor().max(0.0) as u32)
+                    ..((start + length) * scale).ceil().min(f64::from(limit)) as u32
+            };
+            for px_y in pixels(knockout.y, knockout.height, actual_height) {
+                let v = (f64::from(px_y) + 0.5) / scale;
+                for px_x in pixels(knockout.x, knockout.width, actual_width) {
+                    let u = (f64::from(px_x) + 0.5) / scale;
+                    if knockout.contains(u, v) {
+                        img.put_pixel(px_x, px_y, config.bg_color.to_rgba());
+                    }
+                }
+            }
+        }
 
-        // Calculate logo size (e.g., 20% of QR size)
-        let logo_size = actual_size / 5;
-        let logo_rgba = logo_img.to_rgba8();
-        let resized_logo = resize(&logo_rgba, logo_size, logo_size, FilterType::Lanczos3);
+        let logo_size = ((logo.size * scale).round() as u32).max(1);
+        let resized_logo = LogoImage::load(logo_path)?.to_rgba(logo_size)?;
 
         // Calculate position to center
-        let center_x = (actual_size - resized_logo.width()) / 2;
-        let center_y = (actual_size - resized_logo.height()) / 2;
+        let left = actual_width.saturating_sub(logo_size) / 2;
+        let top = actual_height.saturating_sub(logo_size) / 2;
+        overlay(&mut img, &resized_logo, left.into(), top.into());
+    }
 
-        overlay(&mut img, &resized_logo, center_x.into(), center_y.into());
+    if layout.image_size == layout.code_size {
+        return Ok(img);
     }
+    // Padding or scaling applies to both sides alike
+    let image_height = (f64::from(actual_height) * f64::from(layout.image_size)
+        / f64::from(actual_width))
+    .round() as u32;
+    if config.size_mode == SizeMode::Scale {
+        return Ok(resize(
+            &img,
+            layout.image_size,
+            image_height,
+            FilterType::Nearest,
+        ));
+    }
+    let padding = layout.image_size - layout.code_size;
+    let mut canvas = ImageBuffer::from_pixel(
+        layout.image_size,
+        actual_height + padding,
+        config.bg_color.to_rgba(),
+    );
+    let offset = i64::from(padding / 2);
+    overlay(&mut canvas, &img, offset, offset);
+    Ok(canvas)
+}
+
+/// Encode the rendered QR code as a raster image in memory.
+///
+/// Formats without an alpha channel (JPEG) are flattened onto white. With a
+/// `dpi`, PNG and JPEG files record the resolution so they print at size.
+pub fn encode_image(data: &str, format: ImageFormat, config: &RenderConfig) -> Result<Vec<u8>> {
+    encode_buffer(create_qr_image(data, config)?, format, config)
+}
+
+/// Encode a rendered image in `format`, with the JPEG quality and resolution
+/// from `config`
+pub(crate) fn encode_buffer(
+    img: ImageBuffer<Rgba<u8>, Vec<u8>>,
+    format: ImageFormat,
+    config: &RenderConfig,
+) -> Result<Vec<u8>> {
+    use image::codecs::jpeg::{JpegEncoder, PixelDensity};
+
+    let mut buffer = Cursor::new(Vec::new());
+
+    if format == ImageFormat::Jpeg {
+        let white = Color::rgb(255, 255, 255);
+        let flattened = ImageBuffer::from_fn(img.width(), img.height(), |x, y| {
+            let [r, g, b, a] = img.get_pixel(x, y).0;
+            Color::rgba(r, g, b, a).over(white).to_rgb()
+        });
+        let mut encoder =
+            JpegEncoder::new_with_quality(&mut buffer, config.jpeg_quality.clamp(1, 100));
+        if let Some(dpi) = config.dpi {
+            encoder.set_pixel_density(PixelDensity::dpi(dpi));
+        }
+        encoder.encode_image(&flattened)?;
+        return Ok(buffer.into_inner());
+    }
+
+    DynamicImage::ImageRgba8(img).write_to(&mut buffer, ImageOutputFormat::from(format))?;
+    let mut bytes = buffer.into_inner();
+    if let (ImageFormat::Png, Some(dpi)) = (format, config.dpi) {
+        insert_png_phys(&mut bytes, dpi);
+    }
+    Ok(bytes)
+}
 
-    Ok(img)
+/// Add a pHYs chunk recording `dpi` right after the PNG header chunk
+fn insert_png_phys(png: &mut Vec<u8>, dpi: u16) {
+    // 8-byte signature, then IHDR: length, type, 13 data bytes and CRC
+    const IHDR_END: usize = 8 + 4 + 4 + 13 + 4;
+
+    let pixels_per_metre = (f64::from(dpi) / MM_PER_INCH * 1000.0).round() as u32;
+    let mut body = b"pHYs".to_vec();
+    body.extend_from_slice(&pixels_per_metre.to_be_bytes());
+    body.extend_from_slice(&pixels_per_metre.to_be_bytes());
+    body.push(1); // unit: metre
+
+    let mut crc = flate2::Crc::new();
+    crc.update(&body);
+
+    let mut chunk = 9u32.to_be_bytes().to_vec();
+    chunk.extend_from_slice(&body);
+    chunk.extend_from_slice(&crc.sum().to_be_bytes());
+    png.splice(IHDR_END..IHDR_END, chunk);
+}
+
+/// Render QR code to a raster image file in the given format
+pub fn render_to_image(
+    data: &str,
+    output_path: &PathBuf,
+    format: ImageFormat,
+    config: &RenderConfig,
+) -> Result<()> {
+    let bytes = encode_image(data, format, config)?;
+    std::fs::write(output_path, bytes).map_err(|e| QrError::FileWrite {
+        path: output_path.clone(),
+        source: e,
+    })
 }
 
 /// Render QR code to PNG image
 pub fn render_to_png(data: &str, output_path: &PathBuf, config: &RenderConfig) -> Result<()> {
-    let img = create_qr_image(data, config)?;
-    img.save(output_path).map_err(|e| QrError::FileWrite {
-        path: output_path.clone(),
-        source: std::io::Error::new(std::io::ErrorKind::Other, e.to_string()),
-    })?;
-    Ok(())
+    render_to_image(data, output_path, ImageFormat::Png, config)
 }
 
-/// Render QR code to SVG string
 /// Render QR code to SVG string
 #[allow(clippy::cast_possible_truncation)]
 #[allow(clippy::cast_sign_loss)]
+#[allow(clippy::too_many_lines)]
+#[allow(clippy::option_if_let_else)]
 pub fn render_to_svg(data: &str, config: &RenderConfig) -> Result<String> {
     use base64::{engine::general_purpose::STANDARD, Engine};
     use image::codecs::png::PngEncoder;
     use image::ImageEncoder;
     use std::fmt::Write;
 
-    let code = create_qr_code(data, config.ec_level)?;
-    let module_count = code.width();
+    let (grid, logo) = logo_grid(data, config)?;
+
+    let total_modules = grid.width() + (config.quiet_zone as usize * 2);
+    let total_rows = grid.height() + (config.quiet_zone as usize * 2);
+    // Vector output takes fractional modules, so the code fills `size` exactly
+    let module_size = match config.module_size {
+        Some(0) => {
+            return Err(QrError::InvalidSize(
+                "module size must be at least 1 pixel".to_string(),
+            ))
+        }
+        Some(module_size) => f64::from(module_size),
+        None if config.target_size() == 0 => {
+            return Err(QrError::InvalidSize("size must be positive".to_string()))
+        }
+        None => f64::from(config.target_size()) / total_modules as f64,
+    };
+    let actual_size = module_size * total_modules as f64;
+    let actual_height = module_size * total_rows as f64;
 
-    let total_modules = module_count + (config.quiet_zone as usize * 2);
-    let module_size = config.size / total_modules as u32;
-    let actual_size = module_size * total_modules as u32;
+    let frame = FrameLayout::for_code(config, data, actual_size, actual_height);
+    let (canvas_width, canvas_height) = frame
+        .as_ref()
+        .map_or((actual_size, actual_height), |frame| {
+            (frame.width, frame.height)
+        });
 
-    let fg_hex = format!(
-        "#{:02x}{:02x}{:02x}",
-        config.fg_color.r, config.fg_color.g, config.fg_color.b
-    );
-    let bg_hex = format!(
-        "#{:02x}{:02x}{:02x}",
-        config.bg_color.r, config.bg_color.g, config.bg_color.b
-    );
+    // Physical size when printing, else user units (CSS pixels)
+    let mm_per_unit = config
+        .print_size_mm
+        .map(|size_mm| size_mm / actual_size)
+        .or_else(|| config.dpi.map(|dpi| MM_PER_INCH / f64::from(dpi)));
+    let display = |length: f64| {
+        mm_per_unit.map_or_else(
+            || fmt_num(length),
+            |mm_per_unit| format!("{}mm", fmt_num(length * mm_per_unit)),
+        )
+    };
 
     let mut svg = String::from(r#"<?xml version="1.0" encoding="UTF-8"?>"#);
-    write!(svg, r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 {size} {size}" width="{size}" height="{size}">"#, size = actual_size).unwrap();
+    write!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 {} {}" width="{}" height="{}">"#,
+        fmt_num(canvas_width),
+        fmt_num(canvas_height),
+        display(canvas_width),
+        display(canvas_height)
+    )
+    .unwrap();
 
     // Gradient definitions
-    let fill_attr = if let Some(grad_end) = config.gradient_color {
-        let grad_hex = format!("#{:02x}{:02x}{:02x}", grad_end.r, grad_end.g, grad_end.b);
-        svg.push_str(r#"<defs><linearGradient id="grad" x1="0%" y1="0%" x2="100%" y2="100%">"#);
+    let fill_attr = if let Some(gradient) = &config.gradient {
+        let id = format!("{}grad", config.svg_id_prefix);
         write!(
             svg,
-            r#"<stop offset="0%" style="stop-color:{};stop-opacity:1" />"#,
-            fg_hex
+            "<defs>{}</defs>",
+            gradient.to_svg_def(&id, actual_size, actual_height)
         )
         .unwrap();
-        write!(
-            svg,
-            r#"<stop offset="100%" style="stop-color:{};stop-opacity:1" />"#,
-            grad_hex
-        )
-        .unwrap();
-        svg.push_str(r"</linearGradient></defs>");
-        "url(#grad)".to_string()
+        format!(r#"fill="url(#{id})""#)
     } else {
-        fg_hex
+        config.fg_color.to_svg_fill()
     };
 
     write!(
         svg,
-        r#"<rect width="100%" height="100%" fill="{}"/>"#,
-        bg_hex
+        r#"<rect width="100%" height="100%" {}/>"#,
+        config.bg_color.to_svg_fill()
     )
     .unwrap();
 
-    let colors = code.to_colors();
-    let quiet_offset = config.quiet_zone * module_size;
-
-    for (i, color) in colors.iter().enumerate() {
-        if *color == qrcode::Color::Dark {
-            let x = (i % module_count) as u32 * module_size + quiet_offset;
-            let y = (i / module_count) as u32 * module_size + quiet_offset;
+    // Frame and caption go around the code, which moves into a group
+    if let Some(frame) = &frame {
+        for fill in &frame.fills {
             write!(
                 svg,
-                r#"<rect x="{}" y="{}" width="{}" height="{}" fill="{}"/>"#,
-                x, y, module_size, module_size, fill_attr
+                r#"<path d="{}" {} fill-rule="evenodd"/>"#,
+                fill.outline().to_svg_data(),
+                frame.frame_color.to_svg_fill()
             )
             .unwrap();
+        }
+        for line in &frame.texts {
+            svg.push_str(&line.to_svg());
+        }
+        write!(
+            svg,
+            r#"<g transform="translate({} {})">"#,
+            fmt_num(frame.code_x),
+            fmt_num(frame.code_y)
+        )
+        .unwrap();
+    }
+
+    let quiet_offset = f64::from(config.quiet_zone) * module_size;
+
+    if config.svg_mode == SvgMode::Compact {
+        write_compact_modules(
+            &mut svg,
+            &grid,
+            config,
+            module_size,
+            quiet_offset,
+            &fill_attr,
+        );
+    } else {
+        for (qr_x, qr_y) in grid.dark_modules() {
+            if grid.is_finder(qr_x, qr_y) {
+                continue;
+            }
+            let x = (qr_x as f64).mul_add(module_size, quiet_offset);
+            let y = (qr_y as f64).mul_add(module_size, quiet_offset);
+            if config.module_style == ModuleStyle::Square {
+                write!(
+                    svg,
+                    r#"<rect x="{}" y="{}" width="{}" height="{}" {}/>"#,
+                    fmt_num(x),
+                    fmt_num(y),
+                    fmt_num(module_size),
+                    fmt_num(module_size),
+                    fill_attr
+                )
+                .unwrap();
+            } else {
+                let path =
+                    config
+                        .module_style
+                        .outline(x, y, module_size, grid.neighbors(qr_x, qr_y));
+                write!(svg, r#"<path d="{}" {}/>"#, path.to_svg_data(), fill_attr).unwrap();
+            }
             svg.push('\n');
         }
+
+        // Finder patterns
+        for (eye, eye_x, eye_y) in grid.eyes() {
+            let x = (eye_x as f64).mul_add(module_size, quiet_offset);
+            let y = (eye_y as f64).mul_add(module_size, quiet_offset);
+            let ring = config.eye_outer_style.ring_outline(eye, x, y, module_size);
+            let pupil = config.eye_inner_style.pupil_outline(eye, x, y, module_size);
+            let ring_fill = config
+                .eye_outer_color
+                .map_or_else(|| fill_attr.clone(), Color::to_svg_fill);
+            let pupil_fill = config
+                .eye_inner_color
+                .map_or_else(|| fill_attr.clone(), Color::to_svg_fill);
+            writeln!(
+                svg,
+                r#"<path d="{}" {} fill-rule="evenodd"/>"#,
+                ring.to_svg_data(),
+                ring_fill
+            )
+            .unwrap();
+            writeln!(svg, r#"<path d="{}" {}/>"#, pupil.to_svg_data(), pupil_fill).unwrap();
+        }
     }
 
     // Logo support
-    if let Some(logo_path) = &config.logo {
-        let logo_img = image::open(logo_path).map_err(|e| QrError::FileWrite {
-            path: logo_path.clone(),
-            source: std::io::Error::new(
-                std::io::ErrorKind::Other,
-                format!("Failed to load logo: {}", e),
-            ),
-        })?;
-
-        // Calculate logo size (20% of QR)
-        let logo_size = actual_size / 5;
-        let logo_rgba = logo_img.to_rgba8();
-        let resized_logo = resize(&logo_rgba, logo_size, logo_size, FilterType::Lanczos3);
-
-        // Encode resized logo to PNG base64
-        let mut buffer = Vec::new();
-        let encoder = PngEncoder::new(&mut buffer);
-        encoder
-            .write_image(
-                resized_logo.as_raw(),
-                logo_size,
-                logo_size,
-                image::ColorType::Rgba8,
+    if let (Some(logo_path), Some(logo)) = (&config.logo, &logo) {
+        if let (Some(knockout), true) = (&logo.knockout, config.bg_color.a > 0) {
+            let scaled = RoundedBox {
+                x: knockout.x * module_size,
+                y: knockout.y * module_size,
+                width: knockout.width * module_size,
+                height: knockout.height * module_size,
+                radius: knockout.radius * module_size,
+            };
+            write!(
+                svg,
+                r#"<path d="{}" {}/>"#,
+                scaled.outline().to_svg_data(),
+                config.bg_color.to_svg_fill()
             )
-            .map_err(|e| QrError::ImageError(e.to_string()))?;
+            .unwrap();
+        }
 
-        let b64_logo = STANDARD.encode(&buffer);
-        let logo_uri = format!("data:image/png;base64,{}", b64_logo);
+        let logo_img = LogoImage::load(logo_path)?;
+        let (logo_x, logo_y) = (logo.x * module_size, logo.y * module_size);
+        let display_size = logo.size * module_size;
 
-        let center_x = (actual_size - logo_size) / 2;
-        let center_y = (actual_size - logo_size) / 2;
+        // Vector logos nest as SVG; bitmaps are embedded as PNG
+        if let Some(nested) = logo_img.to_svg(logo_x, logo_y, display_size, &config.svg_id_prefix) {
+            svg.push_str(&nested);
+        } else {
+            let logo_size = (display_size.round() as u32).max(1);
+            let resized_logo = logo_img.to_rgba(logo_size)?;
 
-        write!(
-            svg,
-            r#"<image x="{}" y="{}" width="{}" height="{}" href="{}" />"#,
-            center_x, center_y, logo_size, logo_size, logo_uri
-        )
-        .unwrap();
+            // Encode resized logo to PNG base64
+            let mut buffer = Vec::new();
+            let encoder = PngEncoder::new(&mut buffer);
+            encoder
+                .write_image(
+                    resized_logo.as_raw(),
+                    logo_size,
+                    logo_size,
+                    image::ColorType::Rgba8,
+                )
+                .map_err(|e| QrError::ImageError(e.to_string()))?;
+
+            let b64_logo = STANDARD.encode(&buffer);
+            let logo_uri = format!("data:image/png;base64,{}", b64_logo);
+
+            let display_size = fmt_num(display_size);
+
+            write!(
+                svg,
+                r#"<image x="{}" y="{}" width="{}" height="{}" href="{}" />"#,
+                fmt_num(logo_x),
+                fmt_num(logo_y),
+                display_size,
+                display_size,
+                logo_uri
+            )
+            .unwrap();
+        }
     }
 
+    if frame.is_some() {
+        svg.push_str("</g>");
+    }
     svg.push_str("</svg>");
     Ok(svg)
 }
 
+/// Write the modules as merged `<path>` elements, one per fill.
+///
+/// Square modules become rectangles covering whole runs and blocks, drawn
+/// with `crispEdges` so the seams between them don't show.
+fn write_compact_modules(
+    svg: &mut String,
+    grid: &ModuleGrid,
+    config: &RenderConfig,
+    module_size: f64,
+    quiet_offset: f64,
+    fill_attr: &str,
+) {
+    use std::fmt::Write;
+
+    const CRISP: &str = r#" shape-rendering="crispEdges""#;
+
+    // Square eyes in the module fill are ordinary modules and merge with the rest
+    let plain_eyes = config.module_style == ModuleStyle::Square
+        && config.eye_outer_style == EyeStyle::Square
+        && config.eye_inner_style == EyeStyle::Square
+        && config.eye_outer_color.is_none()
+        && config.eye_inner_color.is_none();
+
+    if config.module_style == ModuleStyle::Square {
+        let mut d = String::new();
+        for region in grid.dark_regions(plain_eyes) {
+            let width = fmt_num(region.width as f64 * module_size);
+            let _ = write!(
+                d,
+                "M{} {}h{}v{}h-{}z",
+                fmt_num((region.x as f64).mul_add(module_size, quiet_offset)),
+                fmt_num((region.y as f64).mul_add(module_size, quiet_offset)),
+                width,
+                fmt_num(region.height as f64 * module_size),
+                width
+            );
+        }
+        let _ = write!(svg, r#"<path d="{d}" {fill_attr}{CRISP}/>"#);
+    } else {
+        let mut modules = Path::default();
+        for (qr_x, qr_y) in grid.dark_modules() {
+            if !grid.is_finder(qr_x, qr_y) {
+                modules.extend(&config.module_style.outline(
+                    (qr_x as f64).mul_add(module_size, quiet_offset),
+                    (qr_y as f64).mul_add(module_size, quiet_offset),
+                    module_size,
+                    grid.neighbors(qr_x, qr_y),
+                ));
+            }
+        }
+        let _ = write!(svg, r#"<path d="{}" {fill_attr}/>"#, modules.to_svg_data());
+    }
+
+    if plain_eyes {
+        return;
+    }
+    let mut rings = Path::default();
+    let mut pupils = Path::default();
+    for (eye, eye_x, eye_y) in grid.eyes() {
+        let x = (eye_x as f64).mul_add(module_size, quiet_offset);
+        let y = (eye_y as f64).mul_add(module_size, quiet_offset);
+        rings.extend(&config.eye_outer_style.ring_outline(eye, x, y, module_size));
+        pupils.extend(&config.eye_inner_style.pupil_outline(eye, x, y, module_size));
+    }
+    let crisp = |style| if style == EyeStyle::Square { CRISP } else { "" };
+    let _ = write!(
+        svg,
+        r#"<path d="{}" {} fill-rule="evenodd"{}/>"#,
+        rings.to_svg_data(),
+        config
+            .eye_outer_color
+            .map_or_else(|| fill_attr.to_string(), Color::to_svg_fill),
+        crisp(config.eye_outer_style)
+    );
+    let _ = write!(
+        svg,
+        r#"<path d="{}" {}{}/>"#,
+        pupils.to_svg_data(),
+        config
+            .eye_inner_color
+            .map_or_else(|| fill_attr.to_string(), Color::to_svg_fill),
+        crisp(config.eye_inner_style)
+    );
+}
+
 /// Render QR code to SVG file
 pub fn render_to_svg_file(data: &str, output_path: &PathBuf, config: &RenderConfig) -> Result<()> {
     let svg = render_to_svg(data, config)?;
@@ -333,19 +1179,7 @@ pub fn render_to_svg_file(data: &str, output_path: &PathBuf, config: &RenderConf
 pub fn render_to_base64(data: &str, config: &RenderConfig) -> Result<String> {
     use base64::{engine::general_purpose::STANDARD, Engine};
 
-    let img = create_qr_image(data, config)?;
-    let (width, height) = img.dimensions();
-
-    // Encode to PNG in memory
-    let mut buffer = Vec::new();
-    {
-        use image::codecs::png::PngEncoder;
-        use image::ImageEncoder;
-        let encoder = PngEncoder::new(&mut buffer);
-        encoder.write_image(img.as_raw(), width, height, image::ColorType::Rgba8)?;
-    }
-
-    // Convert to base64
+    let buffer = encode_image(data, ImageFormat::Png, config)?;
     let b64 = STANDARD.encode(&buffer);
     Ok(format!("data:image/png;base64,{b64}"))
 }
@@ -0,0 +1,511 @@
+//! rMQR (rectangular Micro QR, ISO/IEC 23941) encoder
+//!
+//! The `qrcode` crate only covers QR and Micro QR, so rMQR symbols are built
+//! here: version selection, bit stream, Reed-Solomon blocks, function
+//! patterns, data placement, masking and format information.
+
+#![allow(clippy::cast_possible_truncation)]
+
+use qrcode::EcLevel;
+
+use crate::error::{QrError, Result};
+use crate::shapes::{Eye, ModuleGrid};
+
+/// Error correction blocks of one level: codewords per block, and
+/// `(block count, data codewords per block)` for up to two groups
+#[derive(Clone, Copy)]
+struct Blocks {
+    ec_per_block: usize,
+    groups: [(usize, usize); 2],
+}
+
+impl Blocks {
+    fn data_codewords(self) -> usize {
+        self.groups.iter().map(|&(count, data)| count * data).sum()
+    }
+}
+
+/// Size and layout of one rMQR version
+struct Version {
+    height: usize,
+    width: usize,
+    /// Columns of the alignment patterns and vertical timing patterns
+    alignment: &'static [usize],
+    m: Blocks,
+    h: Blocks,
+    /// Character count indicator bits: numeric, alphanumeric, byte
+    count_bits: [usize; 3],
+}
+
+const fn blocks(ec_per_block: usize, groups: [(usize, usize); 2]) -> Blocks {
+    Blocks {
+        ec_per_block,
+        groups,
+    }
+}
+
+const fn version(
+    (height, width): (usize, usize),
+    alignment: &'static [usize],
+    m: Blocks,
+    h: Blocks,
+    count_bits: [usize; 3],
+) -> Version {
+    Version {
+        height,
+        width,
+        alignment,
+        m,
+        h,
+        count_bits,
+    }
+}
+
+/// All 32 versions in format information order (ISO/IEC 23941 Tables 1, 3, 8)
+#[rustfmt::skip]
+const VERSIONS: [Version; 32] = [
+    version((7, 43), &[21], blocks(7, [(1, 6), (0, 0)]), blocks(10, [(1, 3), (0, 0)]), [4, 3, 3]),
+    version((7, 59), &[19, 39], blocks(9, [(1, 12), (0, 0)]), blocks(14, [(1, 7), (0, 0)]), [5, 5, 4]),
+    version((7, 77), &[25, 51], blocks(12, [(1, 20), (0, 0)]), blocks(22, [(1, 10), (0, 0)]), [6, 5, 5]),
+    version((7, 99), &[23, 49, 75], blocks(16, [(1, 28), (0, 0)]), blocks(30, [(1, 14), (0, 0)]), [7, 6, 5]),
+    version((7, 139), &[27, 55, 83, 111], blocks(24, [(1, 44), (0, 0)]), blocks(22, [(2, 12), (0, 0)]), [7, 6, 6]),
+    version((9, 43), &[21], blocks(9, [(1, 12), (0, 0)]), blocks(14, [(1, 7), (0, 0)]), [5, 5, 4]),
+    version((9, 59), &[19, 39], blocks(12, [(1, 21), (0, 0)]), blocks(22, [(1, 11), (0, 0)]), [6, 5, 5]),
+    version((9, 77), &[25, 51], blocks(18, [(1, 31), (0, 0)]), blocks(16, [(1, 8), (1, 9)]), [7, 6, 5]),
+    version((9, 99), &[23, 49, 75], blocks(24, [(1, 42), (0, 0)]), blocks(22, [(2, 11), (0, 0)]), [7, 6, 6]),
+    version((9, 139), &[27, 55, 83, 111], blocks(18, [(1, 31), (1, 32)]), blocks(22, [(3, 11), (0, 0)]), [8, 7, 6]),
+    version((11, 27), &[], blocks(8, [(1, 7), (0, 0)]), blocks(10, [(1, 5), (0, 0)]), [4, 4, 3]),
+    version((11, 43), &[21], blocks(12, [(1, 19), (0, 0)]), blocks(20, [(1, 11), (0, 0)]), [6, 5, 5]),
+    version((11, 59), &[19, 39], blocks(16, [(1, 31), (0, 0)]), blocks(16, [(1, 7), (1, 8)]), [7, 6, 5]),
+    version((11, 77), &[25, 51], blocks(24, [(1, 43), (0, 0)]), blocks(22, [(1, 11), (1, 12)]), [7, 6, 6]),
+    version((11, 99), &[23, 49, 75], blocks(16, [(1, 28), (1, 29)]), blocks(30, [(1, 14), (1, 15)]), [8, 7, 6]),
+    version((11, 139), &[27, 55, 83, 111], blocks(24, [(2, 42), (0, 0)]), blocks(30, [(3, 14), (0, 0)]), [8, 7, 7]),
+    version((13, 27), &[], blocks(9, [(1, 12), (0, 0)]), blocks(14, [(1, 7), (0, 0)]), [5, 5, 4]),
+    version((13, 43), &[21], blocks(14, [(1, 27), (0, 0)]), blocks(28, [(1, 13), (0, 0)]), [6, 6, 5]),
+    version((13, 59), &[19, 39], blocks(22, [(1, 38), (0, 0)]), blocks(20, [(2, 10), (0, 0)]), [7, 6, 6]),
+    version((13, 77), &[25, 51], blocks(16, [(1, 26), (1, 27)]), blocks(28, [(1, 14), (1, 15)]), [7, 7, 6]),
+    version((13, 99), &[23, 49, 75], blocks(20, [(1, 36), (1, 37)]), blocks(26, [(1, 11), (2, 12)]), [8, 7, 7]),
+    version((13, 139), &[27, 55, 83, 111], blocks(20, [(2, 35), (1, 36)]), blocks(28, [(2, 13), (2, 14)]), [8, 8, 7]),
+    version((15, 43), &[21], blocks(18, [(1, 33), (0, 0)]), blocks(18, [(1, 7), (1, 8)]), [7, 6, 6]),
+    version((15, 59), &[19, 39], blocks(26, [(1, 48), (0, 0)]), blocks(24, [(2, 13), (0, 0)]), [7, 7, 6]),
+    version((15, 77), &[25, 51], blocks(18, [(1, 33), (1, 34)]), blocks(24, [(2, 10), (1, 11)]), [8, 7, 7]),
+    version((15, 99), &[23, 49, 75], blocks(24, [(2, 44), (0, 0)]), blocks(22, [(4, 12), (0, 0)]), [8, 7, 7]),
+    version((15, 139), &[27, 55, 83, 111], blocks(24, [(2, 42), (1, 43)]), blocks(26, [(1, 13), (4, 14)]), [9, 8, 7]),
+    version((17, 43), &[21], blocks(22, [(1, 39), (0, 0)]), blocks(20, [(1, 10), (1, 11)]), [7, 6, 6]),
+    version((17, 59), &[19, 39], blocks(16, [(2, 28), (0, 0)]), blocks(30, [(2, 14), (0, 0)]), [8, 7, 6]),
+    version((17, 77), &[25, 51], blocks(22, [(2, 39), (0, 0)]), blocks(28, [(1, 12), (2, 13)]), [8, 7, 7]),
+    version((17, 99), &[23, 49, 75], blocks(20, [(2, 33), (1, 34)]), blocks(26, [(4, 14), (0, 0)]), [8, 8, 7]),
+    version((17, 139), &[27, 55, 83, 111], blocks(20, [(4, 38), (0, 0)]), blocks(26, [(2, 12), (4, 13)]), [9, 8, 8]),
+];
+
+/// Characters of the alphanumeric mode, in code order
+const ALPHANUMERIC: &[u8] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ $%*+-./:";
+
+/// Format information masks for the finder and finder sub-pattern sides
+const FORMAT_MASK: u32 = 0x1_FAB2;
+const FORMAT_MASK_SUB: u32 = 0x2_0A7B;
+
+/// BCH (18, 6) generator polynomial of the format information
+const FORMAT_GENERATOR: u32 = 0x1F25;
+
+/// Data encoding mode, with its index into `Version::count_bits`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Mode {
+    Numeric,
+    Alphanumeric,
+    Byte,
+}
+
+impl Mode {
+    /// Most compact mode that can hold all of `data`
+    fn for_data(data: &[u8]) -> Self {
+        if data.iter().all(u8::is_ascii_digit) {
+            Self::Numeric
+        } else if data.iter().all(|c| ALPHANUMERIC.contains(c)) {
+            Self::Alphanumeric
+        } else {
+            Self::Byte
+        }
+    }
+
+    const fn indicator(self) -> u32 {
+        match self {
+            Self::Numeric => 0b001,
+            Self::Alphanumeric => 0b010,
+            Self::Byte => 0b011,
+        }
+    }
+
+    const fn index(self) -> usize {
+        match self {
+            Self::Numeric => 0,
+            Self::Alphanumeric => 1,
+            Self::Byte => 2,
+        }
+    }
+
+    /// Bits taken by `len` characters, without mode and count indicators
+    const fn payload_bits(self, len: usize) -> usize {
+        match self {
+            Self::Numeric => len / 3 * 10 + [0, 4, 7][len % 3],
+            Self::Alphanumeric => len / 2 * 11 + len % 2 * 6,
+            Self::Byte => len * 8,
+        }
+    }
+}
+
+/// Bit stream writer, most significant bit first
+#[derive(Default)]
+pub(crate) struct Bits {
+    bits: Vec<bool>,
+}
+
+impl Bits {
+    pub(crate) fn len(&self) -> usize {
+        self.bits.len()
+    }
+
+    pub(crate) fn push(&mut self, value: u32, count: usize) {
+        self.bits
+            .extend((0..count).rev().map(|i| (value >> i) & 1 == 1));
+    }
+
+    pub(crate) fn to_bytes(&self) -> Vec<u8> {
+        self.bits
+            .chunks(8)
+            .map(|chunk| {
+                chunk
+                    .iter()
+                    .enumerate()
+                    .fold(0, |byte, (i, &bit)| byte | (u8::from(bit) << (7 - i)))
+            })
+            .collect()
+    }
+}
+
+/// Encode `data` as the smallest rMQR symbol (by area) that holds it.
+///
+/// Only error correction levels M and H exist for rMQR.
+pub fn encode(data: &str, ec_level: EcLevel) -> Result<ModuleGrid> {
+    let high = match ec_level {
+        EcLevel::M => false,
+        EcLevel::H => true,
+        EcLevel::L | EcLevel::Q => {
+            return Err(QrError::QrGeneration(format!(
+                "rMQR supports error correction levels M and H, not {ec_level:?}"
+            )))
+        }
+    };
+
+    let data = data.as_bytes();
+    let mode = Mode::for_data(data);
+    let (index, version) = VERSIONS
+        .iter()
+        .enumerate()
+        .filter(|(_, version)| {
+            let count_bits = version.count_bits[mode.index()];
+            let capacity = version.blocks(high).data_codewords() * 8;
+            data.len() < 1 << count_bits
+                && 3 + count_bits + mode.payload_bits(data.len()) <= capacity
+        })
+        .min_by_key(|(_, version)| (version.width * version.height, version.height))
+        .ok_or_else(|| {
+            QrError::QrGeneration(
+                "data too long for an rMQR symbol; use a QR code or error correction level M"
+                    .to_string(),
+            )
+        })?;
+
+    let codewords = version.codewords(&version.data_codewords(data, mode, high), high);
+    Ok(version.build(index, high, &codewords))
+}
+
+impl Version {
+    const fn blocks(&self, high: bool) -> Blocks {
+        if high {
+            self.h
+        } else {
+            self.m
+        }
+    }
+
+    /// Mode and count indicators, payload, terminator and padding
+    fn data_codewords(&self, data: &[u8], mode: Mode, high: bool) -> Vec<u8> {
+        let capacity = self.blocks(high).data_codewords();
+        let mut bits = Bits::default();
+        bits.push(mode.indicator(), 3);
+        bits.push(data.len() as u32, self.count_bits[mode.index()]);
+        match mode {
+            Mode::Numeric => {
+                for group in data.chunks(3) {
+                    let value = group
+                        .iter()
+                        .fold(0, |value, digit| value * 10 + u32::from(digit - b'0'));
+                    bits.push(value, [0, 4, 7, 10][group.len()]);
+                }
+            }
+            Mode::Alphanumeric => {
+                let code = |c: &u8| ALPHANUMERIC.iter().position(|a| a == c).unwrap_or(0) as u32;
+                for pair in data.chunks(2) {
+                    match pair {
+                        [a, b] => bits.push(code(a) * 45 + code(b), 11),
+                        [a] => bits.push(code(a), 6),
+                        _ => {}
+                    }
+                }
+            }
+            Mode::Byte => {
+                for &byte in data {
+                    bits.push(u32::from(byte), 8);
+                }
+            }
+        }
+
+        // Terminator where it fits, then zero bits up to a whole codeword
+        let terminator = (capacity * 8 - bits.len()).min(3);
+        bits.push(0, terminator);
+        bits.push(0, (8 - bits.len() % 8) % 8);
+
+        let mut codewords = bits.to_bytes();
+        let padding = [0xEC, 0x11].iter().copied().cycle();
+        let missing = capacity - codewords.len();
+        codewords.extend(padding.take(missing));
+        codewords
+    }
+
+    /// Split the data into blocks, add error correction and interleave
+    fn codewords(&self, data: &[u8], high: bool) -> Vec<u8> {
+        let blocks = self.blocks(high);
+        let mut data_blocks = Vec::new();
+        let mut rest = data;
+        for &(count, size) in &blocks.groups {
+            for _ in 0..count {
+                let (block, tail) = rest.split_at(size);
+                data_blocks.push(block);
+                rest = tail;
+            }
+        }
+        let ec_blocks: Vec<Vec<u8>> = data_blocks
+            .iter()
+            .map(|block| reed_solomon(block, blocks.ec_per_block))
+            .collect();
+
+        let longest = data_blocks
+            .iter()
+            .map(|block| block.len())
+            .max()
+            .unwrap_or(0);
+        let mut codewords = Vec::new();
+        for i in 0..longest {
+            codewords.extend(data_blocks.iter().filter_map(|block| block.get(i)));
+        }
+        for i in 0..blocks.ec_per_block {
+            codewords.extend(ec_blocks.iter().map(|block| block[i]));
+        }
+        codewords
+    }
+
+    /// Lay out the function patterns and the masked data modules
+    fn build(&self, index: usize, high: bool, codewords: &[u8]) -> ModuleGrid {
+        let (width, height) = (self.width, self.height);
+        let mut symbol = Symbol::new(width, height);
+
+        // Timing patterns along all four edges
+        for x in 0..width {
+            symbol.set(x, 0, x % 2 == 0);
+            symbol.set(x, height - 1, x % 2 == 0);
+        }
+        for y in 0..height {
+            symbol.set(0, y, y % 2 == 0);
+            symbol.set(width - 1, y, y % 2 == 0);
+        }
+
+        // Alignment patterns at both edges, joined by vertical timing patterns
+        for &column in self.alignment {
+            for y in 3..height - 3 {
+                symbol.set(column, y, y % 2 == 0);
+            }
+            for center in [1, height - 2] {
+                for dy in 0..3 {
+                    for dx in 0..3 {
+                        symbol.set(column + dx - 1, center + dy - 1, (dx, dy) != (1, 1));
+                    }
+                }
+            }
+        }
+
+        // Finder pattern and its separator
+        for y in 0..height.min(8) {
+            for x in 0..8usize {
+                let ring = x.abs_diff(3).max(y.abs_diff(3));
+                symbol.set(x, y, x < 7 && y < 7 && ring != 2);
+            }
+        }
+
+        // Finder sub-pattern in the bottom-right corner
+        for dy in 0..5usize {
+            for dx in 0..5usize {
+                let ring = dx.abs_diff(2).max(dy.abs_diff(2));
+                symbol.set(width - 5 + dx, height - 5 + dy, ring != 1);
+            }
+        }
+
+        // Corner finder patterns in the other two corners
+        symbol.set(width - 2, 0, true);
+        symbol.set(width - 1, 1, true);
+        symbol.set(width - 2, 1, false);
+        symbol.set(1, height - 1, true);
+        if height > 9 {
+            symbol.set(0, height - 2, true);
+            symbol.set(1, height - 2, false);
+        }
+
+        // Format information, written after the data (reserve it now)
+        let finder_side = format_cells(width, height, false);
+        let sub_side = format_cells(width, height, true);
+        for &(x, y) in finder_side.iter().chain(&sub_side) {
+            symbol.set(x, y, false);
+        }
+
+        symbol.place_data(codewords);
+
+        let info = format_information(index, high);
+        for (bits, cells) in [
+            (info ^ FORMAT_MASK, finder_side),
+            (info ^ FORMAT_MASK_SUB, sub_side),
+        ] {
+            for (i, &(x, y)) in cells.iter().enumerate() {
+                symbol.set(x, y, (bits >> (17 - i)) & 1 == 1);
+            }
+        }
+
+        ModuleGrid::from_modules(width, height, symbol.dark, &[Eye::TopLeft])
+    }
+}
+
+/// Module grid under construction, tracking which cells are function patterns
+struct Symbol {
+    width: usize,
+    height: usize,
+    dark: Vec<bool>,
+    function: Vec<bool>,
+}
+
+impl Symbol {
+    fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            dark: vec![false; width * height],
+            function: vec![false; width * height],
+        }
+    }
+
+    fn set(&mut self, x: usize, y: usize, dark: bool) {
+        let i = y * self.width + x;
+        self.dark[i] = dark;
+        self.function[i] = true;
+    }
+
+    /// Place the codeword bits in two-module columns, zig-zagging up and down
+    /// from the right (inside the timing pattern), and apply the data mask
+    fn place_data(&mut self, codewords: &[u8]) {
+        let mut bits = codewords
+            .iter()
+            .flat_map(|&byte| (0..8).rev().map(move |i| (byte >> i) & 1 == 1));
+        let mut upwards = true;
+        let mut x = self.width - 2;
+        while x > 0 {
+            for row in 0..self.height {
+                let y = if upwards { self.height - 1 - row } else { row };
+                for column in [x, x - 1] {
+                    let i = y * self.width + column;
+                    if !self.function[i] {
+                        // Remainder bits past the last codeword are zero
+                        let bit = bits.next().unwrap_or(false);
+                        let mask = (y / 2 + column / 3) % 2 == 0;
+                        self.dark[i] = bit != mask;
+                    }
+                }
+            }
+            upwards = !upwards;
+            x = x.saturating_sub(2);
+        }
+    }
+}
+
+/// Cells of one format information copy, most significant bit first
+fn format_cells(width: usize, height: usize, sub_side: bool) -> Vec<(usize, usize)> {
+    let mut cells = Vec::with_capacity(18);
+    if sub_side {
+        cells.extend((3..=5).map(|dx| (width - dx, height - 6)));
+        for dx in 6..=8 {
+            cells.extend((2..=6).map(|dy| (width - dx, height - dy)));
+        }
+    } else {
+        cells.extend((1..=3).rev().map(|y| (11, y)));
+        for x in (8..=10).rev() {
+            cells.extend((1..=5).rev().map(|y| (x, y)));
+        }
+    }
+    cells
+}
+
+/// Error correction level bit and version index, with their BCH code
+fn format_information(index: usize, high: bool) -> u32 {
+    let data = (u32::from(high) << 5 | index as u32) << 12;
+    let mut remainder = data;
+    for bit in (12..18).rev() {
+        if remainder & (1 << bit) != 0 {
+            remainder ^= FORMAT_GENERATOR << (bit - 12);
+        }
+    }
+    data | remainder
+}
+
+/// Reed-Solomon error correction codewords over GF(256), polynomial 0x11D
+fn reed_solomon(data: &[u8], ec_len: usize) -> Vec<u8> {
+    let mut exp = [0u8; 512];
+    let mut log = [0u8; 256];
+    let mut value = 1u16;
+    for (i, slot) in exp.iter_mut().enumerate().take(255) {
+        *slot = value as u8;
+        log[value as usize] = i as u8;
+        value <<= 1;
+        if value & 0x100 != 0 {
+            value ^= 0x11D;
+        }
+    }
+    for i in 255..512 {
+        exp[i] = exp[i - 255];
+    }
+    let multiply = |a: u8, b: u8| {
+        if a == 0 || b == 0 {
+            0
+        } else {
+            exp[usize::from(log[usize::from(a)]) + usize::from(log[usize::from(b)])]
+        }
+    };
+
+    // Generator polynomial (x - α^0)(x - α^1)…, highest degree first
+    let mut generator = vec![1u8];
+    for &root in &exp[..ec_len] {
+        let mut next = vec![0u8; generator.len() + 1];
+        for (j, &coefficient) in generator.iter().enumerate() {
+            next[j] ^= coefficient;
+            next[j + 1] ^= multiply(coefficient, root);
+        }
+        generator = next;
+    }
+
+    let mut remainder = vec![0u8; ec_len];
+    for &byte in data {
+        let factor = byte ^ remainder[0];
+        remainder.rotate_left(1);
+        remainder[ec_len - 1] = 0;
+        for (slot, &coefficient) in remainder.iter_mut().zip(&generator[1..]) {
+            *slot ^= multiply(coefficient, factor);
+        }
+    }
+    remainder
+}
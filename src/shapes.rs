@@ -0,0 +1,545 @@
+//! Module shape geometry shared by the raster and vector renderers
+
+#![allow(clippy::cast_precision_loss)]
+
+use std::fmt::Write;
+
+use clap::ValueEnum;
+use qrcode::QrCode;
+
+/// Size of a finder pattern ("eye") in modules
+pub const FINDER_SIZE: usize = 7;
+
+/// Cubic Bézier control distance for approximating a quarter circle
+const KAPPA: f64 = 0.552_284_749_8;
+
+/// Shape used to paint each dark data module
+#[derive(Clone, Copy, ValueEnum, Debug, Default, PartialEq, Eq)]
+pub enum ModuleStyle {
+    /// Solid squares (classic look)
+    #[default]
+    Square,
+    /// Circular dots
+    Dots,
+    /// Squares with rounded corners
+    Rounded,
+    /// Diamonds (squares rotated by 45°)
+    Diamond,
+    /// Connected blobs where adjacent dark modules merge
+    Liquid,
+}
+
+impl ModuleStyle {
+    /// Corner radii as a fraction of the module size (top-left, top-right,
+    /// bottom-right, bottom-left), or `None` for non-rectangular shapes
+    fn corner_radii(self, n: Neighbors) -> Option<[f64; 4]> {
+        match self {
+            Self::Square => Some([0.0; 4]),
+            Self::Dots => Some([0.5; 4]),
+            Self::Rounded => Some([0.25; 4]),
+            Self::Diamond => None,
+            Self::Liquid => {
+                // A corner is only rounded when neither module touching it is dark,
+                // so runs of dark modules melt into a single blob
+                let round = |a: bool, b: bool| if a || b { 0.0 } else { 0.5 };
+                Some([
+                    round(n.top, n.left),
+                    round(n.top, n.right),
+                    round(n.bottom, n.right),
+                    round(n.bottom, n.left),
+                ])
+            }
+        }
+    }
+
+    /// Whether the point `(u, v)` of a unit module cell is painted
+    #[must_use]
+    pub fn contains(self, u: f64, v: f64, n: Neighbors) -> bool {
+        self.corner_radii(n).map_or_else(
+            || (u - 0.5).abs() + (v - 0.5).abs() <= 0.5,
+            |radii| rounded_rect_contains(u, v, radii),
+        )
+    }
+
+    /// Vector outline of a module at `(x, y)` with side length `size`
+    #[must_use]
+    pub fn outline(self, x: f64, y: f64, size: f64, n: Neighbors) -> Path {
+        self.corner_radii(n).map_or_else(
+            || {
+                let half = size / 2.0;
+                Path::polygon(&[
+                    (x + half, y),
+                    (x + size, y + half),
+                    (x + half, y + size),
+                    (x, y + half),
+                ])
+            },
+            |radii| Path::rounded_rect(x, y, size, size, radii.map(|r| r * size)),
+        )
+    }
+}
+
+/// Shape used for the outer ring or the inner pupil of a finder pattern
+#[derive(Clone, Copy, ValueEnum, Debug, Default, PartialEq, Eq)]
+pub enum EyeStyle {
+    /// Sharp square (classic look)
+    #[default]
+    Square,
+    /// Square with rounded corners
+    Rounded,
+    /// Full circle
+    Circle,
+    /// Rounded everywhere except the corner facing the center of the code
+    Leaf,
+}
+
+impl EyeStyle {
+    /// Corner radii as a fraction of the box size (top-left, top-right,
+    /// bottom-right, bottom-left)
+    const fn corner_radii(self, eye: Eye) -> [f64; 4] {
+        match (self, eye) {
+            (Self::Square, _) => [0.0; 4],
+            (Self::Rounded, _) => [0.25; 4],
+            (Self::Circle, _) => [0.5; 4],
+            (Self::Leaf, Eye::TopLeft) => [0.5, 0.5, 0.0, 0.5],
+            (Self::Leaf, Eye::TopRight) => [0.5, 0.5, 0.5, 0.0],
+            (Self::Leaf, Eye::BottomLeft) => [0.5, 0.0, 0.5, 0.5],
+        }
+    }
+
+    /// Whether the point `(u, v)` of a unit box is inside the shape
+    fn contains(self, eye: Eye, u: f64, v: f64) -> bool {
+        rounded_rect_contains(u, v, self.corner_radii(eye))
+    }
+
+    /// Vector outline of the shape in a box at `(x, y)` with side length `size`
+    fn outline(self, eye: Eye, x: f64, y: f64, size: f64) -> Path {
+        Path::rounded_rect(x, y, size, size, self.corner_radii(eye).map(|r| r * size))
+    }
+
+    /// Whether the point `(u, v)` of the unit finder box is on the outer ring
+    #[must_use]
+    pub fn ring_contains(self, eye: Eye, u: f64, v: f64) -> bool {
+        let to_hole = |t: f64| t.mul_add(FINDER_SIZE as f64, -1.0) / (FINDER_SIZE - 2) as f64;
+        self.contains(eye, u, v) && !self.contains(eye, to_hole(u), to_hole(v))
+    }
+
+    /// Whether the point `(u, v)` of the unit finder box is on the pupil
+    #[must_use]
+    pub fn pupil_contains(self, eye: Eye, u: f64, v: f64) -> bool {
+        let to_pupil = |t: f64| t.mul_add(FINDER_SIZE as f64, -2.0) / (FINDER_SIZE - 4) as f64;
+        self.contains(eye, to_pupil(u), to_pupil(v))
+    }
+
+    /// Outline of the outer ring; the hole relies on the even-odd fill rule
+    #[must_use]
+    pub fn ring_outline(self, eye: Eye, x: f64, y: f64, module_size: f64) -> Path {
+        let mut path = self.outline(eye, x, y, FINDER_SIZE as f64 * module_size);
+        path.extend(&self.outline(
+            eye,
+            x + module_size,
+            y + module_size,
+            (FINDER_SIZE - 2) as f64 * module_size,
+        ));
+        path
+    }
+
+    /// Outline of the 3x3 pupil
+    #[must_use]
+    pub fn pupil_outline(self, eye: Eye, x: f64, y: f64, module_size: f64) -> Path {
+        self.outline(
+            eye,
+            2.0f64.mul_add(module_size, x),
+            2.0f64.mul_add(module_size, y),
+            (FINDER_SIZE - 4) as f64 * module_size,
+        )
+    }
+}
+
+/// One of the three finder patterns
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Eye {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+}
+
+impl Eye {
+    pub const ALL: [Self; 3] = [Self::TopLeft, Self::TopRight, Self::BottomLeft];
+
+    /// Top-left module of this finder pattern in a code `width` by `height`
+    /// modules
+    #[must_use]
+    pub const fn origin(self, width: usize, height: usize) -> (usize, usize) {
+        match self {
+            Self::TopLeft => (0, 0),
+            Self::TopRight => (width.saturating_sub(FINDER_SIZE), 0),
+            Self::BottomLeft => (0, height.saturating_sub(FINDER_SIZE)),
+        }
+    }
+}
+
+/// Dark/light state of the four orthogonal neighbours of a module
+#[allow(clippy::struct_excessive_bools)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Neighbors {
+    pub top: bool,
+    pub right: bool,
+    pub bottom: bool,
+    pub left: bool,
+}
+
+/// Whether `(u, v)` lies inside the unit square with the given corner radii
+fn rounded_rect_contains(u: f64, v: f64, radii: [f64; 4]) -> bool {
+    if !(0.0..=1.0).contains(&u) || !(0.0..=1.0).contains(&v) {
+        return false;
+    }
+    // Corner centers, in the same order as the radii
+    let corners = [
+        (radii[0], radii[0], u < radii[0] && v < radii[0]),
+        (1.0 - radii[1], radii[1], u > 1.0 - radii[1] && v < radii[1]),
+        (
+            1.0 - radii[2],
+            1.0 - radii[2],
+            u > 1.0 - radii[2] && v > 1.0 - radii[2],
+        ),
+        (radii[3], 1.0 - radii[3], u < radii[3] && v > 1.0 - radii[3]),
+    ];
+    corners.iter().zip(radii).all(|(&(cx, cy, in_corner), r)| {
+        !in_corner || (u - cx).mul_add(u - cx, (v - cy) * (v - cy)) <= r * r
+    })
+}
+
+/// Dark module lookup for an encoded symbol
+#[derive(Debug)]
+pub struct ModuleGrid {
+    width: usize,
+    height: usize,
+    dark: Vec<bool>,
+    eyes: &'static [Eye],
+}
+
+impl ModuleGrid {
+    /// Build the grid from an encoded QR or Micro QR code
+    #[must_use]
+    pub fn new(code: &QrCode) -> Self {
+        let width = code.width();
+        let dark = code
+            .to_colors()
+            .into_iter()
+            .map(|c| c == qrcode::Color::Dark)
+            .collect();
+        // Micro QR has a single finder pattern
+        let eyes: &[Eye] = if code.version().is_micro() {
+            &[Eye::TopLeft]
+        } else {
+            &Eye::ALL
+        };
+        Self::from_modules(width, width, dark, eyes)
+    }
+
+    /// Build the grid from modules listed row by row, with finder patterns
+    /// at the given corners
+    #[must_use]
+    pub fn from_modules(
+        width: usize,
+        height: usize,
+        dark: Vec<bool>,
+        eyes: &'static [Eye],
+    ) -> Self {
+        debug_assert_eq!(dark.len(), width * height);
+        Self {
+            width,
+            height,
+            dark,
+            eyes,
+        }
+    }
+
+    /// Number of module columns (without quiet zone)
+    #[must_use]
+    pub const fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Number of module rows (without quiet zone); equal to the width except
+    /// for rectangular symbols
+    #[must_use]
+    pub const fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Finder patterns with the top-left module of each
+    pub fn eyes(&self) -> impl Iterator<Item = (Eye, usize, usize)> + '_ {
+        self.eyes.iter().map(|&eye| {
+            let (x, y) = eye.origin(self.width, self.height);
+            (eye, x, y)
+        })
+    }
+
+    /// Whether the module at `(x, y)` is dark; out-of-range modules are light
+    #[must_use]
+    pub fn is_dark(&self, x: usize, y: usize) -> bool {
+        x < self.width && y < self.height && self.dark[y * self.width + x]
+    }
+
+    /// Turn the module at `(x, y)` light
+    pub fn set_light(&mut self, x: usize, y: usize) {
+        if x < self.width && y < self.height {
+            self.dark[y * self.width + x] = false;
+        }
+    }
+
+    /// Dark/light state of the modules around `(x, y)`
+    #[must_use]
+    pub fn neighbors(&self, x: usize, y: usize) -> Neighbors {
+        Neighbors {
+            top: y.checked_sub(1).is_some_and(|y| self.is_dark(x, y)),
+            right: self.is_dark(x + 1, y),
+            bottom: self.is_dark(x, y + 1),
+            left: x.checked_sub(1).is_some_and(|x| self.is_dark(x, y)),
+        }
+    }
+
+    /// Whether `(x, y)` belongs to one of the finder patterns
+    #[must_use]
+    pub fn is_finder(&self, x: usize, y: usize) -> bool {
+        self.eyes().any(|(_, eye_x, eye_y)| {
+            (eye_x..eye_x + FINDER_SIZE).contains(&x) && (eye_y..eye_y + FINDER_SIZE).contains(&y)
+        })
+    }
+
+    /// Coordinates of every dark module, row by row
+    pub fn dark_modules(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        (0..self.width * self.height)
+            .filter(|&i| self.dark[i])
+            .map(|i| (i % self.width, i / self.width))
+    }
+
+    /// Cover the dark modules with rectangles: horizontal runs, extended
+    /// downwards while the rows below repeat the same run
+    #[must_use]
+    pub fn dark_regions(&self, include_finders: bool) -> Vec<Region> {
+        let mut regions: Vec<Region> = Vec::new();
+        // Regions that reach the previous row, by (x, width)
+        let mut open: Vec<usize> = Vec::new();
+
+        for y in 0..self.height {
+            let mut still_open = Vec::new();
+            let mut x = 0;
+            while x < self.width {
+                let is_run = |x| self.is_dark(x, y) && (include_finders || !self.is_finder(x, y));
+                if !is_run(x) {
+                    x += 1;
+                    continue;
+                }
+                let start = x;
+                while x < self.width && is_run(x) {
+                    x += 1;
+                }
+                let width = x - start;
+
+                if let Some(&index) = open
+                    .iter()
+                    .find(|&&i| regions[i].x == start && regions[i].width == width)
+                {
+                    regions[index].height += 1;
+                    still_open.push(index);
+                } else {
+                    still_open.push(regions.len());
+                    regions.push(Region {
+                        x: start,
+                        y,
+                        width,
+                        height: 1,
+                    });
+                }
+            }
+            open = still_open;
+        }
+        regions
+    }
+}
+
+/// Rectangle of dark modules, in module coordinates
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Region {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+/// Rectangle with uniformly rounded corners
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RoundedBox {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+    pub radius: f64,
+}
+
+impl RoundedBox {
+    /// Whether the point lies inside the box
+    #[allow(clippy::many_single_char_names)]
+    #[must_use]
+    pub fn contains(&self, x: f64, y: f64) -> bool {
+        let (u, v) = (x - self.x, y - self.y);
+        if u < 0.0 || v < 0.0 || u > self.width || v > self.height {
+            return false;
+        }
+        let r = self.radius.min(self.width / 2.0).min(self.height / 2.0);
+        // Distance into the corner square, if the point is in one
+        let dx = (r - u).max(u - (self.width - r)).max(0.0);
+        let dy = (r - v).max(v - (self.height - r)).max(0.0);
+        dx * dx + dy * dy <= r * r
+    }
+
+    #[must_use]
+    pub fn outline(&self) -> Path {
+        Path::rounded_rect(self.x, self.y, self.width, self.height, [self.radius; 4])
+    }
+}
+
+/// A single outline command in absolute coordinates
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Segment {
+    MoveTo(f64, f64),
+    LineTo(f64, f64),
+    CubicTo(f64, f64, f64, f64, f64, f64),
+    Close,
+}
+
+/// Vector outline made of lines and cubic Bézier curves
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Path {
+    pub segments: Vec<Segment>,
+}
+
+impl Path {
+    /// Closed polygon through the given points
+    #[must_use]
+    pub fn polygon(points: &[(f64, f64)]) -> Self {
+        let mut segments: Vec<Segment> = points
+            .iter()
+            .enumerate()
+            .map(|(i, &(x, y))| {
+                if i == 0 {
+                    Segment::MoveTo(x, y)
+                } else {
+                    Segment::LineTo(x, y)
+                }
+            })
+            .collect();
+        segments.push(Segment::Close);
+        Self { segments }
+    }
+
+    /// Axis-aligned rectangle
+    #[must_use]
+    pub fn rect(x: f64, y: f64, w: f64, h: f64) -> Self {
+        Self::polygon(&[(x, y), (x + w, y), (x + w, y + h), (x, y + h)])
+    }
+
+    /// Rectangle with individual corner radii (top-left, top-right,
+    /// bottom-right, bottom-left)
+    #[allow(clippy::many_single_char_names)]
+    #[must_use]
+    pub fn rounded_rect(x: f64, y: f64, w: f64, h: f64, radii: [f64; 4]) -> Self {
+        if radii.iter().all(|&r| r <= 0.0) {
+            return Self::rect(x, y, w, h);
+        }
+        let [tl, tr, br, bl] = radii;
+        let k = |r: f64| r * (1.0 - KAPPA);
+        let mut path = Self::default();
+        path.segments.push(Segment::MoveTo(x + tl, y));
+        path.segments.push(Segment::LineTo(x + w - tr, y));
+        if tr > 0.0 {
+            path.segments.push(Segment::CubicTo(
+                x + w - k(tr),
+                y,
+                x + w,
+                y + k(tr),
+                x + w,
+                y + tr,
+            ));
+        }
+        path.segments.push(Segment::LineTo(x + w, y + h - br));
+        if br > 0.0 {
+            path.segments.push(Segment::CubicTo(
+                x + w,
+                y + h - k(br),
+                x + w - k(br),
+                y + h,
+                x + w - br,
+                y + h,
+            ));
+        }
+        path.segments.push(Segment::LineTo(x + bl, y + h));
+        if bl > 0.0 {
+            path.segments.push(Segment::CubicTo(
+                x + k(bl),
+                y + h,
+                x,
+                y + h - k(bl),
+                x,
+                y + h - bl,
+            ));
+        }
+        path.segments.push(Segment::LineTo(x, y + tl));
+        if tl > 0.0 {
+            path.segments
+                .push(Segment::CubicTo(x, y + k(tl), x + k(tl), y, x + tl, y));
+        }
+        path.segments.push(Segment::Close);
+        path
+    }
+
+    /// Append all segments of `other` to this path
+    pub fn extend(&mut self, other: &Self) {
+        self.segments.extend_from_slice(&other.segments);
+    }
+
+    /// Serialize as SVG path data (the `d` attribute)
+    #[must_use]
+    pub fn to_svg_data(&self) -> String {
+        let mut d = String::new();
+        for segment in &self.segments {
+            match *segment {
+                Segment::MoveTo(x, y) => {
+                    let _ = write!(d, "M{} {}", fmt_num(x), fmt_num(y));
+                }
+                Segment::LineTo(x, y) => {
+                    let _ = write!(d, "L{} {}", fmt_num(x), fmt_num(y));
+                }
+                Segment::CubicTo(x1, y1, x2, y2, x, y) => {
+                    let _ = write!(
+                        d,
+                        "C{} {} {} {} {} {}",
+                        fmt_num(x1),
+                        fmt_num(y1),
+                        fmt_num(x2),
+                        fmt_num(y2),
+                        fmt_num(x),
+                        fmt_num(y)
+                    );
+                }
+                Segment::Close => d.push('Z'),
+            }
+        }
+        d
+    }
+}
+
+/// Format a coordinate with at most three decimals and no trailing zeros
+#[must_use]
+pub fn fmt_num(value: f64) -> String {
+    let s = format!("{value:.3}");
+    let s = s.trim_end_matches('0').trim_end_matches('.');
+    if s == "-0" {
+        "0".to_string()
+    } else {
+        s.to_string()
+    }
+}
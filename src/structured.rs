@@ -0,0 +1,355 @@
+//! Structured Append: one payload split across up to 16 linked QR codes
+//!
+//! Each symbol starts with a header giving its position in the set, the
+//! size of the set and a parity byte of the whole payload, so a reader can
+//! put the parts back together in order. rxing reports the position and size
+//! of decoded codes but not the parity, so reassembly checks the set through
+//! those alone.
+
+#![allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+
+use image::imageops::overlay;
+use image::{ImageBuffer, ImageFormat};
+use qrcode::bits::Bits as QrBits;
+use qrcode::canvas::Canvas;
+use qrcode::{ec, EcLevel, Version};
+
+use crate::decoder::DecodedQr;
+use crate::error::{QrError, Result};
+use crate::renderer::{create_qr_image, encode_buffer, render_to_svg, RenderConfig};
+use crate::rmqr::Bits;
+use crate::shapes::{fmt_num, Eye, ModuleGrid};
+
+/// Most symbols a Structured Append set can have
+pub const MAX_SYMBOLS: usize = 16;
+
+/// Bits ahead of the data: the Structured Append mode, index, total and
+/// parity, then the byte mode indicator (its character count comes on top)
+const HEADER_BITS: usize = 4 + 4 + 4 + 8 + 4;
+
+/// Header linking one symbol to the rest of its set
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StructuredAppend {
+    /// Position in the set, from 0
+    pub index: u8,
+    /// Number of symbols in the set
+    pub total: u8,
+    /// XOR of every byte of the whole payload
+    pub parity: u8,
+}
+
+impl StructuredAppend {
+    /// Position of the symbol in its set
+    #[must_use]
+    pub const fn position(self) -> SetPosition {
+        SetPosition {
+            index: self.index,
+            total: self.total,
+        }
+    }
+}
+
+/// Where a decoded code sits in its Structured Append set
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SetPosition {
+    /// Position in the set, from 0
+    pub index: u8,
+    /// Number of symbols in the set
+    pub total: u8,
+}
+
+/// Parity byte of a payload: the XOR of all its bytes
+#[must_use]
+pub fn parity(data: &[u8]) -> u8 {
+    data.iter().fold(0, |parity, byte| parity ^ byte)
+}
+
+/// Split `data` into the fewest parts that each fit a version 40 symbol.
+///
+/// Parts break between characters, so each one also reads on its own.
+pub fn split(data: &str, ec_level: EcLevel) -> Result<Vec<(StructuredAppend, &str)>> {
+    let parity = parity(data.as_bytes());
+    for total in 1..=MAX_SYMBOLS {
+        let target = (data.len() + total - 1) / total;
+        let mut parts = Vec::new();
+        let mut rest = data;
+        while !rest.is_empty() {
+            let mut end = target.min(rest.len());
+            while !rest.is_char_boundary(end) {
+                end += 1;
+            }
+            let (part, tail) = rest.split_at(end);
+            parts.push(part);
+            rest = tail;
+        }
+        if parts.iter().all(|part| fits(part.len(), 40, ec_level)) {
+            let total = parts.len().max(1) as u8;
+            return Ok(parts
+                .into_iter()
+                .enumerate()
+                .map(|(index, part)| {
+                    let header = StructuredAppend {
+                        index: index as u8,
+                        total,
+                        parity,
+                    };
+                    (header, part)
+                })
+                .collect());
+        }
+    }
+    Err(QrError::QrGeneration(format!(
+        "data too long for {MAX_SYMBOLS} linked QR codes at error correction level {ec_level:?}; \
+         lower the error correction level"
+    )))
+}
+
+/// Encode one part of a set as the smallest QR code that holds it, in byte
+/// mode after the Structured Append header
+pub fn encode(data: &str, header: StructuredAppend, ec_level: EcLevel) -> Result<ModuleGrid> {
+    let bytes = data.as_bytes();
+    let number = (1..=40)
+        .find(|&number| fits(bytes.len(), number, ec_level))
+        .ok_or_else(|| {
+            QrError::QrGeneration(format!(
+                "part {} of {} is too long for a QR code",
+                header.index + 1,
+                header.total
+            ))
+        })?;
+    let version = Version::Normal(number);
+
+    let mut bits = Bits::default();
+    bits.push(0b0011, 4);
+    bits.push(u32::from(header.index), 4);
+    bits.push(u32::from(header.total - 1), 4);
+    bits.push(u32::from(header.parity), 8);
+    bits.push(0b0100, 4);
+    bits.push(bytes.len() as u32, count_bits(number));
+    for &byte in bytes {
+        bits.push(u32::from(byte), 8);
+    }
+
+    // Terminator where it fits, then zero bits up to a whole codeword
+    let capacity = capacity_bits(number, ec_level);
+    bits.push(0, (capacity - bits.len()).min(4));
+    bits.push(0, (8 - bits.len() % 8) % 8);
+    let mut codewords = bits.to_bytes();
+    let missing = capacity / 8 - codewords.len();
+    codewords.extend([0xEC, 0x11].iter().copied().cycle().take(missing));
+
+    let (data_codewords, ec_codewords) = ec::construct_codewords(&codewords, version, ec_level)?;
+    let mut canvas = Canvas::new(version, ec_level);
+    canvas.draw_all_functional_patterns();
+    canvas.draw_data(&data_codewords, &ec_codewords);
+    let dark = canvas
+        .apply_best_mask()
+        .into_colors()
+        .into_iter()
+        .map(|color| color == qrcode::Color::Dark)
+        .collect();
+    let width = 17 + 4 * number as usize;
+    Ok(ModuleGrid::from_modules(width, width, dark, &Eye::ALL))
+}
+
+/// Put decoded parts back together in order, checking that none is missing
+/// and that they all come from the same set. Parts read more than once count
+/// once.
+pub fn reassemble(parts: &[DecodedQr]) -> Result<String> {
+    let mut linked: Vec<(SetPosition, &str)> = parts
+        .iter()
+        .filter_map(|part| Some((part.structured_append?, part.content.as_str())))
+        .collect();
+    let Some(&(first, _)) = linked.first() else {
+        return Err(QrError::DecodeError(
+            "no Structured Append codes found".to_string(),
+        ));
+    };
+    linked.sort_by_key(|(position, _)| position.index);
+    linked.dedup();
+    let mixed = linked
+        .iter()
+        .any(|(position, _)| position.total != first.total)
+        || linked
+            .windows(2)
+            .any(|pair| pair[0].0.index == pair[1].0.index);
+    if mixed {
+        return Err(QrError::DecodeError(
+            "the codes come from more than one Structured Append set".to_string(),
+        ));
+    }
+
+    let missing: Vec<String> = (0..first.total)
+        .filter(|&index| !linked.iter().any(|(position, _)| position.index == index))
+        .map(|index| (index + 1).to_string())
+        .collect();
+    if !missing.is_empty() {
+        return Err(QrError::DecodeError(format!(
+            "missing part {} of {}",
+            missing.join(", "),
+            first.total
+        )));
+    }
+    Ok(linked.iter().map(|(_, content)| *content).collect())
+}
+
+/// Render settings for one part of a set
+#[must_use]
+pub fn part_config(config: &RenderConfig, header: StructuredAppend) -> RenderConfig {
+    RenderConfig {
+        structured_append: Some(header),
+        ..config.clone()
+    }
+}
+
+/// File name of one part of a set: `codes.png` becomes `codes-1.png`, ...
+#[must_use]
+pub fn part_path(path: &Path, header: StructuredAppend) -> PathBuf {
+    let stem = path
+        .file_stem()
+        .map_or_else(|| "qrcode".into(), |stem| stem.to_string_lossy());
+    let mut name = format!("{stem}-{}", header.index + 1);
+    if let Some(extension) = path.extension() {
+        name.push('.');
+        name.push_str(&extension.to_string_lossy());
+    }
+    path.with_file_name(name)
+}
+
+/// Lay every part of a set out on one raster image, left to right and top
+/// to bottom, each centred in a cell the size of the largest
+pub fn render_sheet_image(
+    parts: &[(StructuredAppend, &str)],
+    format: ImageFormat,
+    config: &RenderConfig,
+) -> Result<Vec<u8>> {
+    let images = parts
+        .iter()
+        .map(|&(header, part)| create_qr_image(part, &part_config(config, header)))
+        .collect::<Result<Vec<_>>>()?;
+    let cell_width = images.iter().map(ImageBuffer::width).max().unwrap_or(0);
+    let cell_height = images.iter().map(ImageBuffer::height).max().unwrap_or(0);
+    let (columns, rows) = sheet_grid(images.len());
+
+    let mut sheet = ImageBuffer::from_pixel(
+        cell_width * columns,
+        cell_height * rows,
+        config.bg_color.to_rgba(),
+    );
+    for (i, img) in images.iter().enumerate() {
+        let (column, row) = (i as u32 % columns, i as u32 / columns);
+        let x = column * cell_width + (cell_width - img.width()) / 2;
+        let y = row * cell_height + (cell_height - img.height()) / 2;
+        overlay(&mut sheet, img, i64::from(x), i64::from(y));
+    }
+    encode_buffer(sheet, format, config)
+}
+
+/// Lay every part of a set out in one SVG document, nesting each part's
+/// own SVG in its cell
+pub fn render_sheet_svg(
+    parts: &[(StructuredAppend, &str)],
+    config: &RenderConfig,
+) -> Result<String> {
+    let mut cells = Vec::new();
+    for &(header, part) in parts {
+        // Ids must stay unique once the parts share a document
+        let part_config = RenderConfig {
+            svg_id_prefix: format!("{}part{}-", config.svg_id_prefix, header.index + 1),
+            ..part_config(config, header)
+        };
+        let svg = render_to_svg(part, &part_config)?;
+        let (width, height, body) = split_svg(&svg);
+        cells.push((width, height, body.to_string()));
+    }
+    let cell_width = cells.iter().map(|cell| cell.0).fold(0.0, f64::max);
+    let cell_height = cells.iter().map(|cell| cell.1).fold(0.0, f64::max);
+    let (columns, rows) = sheet_grid(cells.len());
+    let sheet_width = cell_width * f64::from(columns);
+    let sheet_height = cell_height * f64::from(rows);
+
+    let mut svg = String::from(r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+    let _ = write!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 {w} {h}" width="{w}" height="{h}">"#,
+        w = fmt_num(sheet_width),
+        h = fmt_num(sheet_height),
+    );
+    let _ = write!(
+        svg,
+        r#"<rect width="100%" height="100%" {}/>"#,
+        config.bg_color.to_svg_fill()
+    );
+    for (i, (width, height, body)) in cells.iter().enumerate() {
+        let (column, row) = (i as u32 % columns, i as u32 / columns);
+        let x = f64::from(column).mul_add(cell_width, (cell_width - width) / 2.0);
+        let y = f64::from(row).mul_add(cell_height, (cell_height - height) / 2.0);
+        let _ = write!(
+            svg,
+            r#"<svg x="{}" y="{}" width="{w}" height="{h}" viewBox="0 0 {w} {h}">{body}</svg>"#,
+            fmt_num(x),
+            fmt_num(y),
+            w = fmt_num(*width),
+            h = fmt_num(*height),
+        );
+    }
+    svg.push_str("</svg>");
+    Ok(svg)
+}
+
+/// Columns and rows of a sheet of `count` codes: as square as possible
+pub(crate) fn sheet_grid(count: usize) -> (u32, u32) {
+    let columns = (1..=count.max(1))
+        .find(|columns| columns * columns >= count)
+        .unwrap_or(1);
+    let rows = (count + columns - 1) / columns;
+    (columns as u32, rows as u32)
+}
+
+/// Size from the `viewBox` of an SVG document, and the markup inside its root
+fn split_svg(svg: &str) -> (f64, f64, &str) {
+    let root = svg.find("<svg").unwrap_or(0);
+    let open_end = svg[root..]
+        .find('>')
+        .map_or(svg.len(), |end| root + end + 1);
+    let close = svg.rfind("</svg>").unwrap_or(svg.len());
+    let view_box: Vec<f64> = svg[root..open_end]
+        .split("viewBox=\"")
+        .nth(1)
+        .and_then(|rest| rest.split('"').next())
+        .map(|view_box| {
+            view_box
+                .split_whitespace()
+                .filter_map(|n| n.parse().ok())
+                .collect()
+        })
+        .unwrap_or_default();
+    match view_box[..] {
+        [_, _, width, height] => (width, height, &svg[open_end..close]),
+        _ => (0.0, 0.0, &svg[open_end..close]),
+    }
+}
+
+/// Whether `len` bytes fit a version `number` symbol after the header
+fn fits(len: usize, number: i16, ec_level: EcLevel) -> bool {
+    HEADER_BITS + count_bits(number) + 8 * len <= capacity_bits(number, ec_level)
+}
+
+/// Width of the byte mode character count
+const fn count_bits(number: i16) -> usize {
+    if number < 10 {
+        8
+    } else {
+        16
+    }
+}
+
+/// Data bits a version `number` symbol holds at `ec_level`
+fn capacity_bits(number: i16, ec_level: EcLevel) -> usize {
+    QrBits::new(Version::Normal(number))
+        .max_len(ec_level)
+        .expect("QR versions 1-40 have every error correction level")
+}
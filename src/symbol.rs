@@ -0,0 +1,193 @@
+//! Symbol types: QR, Micro QR and rectangular Micro QR (rMQR), plus the
+//! other 2D and linear barcodes rxing can write
+
+use clap::ValueEnum;
+use qrcode::{EcLevel, QrCode, Version};
+use rxing::BarcodeFormat;
+
+use crate::barcode;
+use crate::error::{QrError, Result};
+use crate::rmqr;
+use crate::shapes::ModuleGrid;
+
+/// Kind of 2D symbol to generate
+#[derive(Clone, Copy, ValueEnum, Debug, Default, PartialEq, Eq)]
+pub enum SymbolType {
+    /// Standard QR code (versions 1-40)
+    #[default]
+    Qr,
+    /// Micro QR (M1-M4): one finder pattern, up to 35 digits
+    Micro,
+    /// Rectangular Micro QR (R7x43-R17x139) for narrow labels
+    Rmqr,
+    /// Data Matrix (ECC 200), common on parts and packaging
+    DataMatrix,
+    /// Aztec code, read without a quiet zone
+    Aztec,
+    /// PDF417 stacked barcode, as on ID cards and boarding passes
+    Pdf417,
+    /// Code 128 linear barcode (ASCII text)
+    Code128,
+    /// EAN-13 retail barcode (12 digits plus check digit)
+    Ean13,
+    /// UPC-A retail barcode (11 digits plus check digit)
+    UpcA,
+    /// Interleaved 2 of 5 (an even number of digits), as on cartons
+    Itf,
+}
+
+impl SymbolType {
+    /// Quiet zone the symbology calls for, in modules. Data Matrix and
+    /// Aztec need less, but get 2 so they stand out on dark terminals; linear
+    /// barcodes all get EAN-13's 11, the widest of their margins.
+    #[must_use]
+    pub const fn standard_quiet_zone(self) -> u32 {
+        match self {
+            Self::Qr => 4,
+            Self::Micro | Self::Rmqr | Self::DataMatrix | Self::Aztec | Self::Pdf417 => 2,
+            Self::Code128 | Self::Ean13 | Self::UpcA | Self::Itf => 11,
+        }
+    }
+
+    /// Quiet zone used when `--quiet-zone` isn't given: 2 modules, or the
+    /// standard one for linear barcodes, which don't scan with less
+    #[must_use]
+    pub const fn default_quiet_zone(self) -> u32 {
+        if self.is_linear() {
+            self.standard_quiet_zone()
+        } else {
+            2
+        }
+    }
+
+    /// Whether this is a QR code or one of its Micro and rectangular variants
+    #[must_use]
+    pub const fn is_qr_family(self) -> bool {
+        matches!(self, Self::Qr | Self::Micro | Self::Rmqr)
+    }
+
+    /// Whether this is a linear (1D) barcode
+    #[must_use]
+    pub const fn is_linear(self) -> bool {
+        matches!(self, Self::Code128 | Self::Ean13 | Self::UpcA | Self::Itf)
+    }
+
+    /// Display name, as in error messages
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Qr => "QR",
+            Self::Micro => "Micro QR",
+            Self::Rmqr => "rMQR",
+            Self::DataMatrix => "Data Matrix",
+            Self::Aztec => "Aztec",
+            Self::Pdf417 => "PDF417",
+            Self::Code128 => "Code 128",
+            Self::Ean13 => "EAN-13",
+            Self::UpcA => "UPC-A",
+            Self::Itf => "ITF",
+        }
+    }
+
+    /// Version name of an encoded symbol, such as `7`, `M2` or `R7x43`; other
+    /// barcodes give their size in modules
+    #[must_use]
+    pub fn version_name(self, grid: &ModuleGrid) -> String {
+        match self {
+            Self::Qr => ((grid.width() - 17) / 4).to_string(),
+            Self::Micro => format!("M{}", (grid.width() - 9) / 2),
+            Self::Rmqr => format!("R{}x{}", grid.height(), grid.width()),
+            _ if self.is_linear() => format!("{} modules wide", grid.width()),
+            _ => format!("{}x{}", grid.width(), grid.height()),
+        }
+    }
+
+    /// Text a scanner reports for `data`: retail barcodes add their check
+    /// digit, and EAN-13 codes starting with 0 read as UPC-A
+    #[must_use]
+    pub fn scanned_content(self, data: &str) -> String {
+        let with_check_digit = |length: usize| {
+            if data.len() + 1 == length && data.bytes().all(|b| b.is_ascii_digit()) {
+                format!("{data}{}", check_digit(data))
+            } else {
+                data.to_string()
+            }
+        };
+        match self {
+            Self::Ean13 => {
+                let full = with_check_digit(13);
+                match full.strip_prefix('0') {
+                    Some(upc) if full.len() == 13 => upc.to_string(),
+                    _ => full,
+                }
+            }
+            Self::UpcA => with_check_digit(12),
+            _ => data.to_string(),
+        }
+    }
+
+    /// rxing format of the barcodes it writes
+    const fn barcode_format(self) -> Option<BarcodeFormat> {
+        match self {
+            Self::Qr | Self::Micro | Self::Rmqr => None,
+            Self::DataMatrix => Some(BarcodeFormat::DATA_MATRIX),
+            Self::Aztec => Some(BarcodeFormat::AZTEC),
+            Self::Pdf417 => Some(BarcodeFormat::PDF_417),
+            Self::Code128 => Some(BarcodeFormat::CODE_128),
+            Self::Ean13 => Some(BarcodeFormat::EAN_13),
+            Self::UpcA => Some(BarcodeFormat::UPC_A),
+            Self::Itf => Some(BarcodeFormat::ITF),
+        }
+    }
+}
+
+/// GS1 check digit: weights 3 and 1 alternate from the rightmost digit
+fn check_digit(digits: &str) -> u32 {
+    let sum: u32 = digits
+        .bytes()
+        .rev()
+        .enumerate()
+        .map(|(i, b)| u32::from(b - b'0') * if i % 2 == 0 { 3 } else { 1 })
+        .sum();
+    (10 - sum % 10) % 10
+}
+
+/// Encode `data` as the smallest symbol of the given type that holds it.
+///
+/// Error correction levels map onto Aztec and PDF417's own settings; Data
+/// Matrix and linear barcodes have fixed error correction or none.
+pub fn encode(data: &str, symbol: SymbolType, ec_level: EcLevel) -> Result<ModuleGrid> {
+    match symbol {
+        SymbolType::Qr => {
+            let code = QrCode::with_error_correction_level(data.as_bytes(), ec_level)?;
+            Ok(ModuleGrid::new(&code))
+        }
+        SymbolType::Micro => encode_micro(data, ec_level),
+        SymbolType::Rmqr => rmqr::encode(data, ec_level),
+        other => {
+            let format = other.barcode_format().expect("rxing barcode format");
+            barcode::encode(data, format, other.name(), ec_level)
+        }
+    }
+}
+
+/// Micro QR: M1 only has error detection (level L), M2 and M3 add level M,
+/// and M4 level Q
+fn encode_micro(data: &str, ec_level: EcLevel) -> Result<ModuleGrid> {
+    if ec_level == EcLevel::H {
+        return Err(QrError::QrGeneration(
+            "Micro QR supports error correction levels L, M and Q, not H".to_string(),
+        ));
+    }
+    (1..=4)
+        .find_map(|version| {
+            QrCode::with_version(data.as_bytes(), Version::Micro(version), ec_level).ok()
+        })
+        .map(|code| ModuleGrid::new(&code))
+        .ok_or_else(|| {
+            QrError::QrGeneration(format!(
+                "data too long for a Micro QR code at error correction level {ec_level:?} \
+                 (M4 holds at most 35 digits or 15 bytes at level L); use --symbol rmqr or qr"
+            ))
+        })
+}
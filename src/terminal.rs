@@ -0,0 +1,377 @@
+//! Inline images in the terminal through the Sixel, Kitty and iTerm2
+//! graphics protocols
+//!
+//! These show the full raster, colors, gradients and logos included, where
+//! Unicode half-blocks can only draw two-tone modules.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt::Write as _;
+use std::io::IsTerminal;
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use clap::ValueEnum;
+use image::RgbaImage;
+
+use crate::renderer::{Color, RenderConfig};
+use crate::shapes::ModuleGrid;
+
+/// Base64 bytes per Kitty graphics escape sequence, the protocol's limit
+const KITTY_CHUNK: usize = 4096;
+
+/// Most colors a Sixel palette is guaranteed to hold
+const SIXEL_COLORS: usize = 256;
+
+/// How terminal output is drawn
+#[derive(Clone, Copy, ValueEnum, Debug, Default, PartialEq, Eq)]
+pub enum TerminalGraphics {
+    /// Text in the --terminal-glyphs set (works everywhere)
+    #[default]
+    Unicode,
+    /// Sixel bitmap graphics (mlterm, foot, xterm in VT340 mode)
+    Sixel,
+    /// Kitty graphics protocol (kitty, Ghostty, Konsole)
+    Kitty,
+    /// iTerm2 inline images (iTerm2, Tabby)
+    Iterm2,
+}
+
+impl TerminalGraphics {
+    /// Protocol of the terminal cqr is running in, or Unicode when output
+    /// isn't a terminal
+    #[must_use]
+    pub fn detect() -> Self {
+        if std::io::stdout().is_terminal() {
+            Self::detect_from(|name| std::env::var(name).ok())
+        } else {
+            Self::Unicode
+        }
+    }
+
+    /// Protocol for a terminal described by the environment variables
+    /// `env` looks up. Inside tmux or screen, which don't pass images
+    /// through, this is Unicode.
+    pub fn detect_from(env: impl Fn(&str) -> Option<String>) -> Self {
+        let term = env("TERM").unwrap_or_default();
+        let program = env("TERM_PROGRAM").unwrap_or_default();
+        if env("TMUX").is_some() || term.starts_with("screen") || term.starts_with("tmux") {
+            return Self::Unicode;
+        }
+        if env("KITTY_WINDOW_ID").is_some()
+            || term == "xterm-kitty"
+            || term == "xterm-ghostty"
+            || program == "ghostty"
+            || env("KONSOLE_VERSION").is_some()
+        {
+            Self::Kitty
+        } else if program == "iTerm.app"
+            || program == "WezTerm"
+            || program == "Tabby"
+            || env("LC_TERMINAL").as_deref() == Some("iTerm2")
+        {
+            Self::Iterm2
+        } else if term.contains("sixel")
+            || term.starts_with("mlterm")
+            || term.starts_with("foot")
+            || term.starts_with("yaft")
+            || program == "contour"
+        {
+            Self::Sixel
+        } else {
+            Self::Unicode
+        }
+    }
+}
+
+/// Kitty graphics escape sequences that display `png` at the cursor. The
+/// data is sent in chunks, and `q=2` keeps the terminal from answering.
+#[must_use]
+pub fn kitty(png: &[u8]) -> String {
+    let encoded = STANDARD.encode(png);
+    let chunks: Vec<&[u8]> = encoded.as_bytes().chunks(KITTY_CHUNK).collect();
+    let mut out = String::new();
+    for (i, chunk) in chunks.iter().enumerate() {
+        let more = u8::from(i + 1 < chunks.len());
+        let keys = if i == 0 { "a=T,f=100,q=2," } else { "" };
+        let chunk = std::str::from_utf8(chunk).expect("base64 is ASCII");
+        let _ = write!(out, "\x1b_G{keys}m={more};{chunk}\x1b\\");
+    }
+    out
+}
+
+/// iTerm2 inline image escape sequence that displays `png` at the cursor,
+/// one image pixel per screen pixel
+#[must_use]
+pub fn iterm2(png: &[u8], width: u32, height: u32) -> String {
+    format!(
+        "\x1b]1337;File=inline=1;size={};width={width}px;height={height}px:{}\x07",
+        png.len(),
+        STANDARD.encode(png)
+    )
+}
+
+/// Sixel escape sequence for `img`. Translucent pixels are blended over
+/// white, as in JPEG output; images with more than 256 colors (gradients,
+/// logos) are reduced to a 6x6x6 color cube.
+#[must_use]
+pub fn sixel(img: &RgbaImage) -> String {
+    let white = Color::rgb(255, 255, 255);
+    let mut pixels: Vec<[u8; 3]> = img
+        .pixels()
+        .map(|pixel| {
+            let [r, g, b, a] = pixel.0;
+            let color = Color::rgba(r, g, b, a).over(white);
+            [color.r, color.g, color.b]
+        })
+        .collect();
+
+    let mut palette = distinct(&pixels);
+    if palette.len() > SIXEL_COLORS {
+        let level = |v: u8| u8::try_from((u16::from(v) * 5 + 127) / 255 * 51).unwrap_or(u8::MAX);
+        for pixel in &mut pixels {
+            *pixel = pixel.map(level);
+        }
+        palette = distinct(&pixels);
+    }
+    let index: HashMap<[u8; 3], usize> = palette.iter().enumerate().map(|(i, &c)| (c, i)).collect();
+    let indexed: Vec<usize> = pixels.iter().map(|pixel| index[pixel]).collect();
+
+    let (width, height) = (img.width() as usize, img.height() as usize);
+    let mut out = format!("\x1bPq\"1;1;{width};{height}");
+    for (i, color) in palette.iter().enumerate() {
+        let [r, g, b] = color.map(|v| (u32::from(v) * 100 + 127) / 255);
+        let _ = write!(out, "#{i};2;{r};{g};{b}");
+    }
+
+    // Each band of six pixel rows is drawn once per color it uses
+    for top in (0..height).step_by(6) {
+        let mut bands: BTreeMap<usize, Vec<u8>> = BTreeMap::new();
+        for y in top..(top + 6).min(height) {
+            for (x, &i) in indexed[y * width..(y + 1) * width].iter().enumerate() {
+                bands.entry(i).or_insert_with(|| vec![0; width])[x] |= 1 << (y - top);
+            }
+        }
+        let lines: Vec<String> = bands
+            .iter()
+            .map(|(i, bits)| format!("#{i}{}", sixel_run_length(bits)))
+            .collect();
+        out.push_str(&lines.join("$"));
+        out.push('-');
+    }
+    out.push_str("\x1b\\");
+    out
+}
+
+/// Colors of `pixels` in order of first appearance
+fn distinct(pixels: &[[u8; 3]]) -> Vec<[u8; 3]> {
+    let mut seen = HashSet::new();
+    pixels
+        .iter()
+        .copied()
+        .filter(|&pixel| seen.insert(pixel))
+        .collect()
+}
+
+/// Sixel characters for one color of a band, with runs of more than three
+/// repeats compressed and the empty tail left off
+fn sixel_run_length(bits: &[u8]) -> String {
+    let used = bits
+        .iter()
+        .rposition(|&b| b != 0)
+        .map_or(0, |last| last + 1);
+    let mut out = String::new();
+    let mut x = 0;
+    while x < used {
+        let run = bits[x..used].iter().take_while(|&&b| b == bits[x]).count();
+        let c = char::from(63 + bits[x]);
+        if run > 3 {
+            let _ = write!(out, "!{run}{c}");
+        } else {
+            out.extend(std::iter::repeat(c).take(run));
+        }
+        x += run;
+    }
+    out
+}
+
+/// Characters Unicode terminal output is drawn with
+#[derive(Clone, Copy, ValueEnum, Debug, Default, PartialEq, Eq)]
+pub enum TerminalGlyphs {
+    /// Half blocks, two module rows per line
+    #[default]
+    HalfBlock,
+    /// Two full blocks per module, one module row per line
+    FullBlock,
+    /// Braille dots, 2x4 modules per character (most compact)
+    Braille,
+    /// "##" and spaces, for logs and email
+    Ascii,
+}
+
+/// Colors of Unicode terminal output
+#[derive(Clone, Copy, ValueEnum, Debug, Default, PartialEq, Eq)]
+pub enum TerminalColor {
+    /// The terminal's own colors
+    #[default]
+    None,
+    /// --fg-color and --bg-color, rounded to the 256-color palette
+    Ansi256,
+    /// --fg-color and --bg-color as 24-bit color
+    Truecolor,
+}
+
+/// Which modules Unicode terminal output draws with ink
+#[derive(Clone, Copy, ValueEnum, Debug, Default, PartialEq, Eq)]
+pub enum TerminalPolarity {
+    /// Inverted for uncolored blocks and Braille (they suit dark terminals),
+    /// normal for ASCII and colored output
+    #[default]
+    Auto,
+    /// Dark modules are ink, for light terminals
+    Normal,
+    /// Light modules are ink, for dark terminals
+    Inverted,
+}
+
+/// Levels of each channel in the 6x6x6 cube of the 256-color palette
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+/// Draw `grid` as text with the glyphs, colors and polarity of `config`,
+/// surrounded by `quiet` light modules
+#[must_use]
+pub fn render_text(grid: &ModuleGrid, quiet: usize, config: &RenderConfig) -> String {
+    let inverted = match config.terminal_polarity {
+        TerminalPolarity::Normal => false,
+        TerminalPolarity::Inverted => true,
+        TerminalPolarity::Auto => {
+            config.terminal_color == TerminalColor::None
+                && config.terminal_glyphs != TerminalGlyphs::Ascii
+        }
+    };
+    let (columns, rows) = (grid.width() + 2 * quiet, grid.height() + 2 * quiet);
+    // Whether a module is drawn; past the last row nothing is
+    let ink = |x: usize, y: usize| {
+        let inside = |v: usize, len: usize| v >= quiet && v < len + quiet;
+        let dark = inside(x, grid.width())
+            && inside(y, grid.height())
+            && grid.is_dark(x - quiet, y - quiet);
+        y < rows && x < columns && dark != inverted
+    };
+
+    let lines: Vec<String> = match config.terminal_glyphs {
+        TerminalGlyphs::HalfBlock => {
+            const BLOCKS: [char; 4] = [' ', '\u{2584}', '\u{2580}', '\u{2588}'];
+            (0..rows)
+                .step_by(2)
+                .map(|y| {
+                    (0..columns)
+                        .map(|x| BLOCKS[usize::from(ink(x, y)) * 2 + usize::from(ink(x, y + 1))])
+                        .collect()
+                })
+                .collect()
+        }
+        TerminalGlyphs::FullBlock | TerminalGlyphs::Ascii => {
+            let mark = if config.terminal_glyphs == TerminalGlyphs::Ascii {
+                "##"
+            } else {
+                "\u{2588}\u{2588}"
+            };
+            (0..rows)
+                .map(|y| {
+                    (0..columns)
+                        .map(|x| if ink(x, y) { mark } else { "  " })
+                        .collect()
+                })
+                .collect()
+        }
+        TerminalGlyphs::Braille => {
+            // Dot bits of the left and right columns, top to bottom
+            const DOTS: [[u32; 4]; 2] = [[0x01, 0x02, 0x04, 0x40], [0x08, 0x10, 0x20, 0x80]];
+            (0..rows)
+                .step_by(4)
+                .map(|y| {
+                    (0..columns)
+                        .step_by(2)
+                        .map(|x| {
+                            let mut bits = 0;
+                            for (dx, column) in DOTS.iter().enumerate() {
+                                for (dy, bit) in column.iter().enumerate() {
+                                    if ink(x + dx, y + dy) {
+                                        bits |= bit;
+                                    }
+                                }
+                            }
+                            char::from_u32(0x2800 + bits).expect("Braille pattern")
+                        })
+                        .collect()
+                })
+                .collect()
+        }
+    };
+
+    let (ink_color, paper_color) = if inverted {
+        (config.bg_color, config.fg_color)
+    } else {
+        (config.fg_color, config.bg_color)
+    };
+    let style = match config.terminal_color {
+        TerminalColor::None => return lines.join("\n"),
+        TerminalColor::Ansi256 | TerminalColor::Truecolor => format!(
+            "\x1b[{};{}m",
+            sgr_color(ink_color, config.terminal_color, 38),
+            sgr_color(paper_color, config.terminal_color, 48)
+        ),
+    };
+    lines
+        .iter()
+        .map(|line| format!("{style}{line}\x1b[0m"))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// SGR parameters that set a foreground (`base` 38) or background (48)
+/// color; fully transparent colors leave the terminal's own
+fn sgr_color(color: Color, mode: TerminalColor, base: u8) -> String {
+    if color.a == 0 {
+        return (base + 1).to_string();
+    }
+    match mode {
+        TerminalColor::Truecolor => format!("{base};2;{};{};{}", color.r, color.g, color.b),
+        _ => format!("{base};5;{}", ansi256(color)),
+    }
+}
+
+/// Nearest entry of the 256-color palette: a 6x6x6 cube color or a gray
+#[must_use]
+pub fn ansi256(color: Color) -> u8 {
+    let nearest_level = |v: u8| {
+        (0..6u8)
+            .min_by_key(|&i| CUBE_LEVELS[usize::from(i)].abs_diff(v))
+            .unwrap_or_default()
+    };
+    let (r, g, b) = (
+        nearest_level(color.r),
+        nearest_level(color.g),
+        nearest_level(color.b),
+    );
+    let cube = [r, g, b].map(|i| CUBE_LEVELS[usize::from(i)]);
+
+    // Grays 232-255 run from 8 to 238 in steps of 10
+    let mean = (u16::from(color.r) + u16::from(color.g) + u16::from(color.b)) / 3;
+    let gray_step = u8::try_from(mean.saturating_sub(3) / 10)
+        .unwrap_or(u8::MAX)
+        .min(23);
+    let gray = 8 + 10 * gray_step;
+
+    let distance = |target: [u8; 3]| {
+        [color.r, color.g, color.b]
+            .iter()
+            .zip(target)
+            .map(|(&v, t)| u32::from(v.abs_diff(t)).pow(2))
+            .sum::<u32>()
+    };
+    if distance([gray; 3]) < distance(cube) {
+        232 + gray_step
+    } else {
+        16 + 36 * r + 6 * g + b
+    }
+}
@@ -0,0 +1,128 @@
+//! Scannability check: decode rendered output and compare it with the input
+
+use std::path::Path;
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use image::DynamicImage;
+
+use crate::cli::OutputFormat;
+use crate::decoder::{decode, decode_all, decode_image, decode_svg, DecodedQr};
+use crate::error::{QrError, Result};
+use crate::matrix::{preview, read_matrix};
+use crate::renderer::RenderConfig;
+use crate::structured;
+
+/// Fail for formats `--verify` can't read back: PDF and EPS would need a
+/// page renderer, and terminal output is only meant for the screen
+pub fn check_verifiable(format: OutputFormat) -> Result<()> {
+    match format {
+        OutputFormat::Pdf | OutputFormat::Eps | OutputFormat::Terminal => Err(
+            QrError::UnverifiableFormat(format!("{format:?}").to_lowercase()),
+        ),
+        _ => Ok(()),
+    }
+}
+
+/// Decode the code written to `path` and check it matches `data`.
+///
+/// Raster and SVG files are decoded as written, HTML through its inline SVG
+/// and base64, `<img>` and Markdown through their embedded PNG. Matrix files
+/// are read back and drawn as an image. PDF, EPS and terminal output can't
+/// be checked (see [`check_verifiable`]). Retail barcodes are expected to
+/// read back with their check digit.
+pub fn verify_output(
+    data: &str,
+    path: &Path,
+    format: OutputFormat,
+    config: &RenderConfig,
+) -> Result<()> {
+    check_verifiable(format)?;
+    let decoded = match format {
+        OutputFormat::HtmlSvg
+        | OutputFormat::HtmlPage
+        | OutputFormat::Base64
+        | OutputFormat::Html
+        | OutputFormat::Markdown => {
+            let text = std::fs::read_to_string(path).map_err(|e| QrError::FileRead {
+                path: path.to_path_buf(),
+                source: e,
+            })?;
+            return verify_printed(data, &text, format, config);
+        }
+        matrix if matrix.is_matrix() => read_matrix(path, matrix, config)
+            .and_then(|rows| decode_image(&DynamicImage::ImageLuma8(preview(&rows)))),
+        _ => decode(&path.to_string_lossy()),
+    };
+    check_decoded(&config.symbol.scanned_content(data), decoded)
+}
+
+/// Decode the code in text output (HTML, Markdown or base64) as printed and
+/// check it matches `data`
+pub fn verify_printed(
+    data: &str,
+    text: &str,
+    format: OutputFormat,
+    config: &RenderConfig,
+) -> Result<()> {
+    check_verifiable(format)?;
+    let decoded = match format {
+        OutputFormat::HtmlSvg | OutputFormat::HtmlPage => {
+            inline_svg(text).and_then(|svg| decode_svg(svg.as_bytes()))
+        }
+        _ => embedded_png(text).and_then(|img| decode_image(&img)),
+    };
+    check_decoded(&config.symbol.scanned_content(data), decoded)
+}
+
+/// The outermost `<svg>` element of an HTML snippet or page
+fn inline_svg(text: &str) -> Result<&str> {
+    let start = text.find("<svg");
+    let end = text.rfind("</svg>").map(|end| end + "</svg>".len());
+    match (start, end) {
+        (Some(start), Some(end)) if start < end => Ok(&text[start..end]),
+        _ => Err(QrError::DecodeError("no inline SVG found".to_string())),
+    }
+}
+
+/// The image of the first base64 data URI in `text`
+fn embedded_png(text: &str) -> Result<DynamicImage> {
+    let start = text
+        .find("base64,")
+        .map(|start| start + "base64,".len())
+        .ok_or_else(|| QrError::DecodeError("no base64 image found".to_string()))?;
+    let encoded: String = text[start..]
+        .chars()
+        .take_while(|&c| c.is_ascii_alphanumeric() || matches!(c, '+' | '/' | '='))
+        .collect();
+    let png = STANDARD
+        .decode(encoded)
+        .map_err(|e| QrError::DecodeError(format!("invalid base64 image: {e}")))?;
+    Ok(image::load_from_memory(&png)?)
+}
+
+/// Decode every code on the Structured Append sheet written to `path` and
+/// check that the set puts back together as `data`
+pub fn verify_sheet(data: &str, path: &Path) -> Result<()> {
+    let decoded = decode_all(&path.to_string_lossy())
+        .and_then(|parts| structured::reassemble(&parts))
+        .map(|content| DecodedQr {
+            content,
+            format: "qrcode".to_string(),
+            structured_append: None,
+        });
+    check_decoded(data, decoded)
+}
+
+/// Compare a decoding result with the data that was encoded
+pub fn check_decoded(expected: &str, decoded: Result<DecodedQr>) -> Result<()> {
+    match decoded {
+        Ok(decoded) if decoded.content == expected => Ok(()),
+        Ok(decoded) => Err(QrError::VerificationFailed(format!(
+            "decoded {:?} instead of {expected:?}",
+            decoded.content
+        ))),
+        Err(e) => Err(QrError::VerificationFailed(format!(
+            "the code could not be read back ({e})"
+        ))),
+    }
+}
@@ -0,0 +1,144 @@
+use clap::Parser;
+use cqr::animate::{encode_apng, encode_gif, frames, reassemble, Assembler, Frame};
+use cqr::cli::Cli;
+use cqr::error::QrError;
+use cqr::renderer::{render_to_png, RenderConfig};
+use tempfile::tempdir;
+
+/// Bytes of every value, as in a binary file
+fn sample(len: usize) -> Vec<u8> {
+    (0..len).map(|i| (i * 131).to_le_bytes()[0]).collect()
+}
+
+fn small() -> RenderConfig {
+    RenderConfig {
+        size: 240,
+        ..RenderConfig::default()
+    }
+}
+
+#[test]
+fn test_frames_round_trip_in_any_order() {
+    let data = sample(1000);
+    let frames = frames(&data, 128, 0.0).unwrap();
+    assert_eq!(frames.len(), 8);
+
+    let mut assembler = Assembler::new();
+    for frame in frames.iter().rev() {
+        let text = frame.to_text();
+        assert!(text.starts_with("CQR:"));
+        assert_eq!(&Frame::parse(&text).unwrap(), frame);
+        assert!(assembler.push(frame).unwrap());
+    }
+    assert!(!assembler.push(&frames[0]).unwrap());
+    assert_eq!(assembler.finish().unwrap(), data);
+
+    // An empty file still makes one frame
+    let empty = cqr::animate::frames(&[], 64, 0.0).unwrap();
+    let mut assembler = Assembler::new();
+    assembler.push(&empty[0]).unwrap();
+    assert!(assembler.finish().unwrap().is_empty());
+}
+
+#[test]
+fn test_fountain_frames_cover_missed_frames() {
+    let data = sample(3000);
+    let frames = frames(&data, 100, 1.0).unwrap();
+    assert_eq!(frames.len(), 60);
+
+    // Every third data frame missed: the fountain frames make up for them
+    let mut assembler = Assembler::new();
+    for frame in frames.iter().filter(|frame| frame.index % 3 != 0) {
+        assembler.push(frame).unwrap();
+    }
+    assert!(assembler.is_complete());
+    assert_eq!(assembler.finish().unwrap(), data);
+
+    let mut assembler = Assembler::new();
+    for frame in &frames[..25] {
+        assembler.push(frame).unwrap();
+    }
+    assert_eq!(assembler.missing(), 5);
+    let error = assembler.finish().unwrap_err();
+    assert!(error.to_string().contains("5 more frames"), "{error}");
+}
+
+#[test]
+fn test_foreign_and_oversized_frames_are_skipped() {
+    assert!(Frame::parse("https://example.com").is_err());
+    assert!(Frame::parse("CQR:not base45!").is_err());
+
+    // Frames of another transfer are left out
+    let mut assembler = Assembler::new();
+    assembler
+        .push(&frames(b"first", 16, 0.0).unwrap()[0])
+        .unwrap();
+    assert!(!assembler
+        .push(&frames(b"second", 16, 0.0).unwrap()[0])
+        .unwrap());
+    assert_eq!(assembler.frames_seen(), 1);
+    assert_eq!(assembler.finish().unwrap(), b"first");
+    assert!(frames(b"x", 16, -1.0).is_err());
+
+    // A header claiming 4 GiB in 1-byte blocks is refused before anything
+    // is allocated for it
+    let huge = Frame {
+        checksum: 1,
+        length: u32::MAX,
+        block_size: 1,
+        index: 0,
+        payload: vec![0],
+    };
+    assert!(matches!(
+        Frame::parse(&huge.to_text()),
+        Err(QrError::InvalidAnimation(_))
+    ));
+    assert!(Assembler::new().push(&huge).is_err());
+    assert!(frames(&sample(70_000), 1, 0.0).is_err());
+
+    let parse = |chunk: &str| Cli::try_parse_from(["cqr", "animate", "f", "--chunk-size", chunk]);
+    assert!(parse("0").is_err());
+    assert!(parse("512").is_ok());
+}
+
+#[test]
+fn test_animations_and_captured_frames_reassemble() {
+    let data = sample(500);
+    let frames = frames(&data, 100, 0.6).unwrap();
+    let texts: Vec<String> = frames.iter().map(Frame::to_text).collect();
+    let dir = tempdir().unwrap();
+
+    for (name, bytes) in [
+        ("transfer.gif", encode_gif(&texts, 5.0, &small()).unwrap()),
+        ("transfer.png", encode_apng(&texts, 5.0, &small()).unwrap()),
+    ] {
+        let path = dir.path().join(name);
+        std::fs::write(&path, bytes).unwrap();
+        assert_eq!(reassemble(&path).unwrap().0, data, "{name}");
+    }
+    assert!(encode_gif(&texts, 0.0, &small()).is_err());
+
+    // A camera roll missing two frames, with unrelated files mixed in
+    let captures = dir.path().join("captures");
+    std::fs::create_dir(&captures).unwrap();
+    for (i, text) in texts.iter().enumerate().skip(2) {
+        let path = captures.join(format!("IMG_{i:04}.png"));
+        render_to_png(text, &path, &small()).unwrap();
+    }
+    render_to_png("https://example.com", &captures.join("other.png"), &small()).unwrap();
+    // A frame of another transfer, read first, and a forged header
+    let stray = cqr::animate::frames(b"another file", 16, 0.0).unwrap()[0].to_text();
+    render_to_png(&stray, &captures.join("IMG_0000.png"), &small()).unwrap();
+    let forged = Frame {
+        checksum: 1,
+        length: u32::MAX,
+        block_size: 1,
+        index: 0,
+        payload: vec![0],
+    };
+    render_to_png(&forged.to_text(), &captures.join("IMG_0001.png"), &small()).unwrap();
+    std::fs::write(captures.join("notes.txt"), "not an image").unwrap();
+    let (file, read) = reassemble(&captures).unwrap();
+    assert_eq!(file, data);
+    assert_eq!(read, 6);
+}
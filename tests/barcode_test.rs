@@ -0,0 +1,123 @@
+use clap::Parser;
+use cqr::cli::{Cli, OutputFormat};
+use cqr::decoder::{decode, decode_svg};
+use cqr::error::QrError;
+use cqr::renderer::{render_to_png, render_to_svg, RenderConfig};
+use cqr::shapes::ModuleStyle;
+use cqr::symbol::{encode, SymbolType};
+use cqr::verify::verify_output;
+use qrcode::EcLevel;
+use std::path::PathBuf;
+use tempfile::Builder;
+
+fn config(symbol: SymbolType) -> RenderConfig {
+    RenderConfig {
+        symbol,
+        quiet_zone: symbol.default_quiet_zone(),
+        ..RenderConfig::default()
+    }
+}
+
+#[test]
+fn test_every_symbology_decodes() {
+    for (symbol, data, format) in [
+        (SymbolType::DataMatrix, "PART-0042/LOT-7", "datamatrix"),
+        (SymbolType::Aztec, "TICKET 12345", "aztec"),
+        (SymbolType::Pdf417, "ID:4711;NAME:DOE", "pdf 417"),
+        (SymbolType::Code128, "WH-A12-0034", "code 128"),
+        (SymbolType::Ean13, "5901234123457", "ean 13"),
+        (SymbolType::UpcA, "036000291452", "upc a"),
+        (SymbolType::Itf, "12345678901231", "itf"),
+    ] {
+        let config = config(symbol);
+        let file = Builder::new().suffix(".png").tempfile().unwrap();
+        render_to_png(data, &file.path().to_path_buf(), &config).unwrap();
+        let decoded = decode(file.path().to_str().unwrap())
+            .unwrap_or_else(|e| panic!("{symbol:?} PNG failed to decode: {e}"));
+        assert_eq!(decoded.content, data);
+        assert_eq!(decoded.format, format);
+
+        let svg = render_to_svg(data, &config).unwrap();
+        let decoded = decode_svg(svg.as_bytes())
+            .unwrap_or_else(|e| panic!("{symbol:?} SVG failed to decode: {e}"));
+        assert_eq!(decoded.content, data);
+    }
+}
+
+#[test]
+fn test_linear_bars_and_quiet_zone() {
+    let ean = encode("590123412345", SymbolType::Ean13, EcLevel::M).unwrap();
+    assert_eq!((ean.width(), ean.height()), (95, 69));
+    assert!(ean.eyes().next().is_none());
+    for y in 0..ean.height() {
+        assert_eq!(ean.is_dark(2, y), ean.is_dark(2, 0));
+    }
+
+    // Long Code 128 bars grow with the symbol
+    let long = encode(&"A".repeat(30), SymbolType::Code128, EcLevel::M).unwrap();
+    assert_eq!(long.height(), long.width() * 3 / 20);
+
+    let quiet_zone = |args: &[&str]| Cli::try_parse_from(args).unwrap().quiet_zone;
+    assert_eq!(quiet_zone(&["cqr", "text", "1"]), None);
+    assert_eq!(SymbolType::Code128.default_quiet_zone(), 11);
+    assert_eq!(SymbolType::DataMatrix.default_quiet_zone(), 2);
+    let cli = Cli::try_parse_from(["cqr", "text", "1", "--symbology", "data-matrix"]).unwrap();
+    assert_eq!(cli.symbol, SymbolType::DataMatrix);
+}
+
+#[test]
+fn test_retail_check_digit_verifies() {
+    assert_eq!(
+        SymbolType::Ean13.scanned_content("590123412345"),
+        "5901234123457"
+    );
+    // EAN-13 codes in the UPC range read back as UPC-A
+    assert_eq!(
+        SymbolType::Ean13.scanned_content("0036000291452"),
+        "036000291452"
+    );
+    assert_eq!(
+        SymbolType::UpcA.scanned_content("03600029145"),
+        "036000291452"
+    );
+    assert_eq!(SymbolType::Code128.scanned_content("0123"), "0123");
+
+    let config = config(SymbolType::Ean13);
+    let file = Builder::new().suffix(".png").tempfile().unwrap();
+    let path = file.path().to_path_buf();
+    render_to_png("590123412345", &path, &config).unwrap();
+    verify_output("590123412345", &path, OutputFormat::Png, &config).unwrap();
+}
+
+#[test]
+fn test_invalid_barcode_input_and_options() {
+    match encode("ABC", SymbolType::Ean13, EcLevel::M) {
+        Err(e @ QrError::BarcodeGeneration { .. }) => {
+            assert!(e.to_string().contains("EAN-13"), "{e}");
+        }
+        other => panic!("expected BarcodeGeneration, got {other:?}"),
+    }
+    assert!(encode("123", SymbolType::Itf, EcLevel::M).is_err());
+
+    let logo = RenderConfig {
+        logo: Some(PathBuf::from("logo.png")),
+        ..config(SymbolType::DataMatrix)
+    };
+    assert!(matches!(
+        logo.validate(),
+        Err(QrError::BarcodeGeneration { .. })
+    ));
+    let dots = RenderConfig {
+        module_style: ModuleStyle::Dots,
+        ..config(SymbolType::Code128)
+    };
+    assert!(matches!(
+        dots.validate(),
+        Err(QrError::BarcodeGeneration { .. })
+    ));
+    let dotted_matrix = RenderConfig {
+        module_style: ModuleStyle::Dots,
+        ..config(SymbolType::DataMatrix)
+    };
+    assert!(dotted_matrix.validate().is_ok());
+}
@@ -0,0 +1,170 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+use clap::Parser;
+use cqr::cli::{Cli, OutputFormat, WifiSecurity};
+use cqr::decoder::{decode_image, decode_svg};
+use cqr::embed::{
+    alt_text, render_to_html_page, render_to_img_tag, render_to_inline_svg, render_to_markdown,
+};
+use cqr::generators::{
+    describe_payload, generate_bitcoin_string, generate_email_string, generate_event_string,
+    generate_geo_string, generate_phone_string, generate_sepa_string, generate_sms_string,
+    generate_vcard_string, generate_wifi_string,
+};
+use cqr::renderer::RenderConfig;
+use cqr::verify::verify_output;
+use std::path::Path;
+use tempfile::Builder;
+
+fn small() -> RenderConfig {
+    RenderConfig {
+        size: 200,
+        ..RenderConfig::default()
+    }
+}
+
+/// Text between `start` and the next `end`
+fn between<'a>(text: &'a str, start: &str, end: &str) -> &'a str {
+    let from = text.find(start).unwrap() + start.len();
+    &text[from..from + text[from..].find(end).unwrap()]
+}
+
+#[test]
+fn test_payload_descriptions() {
+    let wifi = generate_wifi_string("MyNetwork", "secret", &WifiSecurity::Wpa, false);
+    assert_eq!(describe_payload(&wifi), "WiFi network MyNetwork");
+    assert_eq!(
+        describe_payload(r"WIFI:S:Caf\;e;T:WPA;P:x;;"),
+        "WiFi network Caf;e"
+    );
+    assert_eq!(
+        describe_payload(&generate_email_string("ahoy@example.com", "Hi", "")),
+        "Email to ahoy@example.com"
+    );
+    assert_eq!(
+        describe_payload(&generate_phone_string("+15551234")),
+        "Phone number +15551234"
+    );
+    assert_eq!(
+        describe_payload(&generate_sms_string("+15551234", "yo")),
+        "Text message to +15551234"
+    );
+    assert_eq!(
+        describe_payload(&generate_geo_string(52.52, 13.405)),
+        "Location 52.52, 13.405"
+    );
+    assert_eq!(
+        describe_payload(&generate_bitcoin_string("bc1qxyz", Some(0.01), None, None)),
+        "Bitcoin payment of 0.01 BTC to bc1qxyz"
+    );
+    assert_eq!(
+        describe_payload(&generate_vcard_string("Anne", "Bonny", "", "", "")),
+        "Contact card for Anne Bonny"
+    );
+    assert_eq!(
+        describe_payload(&generate_event_string(
+            "Standup",
+            "20260101T090000",
+            "20260101T091500",
+            "",
+            ""
+        )),
+        "Calendar event Standup"
+    );
+    assert_eq!(
+        describe_payload(&generate_sepa_string(
+            "Red Cross",
+            "DE89 3704 0044 0532 0130 00",
+            None,
+            12.5,
+            None,
+            None
+        )),
+        "SEPA payment of EUR 12.50 to Red Cross"
+    );
+    assert_eq!(
+        describe_payload("https://example.com/docs/"),
+        "Link to example.com/docs"
+    );
+    assert_eq!(describe_payload("Hello\n  crew"), "Text: Hello crew");
+
+    let long = describe_payload(&"word ".repeat(40));
+    assert_eq!(long.chars().count(), 80);
+    assert!(long.ends_with('\u{2026}'));
+}
+
+#[test]
+fn test_img_tag() {
+    let tag = render_to_img_tag("https://example.com", &small()).unwrap();
+    assert!(
+        tag.starts_with(r#"<img src="data:image/png;base64,"#),
+        "{tag}"
+    );
+    assert!(tag.ends_with(r#"alt="Link to example.com">"#), "{tag}");
+
+    let png = STANDARD.decode(between(&tag, "base64,", "\"")).unwrap();
+    let img = image::load_from_memory(&png).unwrap();
+    assert_eq!(between(&tag, "width=\"", "\""), img.width().to_string());
+    assert_eq!(between(&tag, "height=\"", "\""), img.height().to_string());
+    assert_eq!(decode_image(&img).unwrap().content, "https://example.com");
+
+    // Given alt text wins, and is escaped
+    let config = RenderConfig {
+        alt_text: Some(r#"Scan "here" <now>"#.to_string()),
+        ..small()
+    };
+    assert_eq!(alt_text("x", &config), r#"Scan "here" <now>"#);
+    let tag = render_to_img_tag("x", &config).unwrap();
+    assert!(
+        tag.contains(r#"alt="Scan &quot;here&quot; &lt;now&gt;""#),
+        "{tag}"
+    );
+}
+
+#[test]
+fn test_inline_svg_and_page() {
+    let data = "Hello <crew> & co";
+    let svg = render_to_inline_svg(data, &small()).unwrap();
+    assert!(svg.starts_with(
+        r#"<svg role="img" aria-label="Text: Hello &lt;crew&gt; &amp; co" xmlns="http://www.w3.org/2000/svg""#
+    ), "{svg}");
+    assert!(svg.contains("><title>Text: Hello &lt;crew&gt; &amp; co</title>"));
+    assert!(!svg.contains("<?xml"));
+    assert_eq!(decode_svg(svg.as_bytes()).unwrap().content, data);
+
+    let page = render_to_html_page(data, &small()).unwrap();
+    assert!(page.starts_with("<!DOCTYPE html>\n<html lang=\"en\">"));
+    assert!(page.contains("<title>Text: Hello &lt;crew&gt; &amp; co</title>"));
+    assert!(page.contains(&svg));
+
+    let file = Builder::new().suffix(".html").tempfile().unwrap();
+    std::fs::write(file.path(), &page).unwrap();
+    verify_output(data, file.path(), OutputFormat::HtmlPage, &small()).unwrap();
+}
+
+#[test]
+fn test_markdown_and_format_selection() {
+    let config = RenderConfig {
+        alt_text: Some("Deck [A] *map*".to_string()),
+        ..small()
+    };
+    let markdown = render_to_markdown("x", &config).unwrap();
+    assert!(
+        markdown.starts_with(r"![Deck \[A\] \*map\*](data:image/png;base64,iVBOR"),
+        "{markdown}"
+    );
+    assert!(markdown.ends_with(')'));
+
+    assert_eq!(
+        OutputFormat::from_extension(Path::new("qr.HTML")),
+        Some(OutputFormat::HtmlPage)
+    );
+    assert_eq!(OutputFormat::HtmlPage.extension(), "html");
+    assert!(OutputFormat::Markdown.is_printed());
+    assert!(!OutputFormat::HtmlPage.is_printed());
+
+    let cli = Cli::try_parse_from(["cqr", "text", "hi", "-F", "html-svg", "--alt", "Hi"]).unwrap();
+    assert_eq!(cli.output_format(), OutputFormat::HtmlSvg);
+    assert_eq!(cli.alt.as_deref(), Some("Hi"));
+    let cli = Cli::try_parse_from(["cqr", "text", "hi", "-o", "page.html"]).unwrap();
+    assert_eq!(cli.output_format(), OutputFormat::HtmlPage);
+}
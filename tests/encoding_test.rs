@@ -0,0 +1,160 @@
+use clap::Parser;
+use cqr::cli::Cli;
+use cqr::decoder::decode;
+use cqr::encoding::{encode, EncodingMode};
+use cqr::renderer::{render_to_png, RenderConfig};
+use cqr::symbol::{self, SymbolType};
+use qrcode::EcLevel;
+use tempfile::Builder;
+
+fn dark_modules(grid: &cqr::shapes::ModuleGrid) -> Vec<bool> {
+    (0..grid.height())
+        .flat_map(|y| (0..grid.width()).map(move |x| grid.is_dark(x, y)))
+        .collect()
+}
+
+#[test]
+fn test_defaults_match_automatic_encoding() {
+    for data in [
+        "HELLO 123",
+        "https://example.com/a?b=1",
+        "0123456789012345",
+        "",
+    ] {
+        let encoded = encode(data, &RenderConfig::default()).unwrap();
+        let auto = symbol::encode(data, SymbolType::Qr, EcLevel::M).unwrap();
+        assert_eq!(dark_modules(&encoded.grid), dark_modules(&auto), "{data}");
+        assert_eq!(
+            SymbolType::Qr.version_name(&auto),
+            encoded.version.to_string()
+        );
+    }
+
+    // The reported mask is the one applied: forcing it gives the same symbol
+    let automatic = encode("HELLO 123", &RenderConfig::default()).unwrap();
+    let forced = encode(
+        "HELLO 123",
+        &RenderConfig {
+            mask: Some(automatic.mask),
+            ..RenderConfig::default()
+        },
+    )
+    .unwrap();
+    assert_eq!(dark_modules(&forced.grid), dark_modules(&automatic.grid));
+
+    let mixed = encode("ABC123456789abc", &RenderConfig::default()).unwrap();
+    assert!(mixed.segments.len() > 1, "{}", mixed.segment_names());
+}
+
+#[test]
+fn test_pinned_version_and_mask_decode() {
+    for mask in 0..8 {
+        let config = RenderConfig {
+            qr_version: Some(4),
+            exact_version: true,
+            mask: Some(mask),
+            ..RenderConfig::default()
+        };
+        let encoded = encode("LABEL-0042", &config).unwrap();
+        assert_eq!((encoded.version, encoded.mask), (4, mask));
+        assert_eq!(encoded.grid.width(), 33);
+
+        let file = Builder::new().suffix(".png").tempfile().unwrap();
+        render_to_png("LABEL-0042", &file.path().to_path_buf(), &config).unwrap();
+        assert_eq!(
+            decode(file.path().to_str().unwrap()).unwrap().content,
+            "LABEL-0042"
+        );
+    }
+
+    // A minimum version still grows with the data
+    let minimum = RenderConfig {
+        qr_version: Some(3),
+        ..RenderConfig::default()
+    };
+    assert_eq!(encode("1", &minimum).unwrap().version, 3);
+    assert_eq!(encode(&"x".repeat(200), &minimum).unwrap().version, 10);
+
+    let exact = RenderConfig {
+        exact_version: true,
+        ..minimum
+    };
+    let err = encode(&"x".repeat(200), &exact).unwrap_err();
+    assert!(err.to_string().contains("needs version 10"), "{err}");
+}
+
+#[test]
+fn test_segment_modes() {
+    let config = |encoding_mode| RenderConfig {
+        encoding_mode,
+        ..RenderConfig::default()
+    };
+    // 40 digits fit version 2 as numeric, but need version 3 as bytes
+    let digits = "1234567890".repeat(4);
+    let numeric = encode(&digits, &config(EncodingMode::Numeric)).unwrap();
+    assert_eq!(
+        (numeric.version, numeric.segment_names().as_str()),
+        (2, "numeric")
+    );
+    let bytes = encode(&digits, &config(EncodingMode::Byte)).unwrap();
+    assert_eq!((bytes.version, bytes.segment_names().as_str()), (3, "byte"));
+
+    let kanji = config(EncodingMode::Kanji);
+    assert_eq!(
+        encode("漢字", &kanji).unwrap().segments,
+        [EncodingMode::Kanji]
+    );
+    let file = Builder::new().suffix(".png").tempfile().unwrap();
+    render_to_png("日本語テキスト", &file.path().to_path_buf(), &kanji).unwrap();
+    assert_eq!(
+        decode(file.path().to_str().unwrap()).unwrap().content,
+        "日本語テキスト"
+    );
+
+    for (mode, data) in [
+        (EncodingMode::Numeric, "12a"),
+        (EncodingMode::Alphanumeric, "hello"),
+        (EncodingMode::Kanji, "ABC"),
+    ] {
+        let err = encode(data, &config(mode)).unwrap_err();
+        assert!(err.to_string().contains(mode.name()), "{err}");
+    }
+}
+
+#[test]
+fn test_cli_options_and_validation() {
+    let cli = Cli::try_parse_from([
+        "cqr",
+        "text",
+        "hi",
+        "--qr-version",
+        "7",
+        "--exact-version",
+        "--mask",
+        "5",
+        "--encoding-mode",
+        "alphanumeric",
+    ])
+    .unwrap();
+    assert_eq!(cli.qr_version, Some(7));
+    assert!(cli.exact_version);
+    assert_eq!(cli.mask, Some(5));
+    assert_eq!(cli.encoding_mode, EncodingMode::Alphanumeric);
+
+    assert!(Cli::try_parse_from(["cqr", "text", "hi", "--exact-version"]).is_err());
+    assert!(Cli::try_parse_from(["cqr", "text", "hi", "--mask", "8"]).is_err());
+    assert!(Cli::try_parse_from(["cqr", "text", "hi", "--qr-version", "41"]).is_err());
+
+    let micro = RenderConfig {
+        symbol: SymbolType::Micro,
+        mask: Some(1),
+        ..RenderConfig::default()
+    };
+    assert!(micro.validate().is_err());
+    let out_of_range = RenderConfig {
+        qr_version: Some(0),
+        ..RenderConfig::default()
+    };
+    assert!(out_of_range.validate().is_err());
+    assert!(RenderConfig::default().validate().is_ok());
+}
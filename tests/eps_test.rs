@@ -0,0 +1,108 @@
+use cqr::eps::render_to_eps;
+use cqr::error::QrError;
+use cqr::frame::FrameStyle;
+use cqr::gradient::Gradient;
+use cqr::renderer::{Color, RenderConfig};
+use cqr::shapes::ModuleGrid;
+use qrcode::{EcLevel, QrCode};
+use std::path::PathBuf;
+
+#[test]
+fn test_eps_bounding_box_and_module_count() {
+    let data = "Hello EPS";
+    let config = RenderConfig {
+        print_size_mm: Some(25.4), // exactly one inch = 72pt
+        ..RenderConfig::default()
+    };
+
+    let eps = render_to_eps(data, &config).expect("Failed to render EPS");
+
+    assert!(eps.starts_with("%!PS-Adobe-3.0 EPSF-3.0\n"));
+    assert!(eps.contains("%%BoundingBox: 0 0 72 72\n"));
+    assert!(eps.contains("%%HiResBoundingBox: 0 0 72 72\n"));
+    assert!(eps.trim_end().ends_with("%%EOF"));
+
+    // One subpath per data module plus ring, hole and pupil for each eye
+    let grid = ModuleGrid::new(&QrCode::with_error_correction_level(data, EcLevel::M).unwrap());
+    let data_modules = grid
+        .dark_modules()
+        .filter(|&(x, y)| !grid.is_finder(x, y))
+        .count();
+    let subpaths = eps.lines().filter(|line| line.ends_with(" Z")).count();
+    assert_eq!(subpaths, data_modules + 9);
+
+    // Quiet zone of 2 modules on each side scales the symbol to the bounding box
+    let total = grid.width() + 4;
+    assert!(eps.contains(&format!("0 0 {total} {total} rectfill")));
+}
+
+#[test]
+fn test_eps_colors() {
+    let config = RenderConfig {
+        fg_color: Color::rgb(255, 0, 0),
+        bg_color: Color::rgb(0, 0, 255),
+        ..RenderConfig::default()
+    };
+
+    let eps = render_to_eps("Hello Colors", &config).unwrap();
+
+    assert!(eps.contains("0 0 1 setrgbcolor 0 0"));
+    assert!(eps.contains("1 0 0 setrgbcolor newpath"));
+    assert!(eps.contains("%%LanguageLevel: 2"));
+}
+
+#[test]
+fn test_eps_gradient_uses_shading() {
+    let config = RenderConfig {
+        gradient: Some(Gradient::parse("radial:#000,#1a237e").unwrap()),
+        bg_color: Color::TRANSPARENT,
+        ..RenderConfig::default()
+    };
+
+    let eps = render_to_eps("Hello Gradient", &config).unwrap();
+
+    assert!(eps.contains("%%LanguageLevel: 3"));
+    assert!(eps.contains("clip << /ShadingType 3"));
+    assert!(!eps.contains("rectfill"));
+}
+
+#[test]
+fn test_eps_rejects_unsupported_options() {
+    let configs = [
+        RenderConfig {
+            logo: Some(PathBuf::from("logo.png")),
+            ..RenderConfig::default()
+        },
+        RenderConfig {
+            caption: Some("{data}".to_string()),
+            ..RenderConfig::default()
+        },
+        RenderConfig {
+            banner: Some("SCAN ME".to_string()),
+            ..RenderConfig::default()
+        },
+        RenderConfig {
+            frame: FrameStyle::Rounded,
+            ..RenderConfig::default()
+        },
+    ];
+    for (config, option) in configs
+        .iter()
+        .zip(["--logo", "--caption", "--banner", "--frame"])
+    {
+        match render_to_eps("Hello EPS", config) {
+            Err(err @ QrError::InvalidLayout(_)) => {
+                assert!(err.to_string().contains(option), "{err}");
+            }
+            other => panic!("{option} was not rejected: {other:?}"),
+        }
+    }
+
+    let config = RenderConfig {
+        caption: Some("Scan".to_string()),
+        frame: FrameStyle::Square,
+        ..RenderConfig::default()
+    };
+    let err = render_to_eps("Hello EPS", &config).unwrap_err().to_string();
+    assert!(err.contains("--caption, --frame"), "{err}");
+}
@@ -0,0 +1,122 @@
+use clap::Parser;
+use cqr::batch::process_batch;
+use cqr::cli::{Cli, OutputFormat};
+use cqr::decoder::decode;
+use cqr::renderer::{encode_image, render_to_image, Color, RenderConfig};
+use image::ImageFormat;
+use std::path::Path;
+use tempfile::{tempdir, Builder};
+
+#[test]
+fn test_format_detected_from_extension() {
+    let detect = |args: &[&str]| Cli::try_parse_from(args).unwrap().output_format();
+
+    assert_eq!(
+        detect(&["cqr", "text", "hi", "-o", "code.JPG"]),
+        OutputFormat::Jpeg
+    );
+    assert_eq!(
+        detect(&["cqr", "text", "hi", "-o", "code.tif"]),
+        OutputFormat::Tiff
+    );
+    assert_eq!(
+        detect(&["cqr", "text", "hi", "-o", "code.pdf"]),
+        OutputFormat::Pdf
+    );
+    assert_eq!(
+        detect(&["cqr", "text", "hi", "-o", "code.xyz"]),
+        OutputFormat::Png
+    );
+    assert_eq!(detect(&["cqr", "text", "hi"]), OutputFormat::Png);
+    // An explicit --format wins over the extension
+    assert_eq!(
+        detect(&["cqr", "text", "hi", "-o", "code.png", "-F", "webp"]),
+        OutputFormat::Webp
+    );
+
+    assert_eq!(
+        OutputFormat::from_extension(Path::new("a.jpeg")),
+        Some(OutputFormat::Jpeg)
+    );
+    assert_eq!(OutputFormat::from_extension(Path::new("noext")), None);
+    assert!(Cli::try_parse_from(["cqr", "text", "hi", "--jpeg-quality", "0"]).is_err());
+}
+
+#[test]
+fn test_every_raster_format_decodes() {
+    let data = "https://example.com/formats";
+    let config = RenderConfig::default();
+
+    for format in [
+        OutputFormat::Png,
+        OutputFormat::Jpeg,
+        OutputFormat::Webp,
+        OutputFormat::Gif,
+        OutputFormat::Bmp,
+        OutputFormat::Tiff,
+    ] {
+        let image_format = format.image_format().unwrap();
+        let temp_file = Builder::new()
+            .suffix(&format!(".{}", format.extension()))
+            .tempfile()
+            .unwrap();
+        let path = temp_file.path().to_path_buf();
+        render_to_image(data, &path, image_format, &config).unwrap();
+
+        assert_eq!(image::ImageFormat::from_path(&path).unwrap(), image_format);
+        let decoded = decode(path.to_str().unwrap())
+            .unwrap_or_else(|e| panic!("{format:?} failed to decode: {e}"));
+        assert_eq!(decoded.content, data);
+    }
+}
+
+#[test]
+fn test_jpeg_flattens_transparency_and_webp_is_lossless() {
+    let config = RenderConfig {
+        bg_color: Color::TRANSPARENT,
+        ..RenderConfig::default()
+    };
+
+    let jpeg = encode_image("Transparent", ImageFormat::Jpeg, &config).unwrap();
+    let jpeg = image::load_from_memory_with_format(&jpeg, ImageFormat::Jpeg).unwrap();
+    assert!(!jpeg.color().has_alpha());
+    let corner = jpeg.to_rgb8().get_pixel(0, 0).0;
+    assert!(corner.iter().all(|&c| c > 245), "corner was {corner:?}");
+
+    let png = encode_image("Transparent", ImageFormat::Png, &config).unwrap();
+    let webp = encode_image("Transparent", ImageFormat::WebP, &config).unwrap();
+    let png = image::load_from_memory(&png).unwrap().to_rgba8();
+    let webp = image::load_from_memory(&webp).unwrap().to_rgba8();
+    assert_eq!(png, webp);
+}
+
+#[test]
+fn test_batch_extension_follows_format() {
+    let dir = tempdir().unwrap();
+    let input = dir.path().join("codes.csv");
+    std::fs::write(&input, "filename,data\nfirst,Hello\n").unwrap();
+
+    for (format, extension) in [
+        (OutputFormat::Jpeg, "jpg"),
+        (OutputFormat::Webp, "webp"),
+        (OutputFormat::Tiff, "tiff"),
+        (OutputFormat::Base64, "png"),
+    ] {
+        let output_dir = dir.path().join(extension);
+        process_batch(
+            &input,
+            &output_dir,
+            &format,
+            &RenderConfig::default(),
+            false,
+        )
+        .unwrap();
+
+        let path = output_dir.join(format!("first.{extension}"));
+        assert!(
+            image::open(&path).is_ok(),
+            "{format:?} wrote {}",
+            path.display()
+        );
+    }
+}
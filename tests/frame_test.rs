@@ -0,0 +1,123 @@
+use cqr::batch::process_batch;
+use cqr::cli::OutputFormat;
+use cqr::decoder::decode;
+use cqr::frame::{CaptionPosition, FrameLayout, FrameStyle};
+use cqr::pdf::render_to_pdf;
+use cqr::renderer::{render_to_png, render_to_svg, RenderConfig};
+use tempfile::{tempdir, Builder};
+
+#[test]
+fn test_frame_layout() {
+    assert!(FrameLayout::new(&RenderConfig::default(), "data", 500.0).is_none());
+
+    let below = RenderConfig {
+        caption: Some("Scan me".to_string()),
+        ..RenderConfig::default()
+    };
+    let layout = FrameLayout::new(&below, "data", 500.0).unwrap();
+    assert!((layout.width - 500.0).abs() < 1e-9);
+    assert!(layout.height > 500.0);
+    assert!(layout.code_y.abs() < 1e-9);
+    assert!(layout.texts[0].baseline > 500.0);
+
+    let above_framed = RenderConfig {
+        caption: Some("Scan me".to_string()),
+        caption_position: CaptionPosition::Above,
+        frame: FrameStyle::Rounded,
+        banner: Some("SCAN ME".to_string()),
+        ..RenderConfig::default()
+    };
+    let layout = FrameLayout::new(&above_framed, "data", 500.0).unwrap();
+    assert!(layout.width > 500.0);
+    assert!(layout.code_y > layout.code_x);
+    assert!(layout.texts[0].baseline < layout.code_y);
+    // Banner text sits under the code, knocked out in the background color
+    assert!(layout.texts[1].baseline > layout.code_y + 500.0);
+    assert_eq!(layout.texts[1].color, above_framed.bg_color);
+    assert_eq!(layout.fills.len(), 1);
+    assert!(layout.fills[0].hole.is_some());
+
+    // Long captions shrink to fit the code width
+    let long = RenderConfig {
+        caption: Some(cqr::frame::DATA_PLACEHOLDER.to_string()),
+        ..RenderConfig::default()
+    };
+    let data = "https://example.com/a/very/long/path/that/would/never/fit/at/the/default/size";
+    let layout = FrameLayout::new(&long, data, 500.0).unwrap();
+    assert_eq!(layout.texts[0].text, data);
+    assert!(cqr::frame::text_width(data, layout.texts[0].font_size) <= 500.0);
+}
+
+#[test]
+fn test_framed_png_decodes() {
+    let data = "https://example.com/framed";
+    let config = RenderConfig {
+        caption: Some("Scan to visit".to_string()),
+        banner: Some("SCAN ME".to_string()),
+        frame: FrameStyle::Rounded,
+        ..RenderConfig::default()
+    };
+
+    let temp_file = Builder::new().suffix(".png").tempfile().unwrap();
+    let path = temp_file.path().to_path_buf();
+    render_to_png(data, &path, &config).unwrap();
+
+    let img = image::open(&path).unwrap();
+    assert!(img.height() > img.width());
+    let decoded = decode(path.to_str().unwrap()).expect("Failed to decode framed QR");
+    assert_eq!(decoded.content, data);
+}
+
+#[test]
+fn test_svg_and_pdf_captions() {
+    let config = RenderConfig {
+        caption: Some("Join <Guest> & relax".to_string()),
+        frame: FrameStyle::Square,
+        ..RenderConfig::default()
+    };
+
+    let svg = render_to_svg("WIFI:S:Guest;;", &config).unwrap();
+    assert!(svg.contains(">Join &lt;Guest&gt; &amp; relax</text>"));
+    assert!(svg.contains(r#"text-anchor="middle""#));
+    assert!(svg.contains(r#"<g transform="translate("#));
+    assert!(svg.ends_with("</g></svg>"));
+
+    let pdf = render_to_pdf("WIFI:S:Guest;;", &config).unwrap();
+    let text = String::from_utf8_lossy(&pdf);
+    // Caption glyphs are drawn as outlines: no font resources
+    assert!(!text.contains("/Font"));
+    assert!(text.contains("f*\n"));
+    let media_box = text
+        .split("/MediaBox [0 0 ")
+        .nth(1)
+        .and_then(|rest| rest.split(']').next())
+        .unwrap();
+    let dims: Vec<f64> = media_box.split(' ').map(|n| n.parse().unwrap()).collect();
+    assert!(dims[1] > dims[0]);
+}
+
+#[test]
+fn test_batch_caption_column() {
+    let dir = tempdir().unwrap();
+    let input = dir.path().join("codes.csv");
+    std::fs::write(
+        &input,
+        "filename,data,caption\nlobby,WIFI:S:Lobby;;,Lobby WiFi\nplain,Hello,\n",
+    )
+    .unwrap();
+    let output_dir = dir.path().join("out");
+
+    process_batch(
+        &input,
+        &output_dir,
+        &OutputFormat::Svg,
+        &RenderConfig::default(),
+        false,
+    )
+    .unwrap();
+
+    let lobby = std::fs::read_to_string(output_dir.join("lobby.svg")).unwrap();
+    assert!(lobby.contains(">Lobby WiFi</text>"));
+    let plain = std::fs::read_to_string(output_dir.join("plain.svg")).unwrap();
+    assert!(!plain.contains("<text"));
+}
@@ -0,0 +1,105 @@
+use cqr::decoder::decode;
+use cqr::gradient::{Gradient, GradientKind};
+use cqr::renderer::{render_to_png, render_to_svg, Color, RenderConfig};
+use tempfile::Builder;
+
+#[test]
+fn test_parse_gradient_spec() {
+    let linear = Gradient::parse("linear:90:#f00@0,#0f0@50%,#00f@1").expect("valid spec");
+    assert_eq!(linear.kind, GradientKind::Linear { angle: 90.0 });
+    assert_eq!(linear.stops.len(), 3);
+    assert!((linear.stops[1].offset - 0.5).abs() < f64::EPSILON);
+    assert_eq!(linear.stops[2].color, Color::rgb(0, 0, 255));
+
+    // Angle defaults to 45° and offsets are spread evenly
+    let radial = Gradient::parse("radial:#000,#333,#666").expect("valid spec");
+    assert_eq!(radial.kind, GradientKind::Radial);
+    assert!((radial.stops[1].offset - 0.5).abs() < f64::EPSILON);
+
+    assert!(Gradient::parse("conic:#000,#fff").is_err());
+    assert!(Gradient::parse("linear:45:#000").is_err());
+    assert!(Gradient::parse("linear:45:#000@1,#fff@0").is_err());
+    assert!(Gradient::parse("linear:abc:#000,#fff").is_err());
+}
+
+#[test]
+fn test_multi_stop_interpolation() {
+    let gradient = Gradient::parse("linear:0:#000000@0,#ff0000@0.5,#ff00ff@1").unwrap();
+
+    assert_eq!(gradient.color_at(0.0), Color::rgb(0, 0, 0));
+    assert_eq!(gradient.color_at(0.25), Color::rgb(128, 0, 0));
+    assert_eq!(gradient.color_at(0.75), Color::rgb(255, 0, 128));
+    assert_eq!(gradient.color_at(1.0), Color::rgb(255, 0, 255));
+}
+
+#[test]
+fn test_diagonal_matches_legacy_progress() {
+    let gradient = Gradient::diagonal(Color::rgb(0, 0, 0), Color::rgb(0, 0, 255));
+    let size = 400.0;
+
+    for (x, y) in [(0.0, 0.0), (100.0, 300.0), (400.0, 400.0), (250.0, 10.0)] {
+        let legacy = (x + y) / (2.0 * size);
+        assert!((gradient.progress_at(x, y, size, size) - legacy).abs() < 1e-9);
+    }
+}
+
+#[test]
+fn test_png_pixels_follow_svg_geometry() {
+    let gradient = Gradient::parse("radial:#000000,#1a237e@0.6,#4a148c").unwrap();
+    let config = RenderConfig {
+        gradient: Some(gradient.clone()),
+        ..RenderConfig::default()
+    };
+
+    let temp_file = Builder::new().suffix(".png").tempfile().unwrap();
+    let path = temp_file.path().to_path_buf();
+    render_to_png("Gradient pixels", &path, &config).unwrap();
+
+    let img = image::open(&path).unwrap().to_rgba8();
+    let size = f64::from(img.width());
+    let mut checked = 0;
+    for (x, y, pixel) in img.enumerate_pixels() {
+        if pixel.0 != [255, 255, 255, 255] {
+            let t = gradient.progress_at(f64::from(x) + 0.5, f64::from(y) + 0.5, size, size);
+            let expected = gradient.color_at(t);
+            assert_eq!(pixel.0, [expected.r, expected.g, expected.b, 255]);
+            checked += 1;
+        }
+    }
+    assert!(checked > 0);
+
+    let svg = render_to_svg("Gradient pixels", &config).unwrap();
+    assert!(svg.contains("<radialGradient id=\"grad\" gradientUnits=\"userSpaceOnUse\""));
+    assert_eq!(svg.matches("<stop ").count(), 3);
+}
+
+#[test]
+fn test_gradient_decodes() {
+    let data = "https://example.com/gradient";
+    let config = RenderConfig {
+        gradient: Some(Gradient::parse("linear:135:#0d47a1,#000000@0.5,#b71c1c").unwrap()),
+        ..RenderConfig::default()
+    };
+    config
+        .validate()
+        .expect("Gradient should have enough contrast");
+
+    let temp_file = Builder::new().suffix(".png").tempfile().unwrap();
+    let path = temp_file.path().to_path_buf();
+    render_to_png(data, &path, &config).unwrap();
+
+    let decoded = decode(path.to_str().unwrap()).expect("Failed to decode gradient QR");
+    assert_eq!(decoded.content, data);
+}
+
+#[test]
+fn test_low_contrast_gradient_is_linted() {
+    let config = RenderConfig {
+        gradient: Some(Gradient::parse("linear:#000000,#ffff00").unwrap()),
+        ..RenderConfig::default()
+    };
+
+    // The color lint reports it instead of rendering failing
+    assert!(config.validate().is_ok());
+    assert!(!config.lint_colors().is_empty());
+}
@@ -0,0 +1,115 @@
+use clap::Parser;
+use cqr::cli::Cli;
+use cqr::gradient::Gradient;
+use cqr::lint::{ColorWarning, Deficiency};
+use cqr::renderer::{Color, RenderConfig};
+use std::process::Command;
+
+fn colors(fg: &str, bg: &str) -> RenderConfig {
+    RenderConfig {
+        fg_color: Color::from_hex(fg).unwrap(),
+        bg_color: Color::from_hex(bg).unwrap(),
+        ..RenderConfig::default()
+    }
+}
+
+#[test]
+fn test_default_colors_pass() {
+    assert!(RenderConfig::default().lint_colors().is_empty());
+    assert!(colors("#1a237e", "#fff8e1").lint_colors().is_empty());
+}
+
+#[test]
+fn test_low_contrast_and_inverted_codes() {
+    let warnings = colors("#ccc", "#fff").lint_colors();
+    assert_eq!(warnings.len(), 1);
+    assert!(matches!(
+        &warnings[0],
+        ColorWarning::LowContrast { role, ratio, .. } if role == "foreground" && *ratio < 2.0
+    ));
+
+    let warnings = colors("#fff", "#000").lint_colors();
+    assert_eq!(warnings, vec![ColorWarning::Inverted]);
+    assert!(warnings[0].to_string().contains("dark-on-light"));
+
+    // Faint modules on a transparent background are judged on white
+    let warnings = colors("#00000020", "transparent").lint_colors();
+    assert!(matches!(warnings[..], [ColorWarning::LowContrast { .. }]));
+
+    // Every gradient stop and finder color is checked
+    let config = RenderConfig {
+        gradient: Some(Gradient::parse("linear:0:#000@0,#ddd@1").unwrap()),
+        eye_inner_color: Some(Color::from_hex("#eee").unwrap()),
+        ..RenderConfig::default()
+    };
+    let roles: Vec<String> = config
+        .lint_colors()
+        .into_iter()
+        .filter_map(|warning| match warning {
+            ColorWarning::LowContrast { role, .. } => Some(role),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(roles, ["gradient stop 2", "eye inner color"]);
+}
+
+#[test]
+fn test_color_vision_deficiency_simulation() {
+    // Neutral colors look the same to everyone
+    for deficiency in Deficiency::ALL {
+        let gray = deficiency.simulate(Color::rgb(128, 128, 128));
+        assert!(
+            gray.r.abs_diff(128) <= 1 && gray.g.abs_diff(128) <= 1 && gray.b.abs_diff(128) <= 1
+        );
+    }
+
+    // Blue on amber scans for typical vision but washes out without red cones
+    let config = colors("#3355ff", "#ffcc00");
+    assert!(Color::rgb(0x33, 0x55, 0xff).contrast_ratio(config.bg_color) > 3.0);
+    let warnings = config.lint_colors();
+    assert!(
+        matches!(
+            &warnings[..],
+            [ColorWarning::ColorBlind {
+                deficiency: Deficiency::Protanopia,
+                ..
+            }]
+        ),
+        "{warnings:?}"
+    );
+    assert!(warnings[0].to_string().contains("red-blind"));
+}
+
+#[test]
+fn test_strict_turns_warnings_into_errors() {
+    assert!(
+        Cli::try_parse_from(["cqr", "text", "hi", "--strict"])
+            .unwrap()
+            .strict
+    );
+    assert!(!Cli::try_parse_from(["cqr", "text", "hi"]).unwrap().strict);
+
+    // A low-contrast gradient stop only warns, unless --strict is given
+    let dir = tempfile::tempdir().unwrap();
+    let output = dir.path().join("qr.png");
+    let run = |strict: bool| {
+        let mut command = Command::new(env!("CARGO_BIN_EXE_cqr"));
+        command
+            .args(["text", "hi", "--gradient", "linear:#000000,#ffff00", "-o"])
+            .arg(&output);
+        if strict {
+            command.arg("--strict");
+        }
+        command.output().unwrap()
+    };
+    let lenient = run(false);
+    assert!(lenient.status.success());
+    assert!(String::from_utf8_lossy(&lenient.stderr).contains("gradient stop 2"));
+    assert!(output.exists());
+    std::fs::remove_file(&output).unwrap();
+
+    let strict = run(true);
+    assert!(!strict.status.success());
+    assert!(String::from_utf8_lossy(&strict.stderr).contains("--strict"));
+    assert!(!output.exists());
+}
@@ -0,0 +1,272 @@
+use clap::Parser;
+use cqr::cli::Cli;
+use cqr::decoder::decode;
+use cqr::error::QrError;
+use cqr::logo::{LogoKnockout, LogoLayout};
+use cqr::renderer::{render_to_png, render_to_svg, Color, RenderConfig};
+use cqr::shapes::ModuleGrid;
+use image::{ImageBuffer, Rgba};
+use qrcode::{EcLevel, QrCode};
+use resvg::{tiny_skia, usvg};
+use std::path::PathBuf;
+use tempfile::{Builder, NamedTempFile};
+
+const DATA: &str = "https://example.com/logo";
+
+/// A red logo whose right half is transparent
+fn logo_file() -> NamedTempFile {
+    let file = Builder::new().suffix(".png").tempfile().unwrap();
+    let logo: ImageBuffer<Rgba<u8>, Vec<u8>> = ImageBuffer::from_fn(32, 32, |x, _| {
+        Rgba([200, 0, 0, if x < 16 { 255 } else { 0 }])
+    });
+    logo.save(file.path()).unwrap();
+    file
+}
+
+fn logo_config(logo: &NamedTempFile) -> RenderConfig {
+    RenderConfig {
+        logo: Some(logo.path().to_path_buf()),
+        ec_level: EcLevel::H,
+        ..RenderConfig::default()
+    }
+}
+
+fn grid(config: &RenderConfig) -> ModuleGrid {
+    ModuleGrid::new(&QrCode::with_error_correction_level(DATA, config.ec_level).unwrap())
+}
+
+#[test]
+fn test_logo_layout_and_knockout_shapes() {
+    let logo = logo_file();
+    let square = RenderConfig {
+        logo_scale: 0.25,
+        logo_knockout: LogoKnockout::Square,
+        ..logo_config(&logo)
+    };
+    let grid = grid(&square);
+    let total = f64::from(u32::try_from(grid.width()).unwrap() + 4);
+
+    let layout = LogoLayout::new(&square, &grid).unwrap().unwrap();
+    assert!((layout.size - total * 0.25).abs() < 1e-9);
+    assert!((layout.x - (total - layout.size) / 2.0).abs() < 1e-9);
+    let knockout = layout.knockout.unwrap();
+    assert!((knockout.width - (layout.size + 2.0)).abs() < 1e-9);
+
+    // The center module is hidden, the corners are not
+    let center = grid.width() / 2;
+    assert!(layout.covers(center, center));
+    assert!(!layout.covers(0, 0));
+
+    // A circle hides fewer modules than the square around it
+    let circle = RenderConfig {
+        logo_knockout: LogoKnockout::Circle,
+        ..square
+    };
+    let circle = LogoLayout::new(&circle, &grid).unwrap().unwrap();
+    assert!(circle.covered_modules(&grid) < layout.covered_modules(&grid));
+
+    // Clearing leaves no dark module under the knockout
+    let mut cleared = grid;
+    layout.clear_modules(&mut cleared);
+    for (x, y) in cleared.dark_modules() {
+        assert!(!layout.covers(x, y), "module ({x}, {y}) is still dark");
+    }
+
+    assert!(LogoLayout::new(&RenderConfig::default(), &cleared)
+        .unwrap()
+        .is_none());
+}
+
+#[test]
+fn test_logo_rejected_beyond_error_correction() {
+    let logo = logo_file();
+    let large = RenderConfig {
+        logo_scale: 0.3,
+        ..logo_config(&logo)
+    };
+    assert!(render_to_svg(DATA, &large).is_ok());
+
+    let low = RenderConfig {
+        ec_level: EcLevel::L,
+        ..large.clone()
+    };
+    match render_to_svg(DATA, &low) {
+        Err(QrError::LogoTooLarge { coverage, max, .. }) => assert!(coverage > max),
+        other => panic!("expected LogoTooLarge, got {other:?}"),
+    }
+
+    let huge = RenderConfig {
+        logo_scale: 0.6,
+        ..large
+    };
+    let path = PathBuf::from("unused.png");
+    assert!(matches!(
+        render_to_png(DATA, &path, &huge),
+        Err(QrError::LogoTooLarge { .. })
+    ));
+    assert!(!path.exists());
+}
+
+#[test]
+fn test_knocked_out_logo_decodes() {
+    let logo = logo_file();
+    for (knockout, clear) in [
+        (LogoKnockout::Square, false),
+        (LogoKnockout::Rounded, true),
+        (LogoKnockout::Circle, false),
+        (LogoKnockout::None, true),
+    ] {
+        let config = RenderConfig {
+            logo_knockout: knockout,
+            logo_clear_modules: clear,
+            ..logo_config(&logo)
+        };
+        let temp_file = Builder::new().suffix(".png").tempfile().unwrap();
+        let path = temp_file.path().to_path_buf();
+        render_to_png(DATA, &path, &config).unwrap();
+
+        let decoded = decode(path.to_str().unwrap())
+            .unwrap_or_else(|e| panic!("{knockout:?} failed to decode: {e}"));
+        assert_eq!(decoded.content, DATA);
+
+        // The transparent half of the logo shows the knockout, not modules
+        if knockout != LogoKnockout::None {
+            let img = image::open(&path).unwrap().to_rgba8();
+            let center = img.width() / 2;
+            for x in center + img.width() / 40..center + img.width() / 12 {
+                assert_eq!(img.get_pixel(x, center).0, [255, 255, 255, 255]);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_vector_knockout_and_default_ec_level() {
+    let logo = logo_file();
+    let config = RenderConfig {
+        logo_knockout: LogoKnockout::Circle,
+        bg_color: Color::rgb(0xff, 0xee, 0xdd),
+        ..logo_config(&logo)
+    };
+    let svg = render_to_svg(DATA, &config).unwrap();
+    let knockout = svg.find(r##"fill="#ffeedd"/><image"##);
+    assert!(knockout.is_some(), "{svg}");
+
+    let pdf = cqr::pdf::render_to_pdf(DATA, &config).unwrap();
+    assert!(String::from_utf8_lossy(&pdf).contains("/Im0 Do"));
+
+    // A logo raises the default error correction, but an explicit level wins
+    let parse = |args: &[&str]| Cli::try_parse_from(args).unwrap().error_correction;
+    assert!(parse(&["cqr", "text", "hi", "--logo", "a.png"]).is_none());
+    assert!(parse(&["cqr", "text", "hi", "--logo", "a.png", "-e", "q"]).is_some());
+    assert!(Cli::try_parse_from(["cqr", "text", "hi", "--logo-knockout", "star"]).is_err());
+}
+
+#[test]
+fn test_transparent_background_knockout_in_svg_and_pdf() {
+    let logo = logo_file();
+    let config = RenderConfig {
+        logo_knockout: LogoKnockout::Square,
+        bg_color: Color::TRANSPARENT,
+        ..logo_config(&logo)
+    };
+
+    // The transparent half of the logo shows no modules
+    let svg = render_to_svg(DATA, &config).unwrap();
+    let tree = usvg::Tree::from_str(&svg, &usvg::Options::default()).unwrap();
+    let width = config.size;
+    let mut pixmap = tiny_skia::Pixmap::new(width, width).unwrap();
+    resvg::render(&tree, tiny_skia::Transform::default(), &mut pixmap.as_mut());
+    let center = width / 2;
+    for y in center - width / 12..center + width / 12 {
+        for x in center + width / 40..center + width / 12 {
+            assert_eq!(pixmap.pixel(x, y).unwrap().alpha(), 0, "({x}, {y})");
+        }
+    }
+
+    // PDF leaves out the same modules: one subpath each
+    let mut cleared = grid(&config);
+    let dark = cleared.dark_modules().count();
+    LogoLayout::new(&config, &grid(&config))
+        .unwrap()
+        .unwrap()
+        .clear_knockout(&mut cleared);
+    let removed = dark - cleared.dark_modules().count();
+    assert!(removed > 0);
+    let subpaths = |config: &RenderConfig| {
+        let pdf = cqr::pdf::render_to_pdf(DATA, config).unwrap();
+        String::from_utf8_lossy(&pdf).matches(" m\n").count()
+    };
+    let no_knockout = RenderConfig {
+        logo_knockout: LogoKnockout::None,
+        ..config.clone()
+    };
+    assert_eq!(subpaths(&no_knockout) - subpaths(&config), removed);
+}
+
+/// A 2:1 vector mark: a blue bar with a white dot in the middle
+fn svg_logo_file() -> NamedTempFile {
+    let file = Builder::new().suffix(".svg").tempfile().unwrap();
+    std::fs::write(
+        file.path(),
+        r##"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 100 50">
+            <defs><linearGradient id="grad"><stop stop-color="#1e88e5"/><stop offset="1" stop-color="#1e88e5"/></linearGradient></defs>
+            <rect width="100" height="50" fill="url(#grad)"/>
+            <circle cx="50" cy="25" r="10" fill="#fff"/>
+        </svg>"##,
+    )
+    .unwrap();
+    file
+}
+
+#[test]
+fn test_svg_logo_stays_vector_in_svg() {
+    let logo = svg_logo_file();
+    let svg = render_to_svg(DATA, &logo_config(&logo)).unwrap();
+
+    assert!(!svg.contains("data:image/png"));
+    assert!(svg.contains(r#"viewBox="0 0 100 50"><svg"#), "{svg}");
+    // The logo's ids can't clash with the code's own gradient
+    assert!(svg.contains(r#"id="logo-grad""#));
+    assert!(!svg.contains(r#"id="grad""#));
+
+    let invalid = Builder::new().suffix(".svg").tempfile().unwrap();
+    std::fs::write(invalid.path(), "<svg").unwrap();
+    assert!(render_to_svg(DATA, &logo_config(&invalid)).is_err());
+}
+
+#[test]
+fn test_svg_logo_rasterized_for_png_and_pdf() {
+    let logo = svg_logo_file();
+    let config = RenderConfig {
+        logo_knockout: LogoKnockout::Square,
+        ..logo_config(&logo)
+    };
+    let temp_file = Builder::new().suffix(".png").tempfile().unwrap();
+    let path = temp_file.path().to_path_buf();
+    render_to_png(DATA, &path, &config).unwrap();
+    assert_eq!(decode(path.to_str().unwrap()).unwrap().content, DATA);
+
+    // Aspect ratio kept: the bar is blue beside the dot, but the knockout
+    // shows above and below it
+    let img = image::open(&path).unwrap().to_rgba8();
+    let center = img.width() / 2;
+    let logo_size = img.width() / 5;
+    assert_eq!(img.get_pixel(center, center).0, [255, 255, 255, 255]);
+    assert_eq!(
+        img.get_pixel(center - logo_size * 2 / 5, center).0,
+        [0x1e, 0x88, 0xe5, 255]
+    );
+    assert_eq!(
+        img.get_pixel(center, center - logo_size * 2 / 5).0,
+        [255, 255, 255, 255]
+    );
+
+    // PDF embeds it rendered at 300 DPI: 20% of 50mm is 10mm, 119 pixels
+    let pdf = cqr::pdf::render_to_pdf(DATA, &config).unwrap();
+    let text = String::from_utf8_lossy(&pdf);
+    assert!(
+        text.contains("/Subtype /Image /Width 119 /Height 119"),
+        "{text}"
+    );
+}
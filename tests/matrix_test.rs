@@ -0,0 +1,163 @@
+use clap::Parser;
+use cqr::cli::{Cli, OutputFormat};
+use cqr::matrix::{read_matrix, render_matrix};
+use cqr::renderer::RenderConfig;
+use cqr::symbol::SymbolType;
+use cqr::verify::verify_output;
+use std::path::Path;
+use tempfile::Builder;
+
+fn text(data: &str, format: OutputFormat, config: &RenderConfig) -> String {
+    String::from_utf8(render_matrix(data, format, config).unwrap()).unwrap()
+}
+
+/// Write `data` as a matrix file and return it
+fn written(data: &str, format: OutputFormat, config: &RenderConfig) -> tempfile::NamedTempFile {
+    let file = Builder::new()
+        .suffix(&format!(".{}", format.extension()))
+        .tempfile()
+        .unwrap();
+    std::fs::write(file.path(), render_matrix(data, format, config).unwrap()).unwrap();
+    file
+}
+
+#[test]
+fn test_json_matches_symbol() {
+    let config = RenderConfig::default();
+    let json: serde_json::Value =
+        serde_json::from_str(&text("HELLO", OutputFormat::MatrixJson, &config)).unwrap();
+    assert_eq!(json["symbol"], "QR");
+    assert_eq!(json["version"], "1");
+    assert_eq!(json["error_correction"], "M");
+    assert_eq!(
+        (json["width"].as_u64(), json["height"].as_u64()),
+        (Some(21), Some(21))
+    );
+    assert_eq!(json["quiet_zone"], 0);
+
+    let grid = config.encode("HELLO").unwrap();
+    let modules: Vec<Vec<u8>> = serde_json::from_value(json["modules"].clone()).unwrap();
+    assert_eq!(modules.len(), 21);
+    for (y, row) in modules.iter().enumerate() {
+        for (x, &module) in row.iter().enumerate() {
+            assert_eq!(module == 1, grid.is_dark(x, y), "({x}, {y})");
+        }
+    }
+
+    // Symbols without error correction levels leave it out; rMQR is not square
+    let data_matrix = RenderConfig {
+        symbol: SymbolType::DataMatrix,
+        ..RenderConfig::default()
+    };
+    let json: serde_json::Value =
+        serde_json::from_str(&text("hi", OutputFormat::MatrixJson, &data_matrix)).unwrap();
+    assert!(json["error_correction"].is_null());
+    let rmqr = RenderConfig {
+        symbol: SymbolType::Rmqr,
+        ..RenderConfig::default()
+    };
+    let json: serde_json::Value =
+        serde_json::from_str(&text("hi", OutputFormat::MatrixJson, &rmqr)).unwrap();
+    assert!(json["width"].as_u64() > json["height"].as_u64());
+    assert_eq!(
+        json["modules"][0].as_array().unwrap().len() as u64,
+        json["width"]
+    );
+}
+
+#[test]
+fn test_quiet_zone_option() {
+    let bare = text("HELLO", OutputFormat::MatrixText, &RenderConfig::default());
+    assert_eq!(bare.lines().count(), 21);
+    assert!(bare.lines().all(|line| line.len() == 21));
+    assert!(bare.starts_with("1111111"));
+
+    let framed = RenderConfig {
+        matrix_quiet_zone: true,
+        quiet_zone: 4,
+        ..RenderConfig::default()
+    };
+    let text_grid = text("HELLO", OutputFormat::MatrixText, &framed);
+    let lines: Vec<&str> = text_grid.lines().collect();
+    assert_eq!(lines.len(), 29);
+    assert_eq!(lines[0], "0".repeat(29));
+    assert_eq!(&lines[4][4..25], bare.lines().next().unwrap());
+
+    let csv = text("HELLO", OutputFormat::MatrixCsv, &framed);
+    let first = csv.lines().nth(4).unwrap();
+    assert_eq!(first.split(',').count(), 29);
+    assert!(first.starts_with("0,0,0,0,1,1,1,1,1,1,1,0,"));
+    let json: serde_json::Value =
+        serde_json::from_str(&text("HELLO", OutputFormat::MatrixJson, &framed)).unwrap();
+    assert_eq!(
+        (json["width"].as_u64(), json["quiet_zone"].as_u64()),
+        (Some(29), Some(4))
+    );
+}
+
+#[test]
+fn test_netpbm_files() {
+    let config = RenderConfig::default();
+    let pbm = render_matrix("HELLO", OutputFormat::Pbm, &config).unwrap();
+    // 21 modules take three bytes a row
+    assert!(pbm.starts_with(b"P4\n21 21\n"));
+    assert_eq!(pbm.len(), b"P4\n21 21\n".len() + 21 * 3);
+    assert_eq!(&pbm[9..12], &[0xfe, 0x8b, 0xf8]);
+
+    let pgm = render_matrix("HELLO", OutputFormat::Pgm, &config).unwrap();
+    assert!(pgm.starts_with(b"P5\n21 21\n255\n"));
+    assert_eq!(&pgm[13..21], &[0, 0, 0, 0, 0, 0, 0, 255]);
+
+    for format in [OutputFormat::Pbm, OutputFormat::Pgm] {
+        let file = written("HELLO", format, &config);
+        let img = image::open(file.path()).unwrap().to_luma8();
+        assert_eq!(img.dimensions(), (21, 21));
+        assert_eq!(img.get_pixel(0, 0).0, [0]);
+        assert_eq!(img.get_pixel(7, 0).0, [255]);
+        verify_output("HELLO", file.path(), format, &config).unwrap();
+    }
+}
+
+#[test]
+fn test_format_selection_and_verify() {
+    for (name, format) in [
+        ("qr.json", OutputFormat::MatrixJson),
+        ("qr.CSV", OutputFormat::MatrixCsv),
+        ("qr.txt", OutputFormat::MatrixText),
+        ("qr.pbm", OutputFormat::Pbm),
+        ("qr.pgm", OutputFormat::Pgm),
+    ] {
+        assert_eq!(OutputFormat::from_extension(Path::new(name)), Some(format));
+        assert!(format.is_matrix() && !format.is_printed());
+    }
+    let cli = Cli::try_parse_from([
+        "cqr",
+        "text",
+        "hi",
+        "-F",
+        "matrix-csv",
+        "--matrix-quiet-zone",
+    ])
+    .unwrap();
+    assert_eq!(cli.output_format(), OutputFormat::MatrixCsv);
+    assert!(cli.matrix_quiet_zone);
+
+    let config = RenderConfig::default();
+    for format in [
+        OutputFormat::MatrixJson,
+        OutputFormat::MatrixCsv,
+        OutputFormat::MatrixText,
+    ] {
+        let file = written("https://example.com", format, &config);
+        assert_eq!(read_matrix(file.path(), format, &config).unwrap().len(), 25);
+        verify_output("https://example.com", file.path(), format, &config).unwrap();
+    }
+
+    // A damaged file no longer reads back
+    let file = written("HELLO", OutputFormat::MatrixText, &config);
+    let damaged = std::fs::read_to_string(file.path())
+        .unwrap()
+        .replace('1', "0");
+    std::fs::write(file.path(), damaged).unwrap();
+    assert!(verify_output("HELLO", file.path(), OutputFormat::MatrixText, &config).is_err());
+}
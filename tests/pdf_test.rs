@@ -0,0 +1,113 @@
+use cqr::batch::process_batch;
+use cqr::cli::OutputFormat;
+use cqr::gradient::Gradient;
+use cqr::pdf::{render_to_pdf, PageSize};
+use cqr::renderer::RenderConfig;
+use image::{ImageBuffer, Rgba};
+use tempfile::{tempdir, Builder};
+
+/// Check that every cross-reference entry points at its object header
+fn assert_valid_xref(pdf: &[u8]) {
+    let text = String::from_utf8_lossy(pdf);
+    let startxref: usize = text
+        .rsplit("startxref\n")
+        .next()
+        .and_then(|tail| tail.lines().next())
+        .and_then(|line| line.parse().ok())
+        .expect("startxref offset");
+    assert!(pdf[startxref..].starts_with(b"xref\n"));
+
+    let entries = text[startxref..].lines().skip(3);
+    for (i, line) in entries.take_while(|line| line.ends_with(" n ")).enumerate() {
+        let offset: usize = line[..10].parse().unwrap();
+        let header = format!("{} 0 obj", i + 1);
+        assert!(
+            pdf[offset..].starts_with(header.as_bytes()),
+            "object {}",
+            i + 1
+        );
+    }
+}
+
+#[test]
+fn test_render_to_pdf_structure() {
+    let pdf = render_to_pdf("Hello PDF", &RenderConfig::default()).expect("Failed to render PDF");
+
+    assert!(pdf.starts_with(b"%PDF-1.4"));
+    assert!(pdf.ends_with(b"%%EOF\n"));
+    assert_valid_xref(&pdf);
+
+    let text = String::from_utf8_lossy(&pdf);
+    // Default: 50mm code with 10mm margins on a fitted page (70mm = 198.425pt)
+    assert!(text.contains("/MediaBox [0 0 198.425 198.425]"));
+    // Vector paths, not an embedded raster
+    assert!(text.contains(" re f"));
+    assert!(text.contains(" m\n"));
+    assert!(!text.contains("/Subtype /Image"));
+}
+
+#[test]
+fn test_pdf_page_size_and_layout_errors() {
+    let config = RenderConfig {
+        page_size: PageSize::A4,
+        print_size_mm: Some(80.0),
+        ..RenderConfig::default()
+    };
+    let pdf = render_to_pdf("A4 page", &config).unwrap();
+    assert!(String::from_utf8_lossy(&pdf).contains("/MediaBox [0 0 595.276 841.89]"));
+
+    let too_big = RenderConfig {
+        page_size: PageSize::A5,
+        print_size_mm: Some(140.0),
+        page_margin_mm: 10.0,
+        ..RenderConfig::default()
+    };
+    assert!(render_to_pdf("Too big", &too_big).is_err());
+}
+
+#[test]
+fn test_pdf_gradient_and_logo() {
+    let logo_file = Builder::new().suffix(".png").tempfile().unwrap();
+    let logo: ImageBuffer<Rgba<u8>, Vec<u8>> = ImageBuffer::from_fn(16, 16, |x, _| {
+        Rgba([200, 0, 0, if x < 8 { 255 } else { 0 }])
+    });
+    logo.save(logo_file.path()).unwrap();
+
+    let config = RenderConfig {
+        gradient: Some(Gradient::parse("linear:0:#000@0,#1a237e@0.5,#4a148c@1").unwrap()),
+        logo: Some(logo_file.path().to_path_buf()),
+        ..RenderConfig::default()
+    };
+    let pdf = render_to_pdf("Gradient and logo", &config).unwrap();
+    assert_valid_xref(&pdf);
+
+    let text = String::from_utf8_lossy(&pdf);
+    assert!(text.contains("/ShadingType 2"));
+    assert!(text.contains("/FunctionType 3"));
+    assert!(text.contains("/Pattern cs /P0 scn"));
+    assert!(text.contains("/Subtype /Image /Width 16 /Height 16"));
+    assert!(text.contains("/SMask"));
+    assert!(text.contains("/Im0 Do"));
+}
+
+#[test]
+fn test_batch_generates_pdf() {
+    let dir = tempdir().unwrap();
+    let input = dir.path().join("codes.csv");
+    std::fs::write(&input, "filename,data\nfirst,Hello\nsecond,World\n").unwrap();
+    let output_dir = dir.path().join("out");
+
+    let report = process_batch(
+        &input,
+        &output_dir,
+        &OutputFormat::Pdf,
+        &RenderConfig::default(),
+        false,
+    )
+    .expect("Batch failed");
+
+    assert_eq!(report.generated, 2);
+    let pdf = std::fs::read(output_dir.join("first.pdf")).unwrap();
+    assert!(pdf.starts_with(b"%PDF-"));
+    assert!(output_dir.join("second.pdf").exists());
+}
@@ -0,0 +1,87 @@
+use cqr::cli::parse_print_size;
+use cqr::renderer::{encode_image, render_to_svg, RenderConfig, SizeMode};
+use image::ImageFormat;
+
+#[test]
+fn test_parse_print_size_units() {
+    assert!((parse_print_size("25mm").unwrap() - 25.0).abs() < 1e-9);
+    assert!((parse_print_size("25").unwrap() - 25.0).abs() < 1e-9);
+    assert!((parse_print_size("2.5cm").unwrap() - 25.0).abs() < 1e-9);
+    assert!((parse_print_size("1IN").unwrap() - 25.4).abs() < 1e-9);
+    assert!((parse_print_size("72pt").unwrap() - 25.4).abs() < 1e-9);
+
+    assert!(parse_print_size("25ft").is_err());
+    assert!(parse_print_size("mm").is_err());
+    assert!(parse_print_size("-5mm").is_err());
+}
+
+#[test]
+fn test_png_records_dpi() {
+    let config = RenderConfig {
+        print_size_mm: Some(25.0),
+        dpi: Some(600),
+        size_mode: SizeMode::Pad,
+        ..RenderConfig::default()
+    };
+    // 25mm at 600 DPI
+    assert_eq!(config.target_size(), 591);
+
+    let png = encode_image("Print me", ImageFormat::Png, &config).unwrap();
+    // pHYs follows IHDR: 600 DPI is 23622 pixels per metre in both axes
+    assert_eq!(&png[37..41], b"pHYs");
+    assert_eq!(&png[41..45], &23622u32.to_be_bytes());
+    assert_eq!(png[49], 1);
+
+    // The decoder checks chunk CRCs
+    let img = image::load_from_memory(&png).unwrap();
+    assert_eq!(img.width(), 591);
+
+    // JFIF density in inches
+    let jpeg = encode_image("Print me", ImageFormat::Jpeg, &config).unwrap();
+    assert_eq!(&jpeg[6..11], b"JFIF\0");
+    assert_eq!(jpeg[13], 1);
+    assert_eq!(&jpeg[14..18], &[2, 88, 2, 88]);
+}
+
+#[test]
+fn test_svg_physical_size() {
+    let config = RenderConfig {
+        print_size_mm: Some(25.0),
+        ..RenderConfig::default()
+    };
+    let svg = render_to_svg("Print me", &config).unwrap();
+    assert!(svg.contains(r#"viewBox="0 0 512 512" width="25mm" height="25mm""#));
+
+    let at_dpi = RenderConfig {
+        size: 300,
+        dpi: Some(300),
+        ..RenderConfig::default()
+    };
+    let svg = render_to_svg("Print me", &at_dpi).unwrap();
+    assert!(svg.contains(r#"width="25.4mm" height="25.4mm""#));
+}
+
+#[test]
+fn test_small_module_warning() {
+    let data = "https://example.com/a/rather/long/url/that/needs/a/bigger/symbol";
+    let tiny = RenderConfig {
+        print_size_mm: Some(10.0),
+        dpi: Some(300),
+        ..RenderConfig::default()
+    };
+    let warning = tiny.print_warning(data).unwrap().expect("should warn");
+    assert!(warning.contains("below the"), "{warning}");
+
+    let roomy = RenderConfig {
+        print_size_mm: Some(50.0),
+        dpi: Some(600),
+        ..RenderConfig::default()
+    };
+    assert!(roomy.print_warning(data).unwrap().is_none());
+
+    // Screen output has no physical size to check
+    assert!(RenderConfig::default()
+        .print_warning(data)
+        .unwrap()
+        .is_none());
+}
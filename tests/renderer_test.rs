@@ -1,4 +1,11 @@
-use cqr::renderer::{render_to_base64, render_to_svg, render_to_terminal, Color, RenderConfig};
+use cqr::decoder::decode;
+use cqr::error::QrError;
+use cqr::renderer::{
+    encode_image, render_to_base64, render_to_png, render_to_svg, render_to_terminal, Color,
+    PixelLayout, RenderConfig, SizeMode, SvgMode, MAX_IMAGE_SIZE,
+};
+use image::ImageFormat;
+use tempfile::Builder;
 
 #[test]
 fn test_render_to_svg() {
@@ -47,8 +54,18 @@ fn test_render_config_custom() {
     let config = RenderConfig {
         size: 300,
         quiet_zone: 4,
-        fg_color: Color { r: 255, g: 0, b: 0 }, // Red
-        bg_color: Color { r: 0, g: 0, b: 255 }, // Blue
+        fg_color: Color {
+            r: 255,
+            g: 0,
+            b: 0,
+            a: 255,
+        }, // Red
+        bg_color: Color {
+            r: 0,
+            g: 0,
+            b: 255,
+            a: 255,
+        }, // Blue
         ..RenderConfig::default()
     };
 
@@ -57,3 +74,181 @@ fn test_render_config_custom() {
     assert!(svg.contains("fill=\"#ff0000\"")); // Red foreground
     assert!(svg.contains("fill=\"#0000ff\"")); // Blue background
 }
+
+#[test]
+fn test_color_alpha_parsing() {
+    assert_eq!(
+        Color::from_hex("#11223344").unwrap(),
+        Color::rgba(0x11, 0x22, 0x33, 0x44)
+    );
+    assert_eq!(
+        Color::from_hex("#f008").unwrap(),
+        Color::rgba(255, 0, 0, 0x88)
+    );
+    assert_eq!(Color::from_hex("#000").unwrap().a, 255);
+    assert_eq!(Color::from_hex("transparent").unwrap(), Color::TRANSPARENT);
+    assert!(Color::from_hex("#12345").is_err());
+    assert!(Color::from_hex("#gggggg").is_err());
+}
+
+#[test]
+fn test_transparent_background() {
+    use base64::{engine::general_purpose::STANDARD, Engine};
+
+    let config = RenderConfig {
+        bg_color: Color::TRANSPARENT,
+        fg_color: Color::from_hex("#00000080").unwrap(),
+        ..RenderConfig::default()
+    };
+
+    let svg = render_to_svg("Hello Alpha", &config).expect("Failed to render SVG");
+    assert!(svg.contains(r##"<rect width="100%" height="100%" fill="#ffffff" fill-opacity="0"/>"##));
+    assert!(svg.contains(r##"fill="#000000" fill-opacity="0.502""##));
+
+    let b64 = render_to_base64("Hello Alpha", &config).expect("Failed to render Base64");
+    let png = STANDARD
+        .decode(b64.trim_start_matches("data:image/png;base64,"))
+        .unwrap();
+    let img = image::load_from_memory(&png).unwrap().to_rgba8();
+
+    // Quiet zone is fully transparent, dark modules keep their own alpha
+    assert_eq!(img.get_pixel(0, 0).0[3], 0);
+    assert!(img.pixels().any(|p| p.0 == [0, 0, 0, 128]));
+}
+
+#[test]
+fn test_translucent_foreground_blends_with_background() {
+    let fg = Color::rgba(0, 0, 0, 128);
+    let bg = Color::rgb(255, 255, 255);
+
+    assert_eq!(fg.over(bg), Color::rgb(127, 127, 127));
+    assert_eq!(fg.over(Color::TRANSPARENT), fg);
+}
+
+#[test]
+fn test_pixel_layout_modes() {
+    // 25 modules plus a 2-module quiet zone on each side
+    let layout = |size, module_size, size_mode| {
+        RenderConfig {
+            size,
+            module_size,
+            size_mode,
+            ..RenderConfig::default()
+        }
+        .pixel_layout(29)
+        .unwrap()
+    };
+
+    assert_eq!(
+        layout(512, None, SizeMode::Fit),
+        PixelLayout {
+            module_size: 17,
+            code_size: 493,
+            image_size: 493
+        }
+    );
+    assert_eq!(layout(512, None, SizeMode::Pad).image_size, 512);
+    assert_eq!(layout(512, None, SizeMode::Scale).module_size, 18);
+    assert_eq!(layout(512, Some(4), SizeMode::Pad).image_size, 116);
+
+    let too_small = RenderConfig {
+        size: 20,
+        ..RenderConfig::default()
+    };
+    let err = too_small.pixel_layout(29).unwrap_err().to_string();
+    assert!(err.contains("--size 29"), "{err}");
+    assert!(RenderConfig {
+        module_size: Some(0),
+        ..RenderConfig::default()
+    }
+    .pixel_layout(29)
+    .is_err());
+
+    // Oversized images are refused before anything is allocated
+    for (size, module_size) in [(512, Some(200_000_000)), (512, Some(600)), (20_000, None)] {
+        let err = RenderConfig {
+            size,
+            module_size,
+            ..RenderConfig::default()
+        }
+        .pixel_layout(29)
+        .unwrap_err();
+        assert!(matches!(err, QrError::InvalidSize(_)), "{err}");
+    }
+    assert_eq!(
+        layout(512, Some(MAX_IMAGE_SIZE / 29), SizeMode::Fit).code_size,
+        16_356
+    );
+}
+
+#[test]
+fn test_exact_size_modes_decode() {
+    let data = "https://example.com/exact";
+    for size_mode in [SizeMode::Pad, SizeMode::Scale] {
+        let config = RenderConfig {
+            size: 300,
+            size_mode,
+            ..RenderConfig::default()
+        };
+        let png = encode_image(data, ImageFormat::Png, &config).unwrap();
+        let img = image::load_from_memory(&png).unwrap();
+        assert_eq!((img.width(), img.height()), (300, 300));
+
+        let temp_file = Builder::new().suffix(".png").tempfile().unwrap();
+        let path = temp_file.path().to_path_buf();
+        render_to_png(data, &path, &config).unwrap();
+        let decoded = decode(path.to_str().unwrap()).expect("Failed to decode");
+        assert_eq!(decoded.content, data);
+    }
+}
+
+#[test]
+fn test_svg_fractional_module_size() {
+    // 21 modules plus quiet zone = 25, so each module is 12.4 units
+    let config = RenderConfig {
+        size: 310,
+        ..RenderConfig::default()
+    };
+    let svg = render_to_svg("Hi", &config).unwrap();
+
+    assert!(svg.contains(r#"viewBox="0 0 310 310" width="310" height="310""#));
+    assert!(svg.contains(r#"width="12.4" height="12.4""#));
+
+    let fixed = RenderConfig {
+        module_size: Some(10),
+        ..RenderConfig::default()
+    };
+    assert!(render_to_svg("Hi", &fixed)
+        .unwrap()
+        .contains(r#"width="250" height="250""#));
+}
+
+#[test]
+fn test_compact_svg_merges_modules() {
+    let data = "https://example.com/a/longer/url/to/get/a/bigger/symbol/for/compact/svg";
+    let legacy = render_to_svg(data, &RenderConfig::default()).unwrap();
+    let compact_config = RenderConfig {
+        svg_mode: SvgMode::Compact,
+        ..RenderConfig::default()
+    };
+    let compact = render_to_svg(data, &compact_config).unwrap();
+
+    // Background plus one path for all modules, eyes included
+    assert_eq!(compact.matches("<path ").count(), 1);
+    assert_eq!(compact.matches("<rect ").count(), 1);
+    assert!(compact.contains(r#"shape-rendering="crispEdges""#));
+    assert!(compact.len() * 3 < legacy.len());
+
+    // Styled eyes keep their own fills, merged across the three eyes
+    let styled = RenderConfig {
+        svg_mode: SvgMode::Compact,
+        module_style: cqr::shapes::ModuleStyle::Dots,
+        eye_outer_color: Some(Color::rgb(255, 0, 0)),
+        gradient: Some(cqr::gradient::Gradient::parse("radial:#000,#1a237e").unwrap()),
+        ..RenderConfig::default()
+    };
+    let svg = render_to_svg(data, &styled).unwrap();
+    assert_eq!(svg.matches("<path ").count(), 3);
+    assert_eq!(svg.matches(r#"fill="url(#grad)""#).count(), 2);
+    assert!(svg.contains(r##"fill="#ff0000" fill-rule="evenodd" shape-rendering="crispEdges""##));
+}
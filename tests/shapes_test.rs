@@ -0,0 +1,165 @@
+use cqr::decoder::decode;
+use cqr::renderer::{render_to_png, render_to_svg, Color, RenderConfig};
+use cqr::shapes::{Eye, EyeStyle, ModuleGrid, ModuleStyle, Neighbors};
+use qrcode::QrCode;
+use tempfile::Builder;
+
+const ALL_STYLES: [ModuleStyle; 5] = [
+    ModuleStyle::Square,
+    ModuleStyle::Dots,
+    ModuleStyle::Rounded,
+    ModuleStyle::Diamond,
+    ModuleStyle::Liquid,
+];
+
+#[test]
+fn test_every_module_style_decodes() {
+    let data = "https://example.com/module-style";
+
+    for style in ALL_STYLES {
+        let config = RenderConfig {
+            module_style: style,
+            ..RenderConfig::default()
+        };
+
+        let temp_file = Builder::new()
+            .suffix(".png")
+            .tempfile()
+            .expect("Failed to create temp file");
+        let path = temp_file.path().to_path_buf();
+
+        render_to_png(data, &path, &config).expect("Failed to render PNG");
+        let decoded = decode(path.to_str().unwrap())
+            .unwrap_or_else(|e| panic!("{style:?} failed to decode: {e}"));
+
+        assert_eq!(decoded.content, data, "{style:?}");
+    }
+}
+
+const ALL_EYES: [EyeStyle; 4] = [
+    EyeStyle::Square,
+    EyeStyle::Rounded,
+    EyeStyle::Circle,
+    EyeStyle::Leaf,
+];
+
+#[test]
+fn test_every_eye_style_decodes() {
+    let data = "https://example.com/eye-style";
+
+    for outer in ALL_EYES {
+        for inner in ALL_EYES {
+            let config = RenderConfig {
+                eye_outer_style: outer,
+                eye_inner_style: inner,
+                eye_outer_color: Some(Color::rgb(0x1a, 0x23, 0x7e)),
+                ..RenderConfig::default()
+            };
+
+            let temp_file = Builder::new()
+                .suffix(".png")
+                .tempfile()
+                .expect("Failed to create temp file");
+            let path = temp_file.path().to_path_buf();
+
+            render_to_png(data, &path, &config).expect("Failed to render PNG");
+            let decoded = decode(path.to_str().unwrap())
+                .unwrap_or_else(|e| panic!("{outer:?}/{inner:?} failed to decode: {e}"));
+
+            assert_eq!(decoded.content, data, "{outer:?}/{inner:?}");
+        }
+    }
+}
+
+#[test]
+fn test_svg_eye_colors() {
+    let config = RenderConfig {
+        eye_outer_style: EyeStyle::Circle,
+        eye_outer_color: Some(Color::rgb(255, 0, 0)),
+        eye_inner_color: Some(Color::rgb(0, 0, 255)),
+        ..RenderConfig::default()
+    };
+
+    let svg = render_to_svg("Hello Eyes", &config).expect("Failed to render SVG");
+
+    assert_eq!(
+        svg.matches("fill=\"#ff0000\" fill-rule=\"evenodd\"")
+            .count(),
+        3
+    );
+    assert_eq!(svg.matches("fill=\"#0000ff\"").count(), 3);
+}
+
+#[test]
+fn test_eye_ring_and_pupil_geometry() {
+    let center = 0.5;
+    let ring = 0.5 / 7.0;
+    let gap = 1.5 / 7.0;
+
+    for eye in Eye::ALL {
+        assert!(EyeStyle::Square.ring_contains(eye, ring, center));
+        assert!(!EyeStyle::Square.ring_contains(eye, gap, center));
+        assert!(!EyeStyle::Square.pupil_contains(eye, gap, center));
+        assert!(EyeStyle::Circle.pupil_contains(eye, center, center));
+    }
+}
+
+#[test]
+fn test_svg_uses_paths_for_shaped_modules() {
+    let config = RenderConfig {
+        module_style: ModuleStyle::Dots,
+        ..RenderConfig::default()
+    };
+
+    let svg = render_to_svg("Hello Dots", &config).expect("Failed to render SVG");
+
+    assert!(svg.contains("<path d=\"M"));
+    // Data modules are all dots, finder patterns are drawn as whole eyes
+    assert!(!svg.contains("<rect x="));
+    assert_eq!(svg.matches("fill-rule=\"evenodd\"").count(), 3);
+}
+
+#[test]
+fn test_liquid_rounds_only_free_corners() {
+    let isolated = Neighbors::default();
+    let joined = Neighbors {
+        right: true,
+        ..Neighbors::default()
+    };
+
+    // Isolated module is a circle: the corner is empty
+    assert!(!ModuleStyle::Liquid.contains(0.95, 0.05, isolated));
+    // With a dark neighbour to the right the shared corners stay square
+    assert!(ModuleStyle::Liquid.contains(0.95, 0.05, joined));
+    assert!(!ModuleStyle::Liquid.contains(0.05, 0.05, joined));
+}
+
+#[test]
+fn test_dark_regions_cover_every_module_once() {
+    let code = QrCode::new("https://example.com/regions").unwrap();
+    let grid = ModuleGrid::new(&code);
+
+    for include_finders in [true, false] {
+        let mut covered = vec![0u8; grid.width() * grid.width()];
+        for region in grid.dark_regions(include_finders) {
+            for y in region.y..region.y + region.height {
+                for x in region.x..region.x + region.width {
+                    covered[y * grid.width() + x] += 1;
+                }
+            }
+        }
+        for y in 0..grid.width() {
+            for x in 0..grid.width() {
+                let expected = grid.is_dark(x, y) && (include_finders || !grid.is_finder(x, y));
+                assert_eq!(
+                    covered[y * grid.width() + x],
+                    u8::from(expected),
+                    "({x}, {y})"
+                );
+            }
+        }
+    }
+
+    // Merging runs and blocks needs far fewer shapes than modules
+    assert!(grid.dark_regions(true).len() * 2 < grid.dark_modules().count());
+}
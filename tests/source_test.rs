@@ -0,0 +1,164 @@
+use clap::Parser;
+use cqr::cli::{parse_identifier, Cli, OutputFormat};
+use cqr::matrix::{pack, render_matrix, unpack, BitOrder, RowPadding};
+use cqr::renderer::RenderConfig;
+use cqr::verify::verify_output;
+use std::path::Path;
+use tempfile::Builder;
+
+fn source(format: OutputFormat, config: &RenderConfig) -> String {
+    String::from_utf8(render_matrix("HELLO", format, config).unwrap()).unwrap()
+}
+
+/// Ten modules: a dark one at each end
+fn ends() -> Vec<Vec<bool>> {
+    let mut row = vec![false; 10];
+    row[0] = true;
+    row[9] = true;
+    vec![row.clone(), row]
+}
+
+#[test]
+fn test_bit_packing() {
+    use BitOrder::{Lsb, Msb};
+    use RowPadding::{Byte, None};
+
+    assert_eq!(pack(&ends(), Msb, Byte), [0x80, 0x40, 0x80, 0x40]);
+    assert_eq!(pack(&ends(), Lsb, Byte), [0x01, 0x02, 0x01, 0x02]);
+    // Unpadded, the second row starts at bit 10
+    assert_eq!(pack(&ends(), Msb, None), [0x80, 0x60, 0x10]);
+    assert_eq!(pack(&ends(), Lsb, None), [0x01, 0x06, 0x08]);
+
+    for order in [Msb, Lsb] {
+        for padding in [Byte, None] {
+            let bytes = pack(&ends(), order, padding);
+            assert_eq!(unpack(&bytes, 10, 2, order, padding), Some(ends()));
+            assert_eq!(unpack(&bytes[1..], 10, 2, order, padding), Option::None);
+        }
+    }
+}
+
+#[test]
+fn test_c_header() {
+    let header = source(OutputFormat::CHeader, &RenderConfig::default());
+    assert!(header.starts_with(
+        "/* QR version 1, error correction M, 21x21 modules, MSB first, rows padded to whole bytes; set bits are dark */\n\
+         #ifndef QR_H\n#define QR_H\n\n#include <stdint.h>\n\n\
+         #define QR_WIDTH 21\n#define QR_HEIGHT 21\n#define QR_BYTES_PER_ROW 3\n\n\
+         static const uint8_t qr[63] = {\n    0xfe, 0x8b, 0xf8,\n"
+    ), "{header}");
+    assert!(header.ends_with("    0xfe, 0x90, 0x30,\n};\n\n#endif /* QR_H */\n"));
+
+    let unpadded = RenderConfig {
+        row_padding: RowPadding::None,
+        array_name: "door_code".to_string(),
+        ..RenderConfig::default()
+    };
+    let header = source(OutputFormat::CHeader, &unpadded);
+    // 441 modules fill 56 bytes, twelve to a line
+    assert!(
+        header.contains("#define DOOR_CODE_HEIGHT 21\n\nstatic const uint8_t door_code[56] = {\n")
+    );
+    assert!(!header.contains("BYTES_PER_ROW"));
+    assert_eq!(header.matches("0x").count(), 56);
+}
+
+#[test]
+fn test_rust_and_python_sources() {
+    let config = RenderConfig {
+        bit_order: BitOrder::Lsb,
+        ..RenderConfig::default()
+    };
+    let rust = source(OutputFormat::Rust, &config);
+    assert!(rust.starts_with("// QR version 1, error correction M, 21x21 modules, LSB first,"));
+    assert!(rust.contains("pub const QR_WIDTH: usize = 21;\n"));
+    assert!(rust.contains("#[rustfmt::skip]\npub const QR: [u8; 63] = [\n    0x7f, 0xd1, 0x1f,\n"));
+    assert!(rust.ends_with(",\n];\n"));
+
+    let python = source(OutputFormat::Python, &config);
+    assert!(python.contains("\nQR_BYTES_PER_ROW = 3\n\nQR = [\n    0x7f, 0xd1, 0x1f,\n"));
+    assert!(python.ends_with(",\n]\n"));
+
+    // Every packing reads back through --verify
+    for format in [
+        OutputFormat::CHeader,
+        OutputFormat::Rust,
+        OutputFormat::Python,
+    ] {
+        for (bit_order, row_padding) in [
+            (BitOrder::Msb, RowPadding::Byte),
+            (BitOrder::Lsb, RowPadding::None),
+        ] {
+            let config = RenderConfig {
+                bit_order,
+                row_padding,
+                matrix_quiet_zone: true,
+                ..RenderConfig::default()
+            };
+            let file = Builder::new()
+                .suffix(&format!(".{}", format.extension()))
+                .tempfile()
+                .unwrap();
+            let data = "https://example.com";
+            std::fs::write(file.path(), render_matrix(data, format, &config).unwrap()).unwrap();
+            verify_output(data, file.path(), format, &config).unwrap();
+        }
+    }
+}
+
+#[test]
+fn test_source_options() {
+    for (name, format) in [
+        ("qr.h", OutputFormat::CHeader),
+        ("qr.rs", OutputFormat::Rust),
+        ("qr.py", OutputFormat::Python),
+    ] {
+        assert_eq!(OutputFormat::from_extension(Path::new(name)), Some(format));
+        assert!(format.is_matrix());
+    }
+
+    let cli = Cli::try_parse_from(["cqr", "text", "hi", "-o", "code.h"]).unwrap();
+    assert_eq!(cli.output_format(), OutputFormat::CHeader);
+    assert_eq!(
+        (cli.bit_order, cli.row_padding, cli.array_name.as_str()),
+        (BitOrder::Msb, RowPadding::Byte, "qr")
+    );
+    let cli = Cli::try_parse_from([
+        "cqr",
+        "text",
+        "hi",
+        "-F",
+        "python",
+        "--bit-order",
+        "lsb",
+        "--row-padding",
+        "none",
+        "--array-name",
+        "_label2",
+    ])
+    .unwrap();
+    assert_eq!(cli.output_format(), OutputFormat::Python);
+    assert_eq!(
+        (cli.bit_order, cli.row_padding, cli.array_name.as_str()),
+        (BitOrder::Lsb, RowPadding::None, "_label2")
+    );
+
+    for bad in ["", "2fa", "door-code", "qr code"] {
+        assert!(parse_identifier(bad).is_err(), "{bad}");
+    }
+    // Reserved words of any of the three languages
+    for (keyword, language) in [
+        ("int", "C"),
+        ("static", "C"),
+        ("_Bool", "C"),
+        ("fn", "Rust"),
+        ("_", "Rust"),
+        ("class", "Python"),
+        ("None", "Python"),
+    ] {
+        let err = parse_identifier(keyword).unwrap_err();
+        assert!(err.ends_with(&format!("a {language} keyword")), "{err}");
+    }
+    assert!(parse_identifier("types").is_ok());
+    assert!(Cli::try_parse_from(["cqr", "text", "hi", "--array-name", "a.b"]).is_err());
+}
@@ -0,0 +1,163 @@
+use clap::Parser;
+use cqr::cli::Cli;
+use cqr::decoder::{decode, decode_all, DecodedQr};
+use cqr::error::QrError;
+use cqr::gradient::Gradient;
+use cqr::renderer::{render_to_png, RenderConfig};
+use cqr::structured::{
+    parity, part_config, part_path, reassemble, render_sheet_image, render_sheet_svg, split,
+    SetPosition,
+};
+use image::ImageFormat;
+use qrcode::EcLevel;
+use std::fmt::Write;
+use tempfile::{tempdir, Builder};
+
+/// Config blob too long for a version 40 code at level M
+fn long_payload() -> String {
+    (0..200).fold(String::new(), |mut blob, i| {
+        let _ = write!(blob, "key{i:03}=value-{:05};", i * 7919 % 100_000);
+        blob
+    })
+}
+
+#[test]
+fn test_split_fits_fewest_symbols() {
+    let data = long_payload();
+    assert!(qrcode::QrCode::with_error_correction_level(&data, EcLevel::M).is_err());
+
+    let parts = split(&data, EcLevel::M).unwrap();
+    assert_eq!(parts.len(), 2);
+    let joined: String = parts.iter().map(|(_, part)| *part).collect();
+    assert_eq!(joined, data);
+    for (i, (header, _)) in parts.iter().enumerate() {
+        assert_eq!(usize::from(header.index), i);
+        assert_eq!(header.total, 2);
+        assert_eq!(header.parity, parity(data.as_bytes()));
+    }
+    assert_eq!(split(&data, EcLevel::H).unwrap().len(), 3);
+
+    // Parts never break inside a character
+    let accented = "é".repeat(3000);
+    for (_, part) in split(&accented, EcLevel::M).unwrap() {
+        assert!(part.chars().all(|c| c == 'é'));
+    }
+    assert!(matches!(
+        split(&"x".repeat(50_000), EcLevel::M),
+        Err(QrError::QrGeneration(_))
+    ));
+}
+
+#[test]
+fn test_numbered_parts_decode_and_reassemble() {
+    let data = long_payload();
+    let dir = tempdir().unwrap();
+    let output = dir.path().join("config.png");
+    let mut decoded = Vec::new();
+    for (header, part) in split(&data, EcLevel::M).unwrap() {
+        let path = part_path(&output, header);
+        render_to_png(part, &path, &part_config(&RenderConfig::default(), header)).unwrap();
+        let result = decode(path.to_str().unwrap()).unwrap();
+        assert_eq!(result.content, part);
+        assert_eq!(result.structured_append, Some(header.position()));
+        decoded.push(result);
+    }
+    assert!(dir.path().join("config-1.png").exists());
+    assert!(dir.path().join("config-2.png").exists());
+
+    // Order of the images doesn't matter
+    decoded.reverse();
+    assert_eq!(reassemble(&decoded).unwrap(), data);
+}
+
+#[test]
+fn test_reassemble_checks_the_set() {
+    let data = long_payload();
+    let decoded: Vec<DecodedQr> = split(&data, EcLevel::H)
+        .unwrap()
+        .into_iter()
+        .map(|(header, part)| DecodedQr {
+            content: part.to_string(),
+            format: "qrcode".to_string(),
+            structured_append: Some(header.position()),
+        })
+        .collect();
+
+    let missing = reassemble(&decoded[..2]).unwrap_err();
+    assert!(
+        missing.to_string().contains("missing part 3 of 3"),
+        "{missing}"
+    );
+
+    // Reading a part twice is harmless, but two different first parts mean
+    // two sets were mixed up
+    let mut twice = decoded.clone();
+    twice.push(decoded[0].clone());
+    assert_eq!(reassemble(&twice).unwrap(), data);
+    let mut mixed = twice;
+    mixed[3].content.replace_range(0..1, "#");
+    let error = reassemble(&mixed).unwrap_err();
+    assert!(error.to_string().contains("more than one"), "{error}");
+
+    let mut resized = decoded;
+    resized[0].structured_append = Some(SetPosition { index: 0, total: 4 });
+    assert!(reassemble(&resized).is_err());
+    assert!(reassemble(&[]).is_err());
+}
+
+#[test]
+fn test_sheet_decodes_as_whole_payload() {
+    let data = long_payload();
+    let parts = split(&data, EcLevel::M).unwrap();
+    let config = RenderConfig::default();
+
+    let png = render_sheet_image(&parts, ImageFormat::Png, &config).unwrap();
+    let sheet = image::load_from_memory(&png).unwrap();
+    // Two codes side by side
+    assert_eq!(sheet.width(), 2 * sheet.height());
+    let file = Builder::new().suffix(".png").tempfile().unwrap();
+    std::fs::write(file.path(), png).unwrap();
+    let decoded = decode_all(file.path().to_str().unwrap()).unwrap();
+    assert_eq!(reassemble(&decoded).unwrap(), data);
+
+    // Three codes on a two by two grid, each with a gradient and a vector logo
+    let logo = Builder::new().suffix(".svg").tempfile().unwrap();
+    std::fs::write(
+        logo.path(),
+        r##"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 10 10">
+            <defs><linearGradient id="mark"><stop stop-color="#c00"/><stop offset="1" stop-color="#600"/></linearGradient></defs>
+            <rect width="10" height="10" fill="url(#mark)"/>
+        </svg>"##,
+    )
+    .unwrap();
+    let config = RenderConfig {
+        ec_level: EcLevel::H,
+        gradient: Some(Gradient::parse("linear:#000,#1a237e").unwrap()),
+        logo: Some(logo.path().to_path_buf()),
+        ..config
+    };
+    let parts = split(&data, EcLevel::H).unwrap();
+    let svg = render_sheet_svg(&parts, &config).unwrap();
+    // The sheet, then each part with its logo nested two levels deep
+    assert_eq!(svg.matches("<svg").count(), 10);
+    let ids: Vec<&str> = svg
+        .split(" id=\"")
+        .skip(1)
+        .map(|rest| &rest[..rest.find('"').unwrap()])
+        .collect();
+    assert_eq!(ids.len(), 6, "{ids:?}");
+    assert!(
+        ids.iter()
+            .all(|id| ids.iter().filter(|other| *other == id).count() == 1),
+        "{ids:?}"
+    );
+    assert!(svg.contains(r#"fill="url(#part2-grad)""#));
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("sheet.svg");
+    std::fs::write(&path, svg).unwrap();
+    cqr::verify::verify_sheet(&data, &path).unwrap();
+
+    let cli = Cli::try_parse_from(["cqr", "text", "x", "--structured-append", "--sheet"]);
+    assert!(cli.unwrap().sheet);
+    assert!(Cli::try_parse_from(["cqr", "text", "x", "--sheet"]).is_err());
+}
@@ -0,0 +1,123 @@
+use clap::Parser;
+use cqr::cli::Cli;
+use cqr::decoder::{decode, decode_svg};
+use cqr::error::QrError;
+use cqr::renderer::{render_to_png, render_to_svg, render_to_terminal, RenderConfig};
+use cqr::symbol::{encode, SymbolType};
+use qrcode::EcLevel;
+use tempfile::Builder;
+
+fn config(symbol: SymbolType) -> RenderConfig {
+    RenderConfig {
+        symbol,
+        ..RenderConfig::default()
+    }
+}
+
+#[test]
+fn test_rmqr_matches_reference_symbol() {
+    // R11x27-H "ABCDEF" from the ISO/IEC 23941 decoder test vectors
+    let expected = [
+        "XXXXXXX X X X X X X X X XXX",
+        "X     X  XX        X  X X X",
+        "X XXX X    X  XX X   X   XX",
+        "X XXX X XXXX XX X  XXXXXX  ",
+        "X XXX X  X X XX  XX   XXX X",
+        "X     X XXX  X XX  XXXX  X ",
+        "XXXXXXX     X   XX  X XXXXX",
+        "           X   X   X  X   X",
+        "XXXX  X   X X XX XXXXXX X X",
+        "X XX XXXXXX XXX  XXXX X   X",
+        "XXX X X X X X X X X X XXXXX",
+    ];
+    let grid = encode("ABCDEF", SymbolType::Rmqr, EcLevel::H).unwrap();
+    assert_eq!(SymbolType::Rmqr.version_name(&grid), "R11x27");
+    for (y, row) in expected.iter().enumerate() {
+        let actual: String = (0..grid.width())
+            .map(|x| if grid.is_dark(x, y) { 'X' } else { ' ' })
+            .collect();
+        assert_eq!(&actual, row, "row {y}");
+    }
+}
+
+#[test]
+fn test_micro_and_rmqr_decode() {
+    for (symbol, data, version) in [
+        (SymbolType::Micro, "12345", "M1"),
+        (SymbolType::Micro, "HELLO WORLD", "M3"),
+        (
+            SymbolType::Rmqr,
+            "https://example.com/label/00042",
+            "R15x43",
+        ),
+        (SymbolType::Rmqr, "0123456789", "R11x27"),
+    ] {
+        let config = RenderConfig {
+            ec_level: if version == "M1" {
+                EcLevel::L
+            } else {
+                EcLevel::M
+            },
+            ..config(symbol)
+        };
+        let grid = encode(data, symbol, config.ec_level).unwrap();
+        assert_eq!(symbol.version_name(&grid), version, "{data}");
+
+        let file = Builder::new().suffix(".png").tempfile().unwrap();
+        render_to_png(data, &file.path().to_path_buf(), &config).unwrap();
+        let decoded = decode(file.path().to_str().unwrap())
+            .unwrap_or_else(|e| panic!("{version} PNG failed to decode: {e}"));
+        assert_eq!(decoded.content, data);
+
+        let svg = render_to_svg(data, &config).unwrap();
+        assert_eq!(decode_svg(svg.as_bytes()).unwrap().content, data);
+    }
+}
+
+#[test]
+fn test_rectangular_output_dimensions() {
+    let data = "0123456789";
+    let config = config(SymbolType::Rmqr);
+    // R11x27 plus a two-module quiet zone: 31 x 15 modules
+    let file = Builder::new().suffix(".png").tempfile().unwrap();
+    render_to_png(data, &file.path().to_path_buf(), &config).unwrap();
+    let img = image::open(file.path()).unwrap();
+    assert_eq!((img.width(), img.height()), (31 * 16, 15 * 16));
+
+    let svg = render_to_svg(data, &config).unwrap();
+    assert!(svg.contains(r#"viewBox="0 0 512 247.74"#), "{svg}");
+
+    let eps = cqr::eps::render_to_eps(data, &config).unwrap();
+    assert!(eps.contains("%%BoundingBox: 0 0 142 69"), "{eps}");
+    let pdf = cqr::pdf::render_to_pdf(data, &config).unwrap();
+    assert!(String::from_utf8_lossy(&pdf).contains("/MediaBox"));
+
+    // Two module rows per terminal line, quiet zone included
+    let terminal = render_to_terminal(data, &config).unwrap();
+    assert_eq!(terminal.lines().count(), 8);
+    assert!(terminal.lines().all(|line| line.chars().count() == 31));
+}
+
+#[test]
+fn test_symbol_error_correction_limits() {
+    let micro_h = encode("1", SymbolType::Micro, EcLevel::H).unwrap_err();
+    assert!(micro_h.to_string().contains("not H"), "{micro_h}");
+    for level in [EcLevel::L, EcLevel::Q] {
+        assert!(matches!(
+            encode("1", SymbolType::Rmqr, level),
+            Err(QrError::QrGeneration(_))
+        ));
+    }
+
+    let too_long = encode(&"A".repeat(40), SymbolType::Micro, EcLevel::L).unwrap_err();
+    assert!(too_long.to_string().contains("--symbol rmqr"), "{too_long}");
+    assert!(encode(&"A".repeat(400), SymbolType::Rmqr, EcLevel::M).is_err());
+
+    let parse = |args: &[&str]| Cli::try_parse_from(args).unwrap().symbol;
+    assert_eq!(parse(&["cqr", "text", "hi"]), SymbolType::Qr);
+    assert_eq!(
+        parse(&["cqr", "text", "hi", "--symbol", "rmqr"]),
+        SymbolType::Rmqr
+    );
+    assert!(Cli::try_parse_from(["cqr", "text", "hi", "--symbol", "maxicode"]).is_err());
+}
@@ -0,0 +1,241 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+use clap::Parser;
+use cqr::cli::Cli;
+use cqr::decoder::decode;
+use cqr::renderer::{render_to_terminal, Color, RenderConfig};
+use cqr::shapes::ModuleGrid;
+use cqr::terminal::{
+    ansi256, iterm2, kitty, render_text, sixel, TerminalColor, TerminalGlyphs, TerminalGraphics,
+    TerminalPolarity,
+};
+use image::{Rgba, RgbaImage};
+use tempfile::Builder;
+
+const BLACK: Rgba<u8> = Rgba([0, 0, 0, 255]);
+const WHITE: Rgba<u8> = Rgba([255, 255, 255, 255]);
+
+fn detect(vars: &[(&str, &str)]) -> TerminalGraphics {
+    TerminalGraphics::detect_from(|name| {
+        vars.iter()
+            .find(|(key, _)| *key == name)
+            .map(|(_, value)| (*value).to_string())
+    })
+}
+
+#[test]
+fn test_protocol_detection() {
+    assert_eq!(
+        detect(&[("TERM", "xterm-kitty"), ("KITTY_WINDOW_ID", "1")]),
+        TerminalGraphics::Kitty
+    );
+    assert_eq!(
+        detect(&[("TERM_PROGRAM", "ghostty")]),
+        TerminalGraphics::Kitty
+    );
+    assert_eq!(
+        detect(&[("TERM", "xterm-256color"), ("TERM_PROGRAM", "iTerm.app")]),
+        TerminalGraphics::Iterm2
+    );
+    assert_eq!(
+        detect(&[("LC_TERMINAL", "iTerm2")]),
+        TerminalGraphics::Iterm2
+    );
+    assert_eq!(detect(&[("TERM", "foot")]), TerminalGraphics::Sixel);
+    assert_eq!(detect(&[("TERM", "mlterm")]), TerminalGraphics::Sixel);
+    assert_eq!(
+        detect(&[("TERM", "xterm-256color")]),
+        TerminalGraphics::Unicode
+    );
+    assert_eq!(detect(&[]), TerminalGraphics::Unicode);
+    // tmux doesn't pass images through
+    assert_eq!(
+        detect(&[
+            ("TMUX", "/tmp/tmux-1000/default,1,0"),
+            ("KITTY_WINDOW_ID", "1")
+        ]),
+        TerminalGraphics::Unicode
+    );
+}
+
+#[test]
+fn test_sixel_sequences() {
+    // One row: a black then a white pixel, each color on its own line
+    let pair = RgbaImage::from_fn(2, 1, |x, _| if x == 0 { BLACK } else { WHITE });
+    assert_eq!(
+        sixel(&pair),
+        "\x1bPq\"1;1;2;1#0;2;0;0;0#1;2;100;100;100#0@$#1?@-\x1b\\"
+    );
+
+    // Runs are compressed, and seven rows take a second band
+    let tall = RgbaImage::from_fn(10, 7, |_, y| if y < 6 { BLACK } else { WHITE });
+    assert_eq!(
+        sixel(&tall),
+        "\x1bPq\"1;1;10;7#0;2;0;0;0#1;2;100;100;100#0!10~-#1!10@-\x1b\\"
+    );
+
+    // Translucent pixels are blended over white
+    let faint = RgbaImage::from_pixel(1, 1, Rgba([0, 0, 0, 0]));
+    assert!(sixel(&faint).contains("#0;2;100;100;100"));
+
+    // Gradients are reduced to the 256 colors a palette holds
+    let gradient = RgbaImage::from_fn(64, 64, |x, y| {
+        Rgba([x.to_le_bytes()[0] * 4, y.to_le_bytes()[0] * 4, 128, 255])
+    });
+    let palette = sixel(&gradient).matches(";2;").count();
+    assert!(palette <= 256, "{palette} colors");
+}
+
+#[test]
+fn test_kitty_and_iterm2_sequences() {
+    assert_eq!(kitty(b"abc"), "\x1b_Ga=T,f=100,q=2,m=0;YWJj\x1b\\");
+    assert_eq!(
+        iterm2(b"abc", 50, 40),
+        "\x1b]1337;File=inline=1;size=3;width=50px;height=40px:YWJj\x07"
+    );
+
+    // 3100 bytes make 4136 base64 characters: a full chunk and a short one
+    let long = kitty(&[0; 3100]);
+    let chunks: Vec<&str> = long.split("\x1b\\").filter(|c| !c.is_empty()).collect();
+    assert_eq!(chunks.len(), 2);
+    assert!(chunks[0].starts_with("\x1b_Ga=T,f=100,q=2,m=1;"));
+    assert_eq!(chunks[0].len(), "\x1b_Ga=T,f=100,q=2,m=1;".len() + 4096);
+    assert_eq!(chunks[1], format!("\x1b_Gm=0;{}==", "A".repeat(38)));
+}
+
+#[test]
+fn test_terminal_images_decode() {
+    let config = RenderConfig {
+        terminal_graphics: TerminalGraphics::Kitty,
+        size: 200,
+        ..RenderConfig::default()
+    };
+    let out = render_to_terminal("https://example.com", &config).unwrap();
+    let payload = out
+        .strip_prefix("\x1b_Ga=T,f=100,q=2,m=0;")
+        .and_then(|rest| rest.strip_suffix("\x1b\\"))
+        .unwrap();
+    let file = Builder::new().suffix(".png").tempfile().unwrap();
+    std::fs::write(file.path(), STANDARD.decode(payload).unwrap()).unwrap();
+    assert_eq!(
+        decode(file.path().to_str().unwrap()).unwrap().content,
+        "https://example.com"
+    );
+
+    let iterm = RenderConfig {
+        terminal_graphics: TerminalGraphics::Iterm2,
+        ..config.clone()
+    };
+    let out = render_to_terminal("https://example.com", &iterm).unwrap();
+    assert!(out.starts_with("\x1b]1337;File=inline=1;"), "{out}");
+    let sixel_config = RenderConfig {
+        terminal_graphics: TerminalGraphics::Sixel,
+        ..config
+    };
+    let out = render_to_terminal("https://example.com", &sixel_config).unwrap();
+    assert!(out.starts_with("\x1bPq\"1;1;"));
+
+    let parse = |args: &[&str]| Cli::try_parse_from(args).unwrap().terminal_graphics;
+    assert_eq!(parse(&["cqr", "text", "hi"]), None);
+    assert_eq!(
+        parse(&["cqr", "text", "hi", "--terminal-graphics", "iterm2"]),
+        Some(TerminalGraphics::Iterm2)
+    );
+}
+
+/// Dark modules on the diagonal of a 2x2 grid
+fn diagonal() -> ModuleGrid {
+    ModuleGrid::from_modules(2, 2, vec![true, false, false, true], &[])
+}
+
+fn text(glyphs: TerminalGlyphs, polarity: TerminalPolarity) -> String {
+    let config = RenderConfig {
+        terminal_glyphs: glyphs,
+        terminal_polarity: polarity,
+        ..RenderConfig::default()
+    };
+    render_text(&diagonal(), 0, &config)
+}
+
+#[test]
+fn test_text_glyph_sets() {
+    use TerminalGlyphs::{Ascii, Braille, FullBlock, HalfBlock};
+    use TerminalPolarity::{Auto, Inverted, Normal};
+
+    assert_eq!(text(HalfBlock, Normal), "\u{2580}\u{2584}");
+    assert_eq!(
+        text(FullBlock, Normal),
+        "\u{2588}\u{2588}  \n  \u{2588}\u{2588}"
+    );
+    assert_eq!(text(Ascii, Normal), "##  \n  ##");
+    // Dots 1 and 5: top left and the one diagonally below it
+    assert_eq!(text(Braille, Normal), "\u{2811}");
+
+    // Plain blocks and Braille suit dark terminals; ASCII reads as printed
+    assert_eq!(text(HalfBlock, Auto), "\u{2584}\u{2580}");
+    assert_eq!(text(Braille, Auto), "\u{280a}");
+    assert_eq!(text(Ascii, Auto), text(Ascii, Normal));
+    assert_eq!(text(Ascii, Inverted), "  ##\n##  ");
+
+    // The quiet zone is light: ink when inverted
+    let config = RenderConfig {
+        terminal_glyphs: Ascii,
+        terminal_polarity: Inverted,
+        ..RenderConfig::default()
+    };
+    let framed = render_text(&diagonal(), 1, &config);
+    assert_eq!(framed.lines().next(), Some("########"));
+    assert_eq!(framed.lines().count(), 4);
+}
+
+#[test]
+fn test_text_colors() {
+    let config = RenderConfig {
+        terminal_glyphs: TerminalGlyphs::Ascii,
+        terminal_color: TerminalColor::Truecolor,
+        fg_color: Color::rgb(26, 35, 126),
+        bg_color: Color::rgb(255, 255, 255),
+        ..RenderConfig::default()
+    };
+    assert_eq!(
+        render_text(&diagonal(), 0, &config),
+        "\x1b[38;2;26;35;126;48;2;255;255;255m##  \x1b[0m\n\
+         \x1b[38;2;26;35;126;48;2;255;255;255m  ##\x1b[0m"
+    );
+
+    // Colored output defaults to normal polarity; inverted swaps the colors
+    let inverted = RenderConfig {
+        terminal_glyphs: TerminalGlyphs::HalfBlock,
+        terminal_color: TerminalColor::Ansi256,
+        terminal_polarity: TerminalPolarity::Inverted,
+        ..config.clone()
+    };
+    assert!(render_text(&diagonal(), 0, &inverted).starts_with("\x1b[38;5;231;48;5;18m"));
+
+    // A transparent background keeps the terminal's own
+    let transparent = RenderConfig {
+        bg_color: Color::TRANSPARENT,
+        ..config
+    };
+    assert!(render_text(&diagonal(), 0, &transparent).starts_with("\x1b[38;2;26;35;126;49m"));
+
+    assert_eq!(ansi256(Color::rgb(0, 0, 0)), 16);
+    assert_eq!(ansi256(Color::rgb(255, 255, 255)), 231);
+    assert_eq!(ansi256(Color::rgb(255, 0, 0)), 196);
+    assert_eq!(ansi256(Color::rgb(128, 128, 128)), 244);
+
+    let cli = Cli::try_parse_from([
+        "cqr",
+        "text",
+        "hi",
+        "--terminal-glyphs",
+        "braille",
+        "--terminal-color",
+        "ansi256",
+        "--terminal-polarity",
+        "normal",
+    ])
+    .unwrap();
+    assert_eq!(cli.terminal_glyphs, TerminalGlyphs::Braille);
+    assert_eq!(cli.terminal_color, TerminalColor::Ansi256);
+    assert_eq!(cli.terminal_polarity, TerminalPolarity::Normal);
+}
@@ -0,0 +1,188 @@
+use cqr::batch::process_batch;
+use cqr::cli::OutputFormat;
+use cqr::decoder::{decode, decode_svg};
+use cqr::embed::{
+    render_to_html_page, render_to_img_tag, render_to_inline_svg, render_to_markdown,
+};
+use cqr::error::QrError;
+use cqr::renderer::{render_to_base64, render_to_svg, render_to_svg_file, Color, RenderConfig};
+use cqr::verify::{check_decoded, check_verifiable, verify_output, verify_printed};
+use tempfile::{tempdir, Builder};
+
+const DATA: &str = "https://example.com/verify";
+
+#[test]
+fn test_every_format_verifies() {
+    let dir = tempdir().unwrap();
+    let config = RenderConfig {
+        frame: cqr::frame::FrameStyle::Rounded,
+        caption: Some("Verify me".to_string()),
+        ..RenderConfig::default()
+    };
+
+    for format in [
+        OutputFormat::Png,
+        OutputFormat::Jpeg,
+        OutputFormat::Webp,
+        OutputFormat::Svg,
+        OutputFormat::HtmlPage,
+    ] {
+        let path = dir.path().join(format!("code.{}", format.extension()));
+        match format {
+            OutputFormat::Svg => render_to_svg_file(DATA, &path, &config).unwrap(),
+            OutputFormat::HtmlPage => {
+                std::fs::write(&path, render_to_html_page(DATA, &config).unwrap()).unwrap();
+            }
+            raster => {
+                cqr::renderer::render_to_image(
+                    DATA,
+                    &path,
+                    raster.image_format().unwrap(),
+                    &config,
+                )
+                .unwrap();
+            }
+        }
+        verify_output(DATA, &path, format, &config)
+            .unwrap_or_else(|e| panic!("{format:?} failed to verify: {e}"));
+    }
+
+    // Printed output is checked as printed
+    for (format, text) in [
+        (OutputFormat::Base64, render_to_base64(DATA, &config)),
+        (OutputFormat::Html, render_to_img_tag(DATA, &config)),
+        (OutputFormat::HtmlSvg, render_to_inline_svg(DATA, &config)),
+        (OutputFormat::Markdown, render_to_markdown(DATA, &config)),
+    ] {
+        let text = text.unwrap();
+        verify_printed(DATA, &text, format, &config)
+            .unwrap_or_else(|e| panic!("{format:?} failed to verify: {e}"));
+        let other = if format == OutputFormat::HtmlSvg {
+            render_to_inline_svg("something else", &config)
+        } else {
+            render_to_base64("something else", &config)
+        };
+        assert!(verify_printed(DATA, &other.unwrap(), format, &config).is_err());
+    }
+}
+
+#[test]
+fn test_written_file_is_what_gets_checked() {
+    let dir = tempdir().unwrap();
+    let config = RenderConfig::default();
+
+    // A page whose inline SVG was spliced wrong fails, though the data renders fine
+    let path = dir.path().join("page.html");
+    let page = render_to_html_page(DATA, &config).unwrap();
+    std::fs::write(&path, page.replacen("<path", "<!-- <path", 1)).unwrap();
+    assert!(verify_output(DATA, &path, OutputFormat::HtmlPage, &config).is_err());
+    std::fs::write(&path, "<html></html>").unwrap();
+    assert!(verify_output(DATA, &path, OutputFormat::HtmlPage, &config).is_err());
+
+    // PDF, EPS and terminal output can't be read back, so --verify refuses them
+    for format in [OutputFormat::Pdf, OutputFormat::Eps, OutputFormat::Terminal] {
+        let err = check_verifiable(format).unwrap_err();
+        assert!(matches!(err, QrError::UnverifiableFormat(_)), "{err}");
+        assert!(verify_output(DATA, &dir.path().join("code"), format, &config).is_err());
+    }
+    assert!(check_verifiable(OutputFormat::Markdown).is_ok());
+    assert!(matches!(
+        process_batch(
+            &dir.path().join("missing.csv"),
+            &dir.path().join("out"),
+            &OutputFormat::Pdf,
+            &config,
+            true
+        ),
+        Err(QrError::UnverifiableFormat(_))
+    ));
+}
+
+#[test]
+fn test_svg_and_transparent_codes_decode() {
+    let transparent = RenderConfig {
+        bg_color: Color::TRANSPARENT,
+        ..RenderConfig::default()
+    };
+    let svg = render_to_svg(DATA, &transparent).unwrap();
+    assert_eq!(decode_svg(svg.as_bytes()).unwrap().content, DATA);
+
+    // Physical sizes are scaled up far enough to read
+    let printed = RenderConfig {
+        print_size_mm: Some(20.0),
+        ..transparent
+    };
+    let file = Builder::new().suffix(".svg").tempfile().unwrap();
+    render_to_svg_file(DATA, &file.path().to_path_buf(), &printed).unwrap();
+    assert_eq!(decode(file.path().to_str().unwrap()).unwrap().content, DATA);
+}
+
+#[test]
+fn test_unreadable_code_fails_verification() {
+    // Low contrast only draws a lint warning, so this renders but can't scan
+    let unreadable = RenderConfig {
+        fg_color: Color::rgb(40, 40, 40),
+        bg_color: Color::rgb(30, 30, 30),
+        ..RenderConfig::default()
+    };
+    let svg = render_to_svg(DATA, &unreadable).unwrap();
+    assert!(matches!(
+        check_decoded(DATA, decode_svg(svg.as_bytes())),
+        Err(QrError::VerificationFailed(_))
+    ));
+
+    let other = render_to_svg("something else", &RenderConfig::default()).unwrap();
+    let error = check_decoded(DATA, decode_svg(other.as_bytes())).unwrap_err();
+    assert!(error.to_string().contains("instead of"), "{error}");
+}
+
+#[test]
+fn test_batch_reports_each_record() {
+    let dir = tempdir().unwrap();
+    let input = dir.path().join("codes.csv");
+    std::fs::write(&input, "filename,data\nfirst,Hello\nsecond,World\n").unwrap();
+
+    let report = process_batch(
+        &input,
+        &dir.path().join("out"),
+        &OutputFormat::Png,
+        &RenderConfig::default(),
+        true,
+    )
+    .unwrap();
+    assert_eq!(report.generated, 2);
+    assert_eq!(report.checks.len(), 2);
+    assert_eq!(report.checks[1].filename, "second");
+    assert_eq!(report.failures().count(), 0);
+
+    let unreadable = RenderConfig {
+        fg_color: Color::rgb(40, 40, 40),
+        bg_color: Color::rgb(30, 30, 30),
+        ..RenderConfig::default()
+    };
+    let report = process_batch(
+        &input,
+        &dir.path().join("dark"),
+        &OutputFormat::Svg,
+        &unreadable,
+        true,
+    )
+    .unwrap();
+    assert_eq!(report.generated, 2);
+    assert_eq!(report.failures().count(), 2);
+    assert!(report.checks[0]
+        .error
+        .as_ref()
+        .unwrap()
+        .contains("read back"));
+
+    let unchecked = process_batch(
+        &input,
+        &dir.path().join("plain"),
+        &OutputFormat::Png,
+        &RenderConfig::default(),
+        false,
+    )
+    .unwrap();
+    assert!(unchecked.checks.is_empty());
+}